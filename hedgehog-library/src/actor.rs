@@ -1,23 +1,40 @@
-use crate::datasource::{DataProvider, NewFeedMetadata, QueryError};
+use crate::datasource::{DataProvider, DbResult, NewFeedMetadata, QueryError};
+use crate::feed_url;
+use crate::journal::{Change, Journal};
 use crate::model::{
-    Episode, EpisodeId, EpisodePlaybackData, EpisodeStatus, EpisodeSummary, EpisodeSummaryStatus,
-    EpisodesListMetadata, Feed, FeedId, FeedStatus, FeedSummary, GroupId, GroupSummary,
+    Bookmark, BookmarkId, Episode, EpisodeId, EpisodePlaybackData, EpisodeStatus, EpisodeSummary,
+    EpisodeSummaryStatus, EpisodesListMetadata, Feed, FeedError, FeedId, FeedStatus, FeedSummary,
+    GroupId, GroupSummary, ListeningSession, ListeningStats, TagTarget, TrashedFeed, ViewId,
+    ViewSummary,
 };
+use crate::reader::{DatabaseReader, ReadRequest};
 use crate::rss_client::{fetch_feed, WritableFeed};
 use crate::EpisodesQuery;
-use actix::dev::MessageResponse;
 use actix::fut::wrap_future;
 use actix::prelude::*;
+use chrono::{DateTime, Utc};
 use std::collections::{HashMap, HashSet};
 use std::ops::Range;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::Semaphore;
 
+/// How often the database is checked for changes made by other instances of the application.
+const EXTERNAL_CHANGES_INTERVAL: Duration = Duration::from_secs(1);
+
+/// For how long the notification about external changes may be postponed while another instance
+/// keeps modifying the database.
+const EXTERNAL_CHANGES_MAX_DELAY: Duration = Duration::from_secs(5);
+
 pub struct Library {
     data_provider: Box<dyn DataProvider>,
     updating_feeds: HashSet<FeedId>,
+    added_feeds: HashSet<FeedId>,
     feeds_semaphore: Arc<Semaphore>,
     update_listener: Option<Recipient<FeedUpdateNotification>>,
+    journal: Journal,
+    readers: Option<Addr<DatabaseReader>>,
+    external_changes_since: Option<Instant>,
 }
 
 impl Library {
@@ -25,18 +42,53 @@ impl Library {
         Library {
             data_provider: Box::new(data_provider),
             updating_feeds: HashSet::new(),
+            added_feeds: HashSet::new(),
             feeds_semaphore: Arc::new(Semaphore::new(8)),
             update_listener: None,
+            journal: Journal::default(),
+            readers: None,
+            external_changes_since: None,
+        }
+    }
+
+    /// Makes the library serve read-only requests through the readers instead of its own
+    /// connection. Changes are still made by the library one at a time.
+    pub fn with_readers(mut self, readers: Addr<DatabaseReader>) -> Self {
+        self.readers = Some(readers);
+        self
+    }
+
+    /// Runs the query on one of the readers if there are any, or on the library's own connection
+    /// otherwise.
+    fn read<R, F>(&mut self, query: F) -> ResponseActFuture<Self, DbResult<R>>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut dyn DataProvider) -> DbResult<R> + Send + 'static,
+    {
+        match &self.readers {
+            Some(readers) => Box::pin(
+                wrap_future(readers.send(ReadRequest::new(query)))
+                    .map(|result, _library: &mut Library, _ctx| {
+                        result.unwrap_or_else(|error| Err(error.into()))
+                    }),
+            ),
+            None => Box::pin(actix::fut::ready(query(&mut *self.data_provider))),
         }
     }
 }
 
 impl Actor for Library {
     type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(EXTERNAL_CHANGES_INTERVAL, |library, _ctx| {
+            library.check_external_changes();
+        });
+    }
 }
 
 #[derive(Message)]
-#[rtype(result = "Vec<EpisodeSummary>")]
+#[rtype(result = "DbResult<Vec<EpisodeSummary>>")]
 pub struct EpisodeSummariesRequest {
     pub query: EpisodesQuery,
     pub range: Range<usize>,
@@ -49,131 +101,195 @@ impl EpisodeSummariesRequest {
 }
 
 impl Handler<EpisodeSummariesRequest> for Library {
-    type Result = Vec<EpisodeSummary>;
+    type Result = ResponseActFuture<Self, DbResult<Vec<EpisodeSummary>>>;
 
     fn handle(&mut self, msg: EpisodeSummariesRequest, _ctx: &mut Self::Context) -> Self::Result {
-        let result = self
-            .data_provider
-            .get_episode_summaries(msg.query, msg.range);
-        match result {
-            Ok(summaries) => summaries,
-            Err(error) => {
-                log::error!(target: "sql", "cannot fetch episode summaries, {}", error);
-                Vec::new()
+        let EpisodeSummariesRequest { query, range } = msg;
+        let mut generation = None;
+        if let Some(cache) = self.data_provider.episodes_cache() {
+            if let Some(summaries) = cache.cached_episode_summaries(&query, &range) {
+                return Box::pin(actix::fut::ready(Ok(summaries)));
             }
+            generation = Some(cache.generation());
         }
+
+        let future = self.read({
+            let (query, range) = (query.clone(), range.clone());
+            move |data| data.get_episode_summaries(query, range)
+        });
+        Box::pin(future.map(move |result, library: &mut Library, _ctx| {
+            let summaries = result?;
+            if let (Some(cache), Some(generation)) =
+                (library.data_provider.episodes_cache(), generation)
+            {
+                cache.store_episode_summaries(generation, query, range, summaries.clone());
+            }
+            Ok(summaries)
+        }))
     }
 }
 
 #[derive(Message)]
-#[rtype(result = "EpisodesListMetadata")]
+#[rtype(result = "DbResult<EpisodesListMetadata>")]
 pub struct EpisodesListMetadataRequest(pub EpisodesQuery);
 
 impl Handler<EpisodesListMetadataRequest> for Library {
-    type Result = EpisodesListMetadata;
+    type Result = ResponseActFuture<Self, DbResult<EpisodesListMetadata>>;
 
     fn handle(
         &mut self,
         msg: EpisodesListMetadataRequest,
         _ctx: &mut Self::Context,
     ) -> Self::Result {
-        match self.data_provider.get_episodes_list_metadata(msg.0) {
-            Ok(result) => result,
-            Err(error) => {
-                log::error!(target: "sql", "cannot fetch episodes list metadata, {}", error);
-                EpisodesListMetadata::default()
+        let query = msg.0;
+        let mut generation = None;
+        if let Some(cache) = self.data_provider.episodes_cache() {
+            if let Some(metadata) = cache.cached_episodes_list_metadata(&query) {
+                return Box::pin(actix::fut::ready(Ok(metadata)));
             }
+            generation = Some(cache.generation());
         }
+
+        let future = self.read({
+            let query = query.clone();
+            move |data| data.get_episodes_list_metadata(query)
+        });
+        Box::pin(future.map(move |result, library: &mut Library, _ctx| {
+            let metadata = result?;
+            if let (Some(cache), Some(generation)) =
+                (library.data_provider.episodes_cache(), generation)
+            {
+                cache.store_episodes_list_metadata(generation, query, metadata.clone());
+            }
+            Ok(metadata)
+        }))
     }
 }
 
-#[derive(MessageResponse)]
+#[derive(Default)]
 pub struct FeedSummariesResponse {
     pub feeds: Vec<FeedSummary>,
     pub groups: Vec<GroupSummary>,
+    pub views: Vec<ViewSummary>,
+    pub tags: Vec<String>,
 }
 
 #[derive(Message)]
-#[rtype(result = "FeedSummariesResponse")]
+#[rtype(result = "DbResult<FeedSummariesResponse>")]
 pub struct FeedSummariesRequest;
 
 impl Handler<FeedSummariesRequest> for Library {
-    type Result = FeedSummariesResponse;
+    type Result = ResponseActFuture<Self, DbResult<FeedSummariesResponse>>;
 
     fn handle(&mut self, _msg: FeedSummariesRequest, _ctx: &mut Self::Context) -> Self::Result {
-        let feeds = self
-            .data_provider
-            .get_feed_summaries()
-            .unwrap_or_else(|error| {
-                log::error!(target: "sql", "cannot fetch feed summaries, {}", error);
-                Vec::new()
-            });
-        let groups = self
-            .data_provider
-            .get_group_summaries()
-            .unwrap_or_else(|error| {
-                log::error!(target: "sql", "cannot fetch group summaries, {}", error);
-                Vec::new()
-            });
-        FeedSummariesResponse { feeds, groups }
+        self.read(|data| {
+            Ok(FeedSummariesResponse {
+                feeds: data.get_feed_summaries()?,
+                groups: data.get_group_summaries()?,
+                views: data.get_view_summaries()?,
+                tags: data.get_tags()?,
+            })
+        })
     }
 }
 
 #[derive(Message)]
-#[rtype(result = "Option<EpisodePlaybackData>")]
+#[rtype(result = "DbResult<Option<EpisodePlaybackData>>")]
 pub struct EpisodePlaybackDataRequest(pub EpisodeId);
 
 impl Handler<EpisodePlaybackDataRequest> for Library {
-    type Result = Option<EpisodePlaybackData>;
+    type Result = ResponseActFuture<Self, DbResult<Option<EpisodePlaybackData>>>;
 
     fn handle(
         &mut self,
         msg: EpisodePlaybackDataRequest,
         _ctx: &mut Self::Context,
     ) -> Self::Result {
-        match self.data_provider.get_episode_playback_data(msg.0) {
-            Ok(result) => result,
-            Err(error) => {
-                log::error!(target: "sql", "cannot get episode playback data, {}", error);
-                None
-            }
-        }
+        self.read(move |data| data.get_episode_playback_data(msg.0))
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "DbResult<Option<EpisodeId>>")]
+pub struct NextEpisodeRequest(pub EpisodesQuery, pub EpisodeId);
+
+impl Handler<NextEpisodeRequest> for Library {
+    type Result = ResponseActFuture<Self, DbResult<Option<EpisodeId>>>;
+
+    fn handle(&mut self, msg: NextEpisodeRequest, _ctx: &mut Self::Context) -> Self::Result {
+        self.read(move |data| data.get_next_episode(msg.0, msg.1))
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "DbResult<Vec<EpisodeSummary>>")]
+pub struct QueueRequest;
+
+impl Handler<QueueRequest> for Library {
+    type Result = ResponseActFuture<Self, DbResult<Vec<EpisodeSummary>>>;
+
+    fn handle(&mut self, _msg: QueueRequest, _ctx: &mut Self::Context) -> Self::Result {
+        self.read(|data| data.get_queue())
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "DbResult<Vec<Bookmark>>")]
+pub struct BookmarksRequest;
+
+impl Handler<BookmarksRequest> for Library {
+    type Result = ResponseActFuture<Self, DbResult<Vec<Bookmark>>>;
+
+    fn handle(&mut self, _msg: BookmarksRequest, _ctx: &mut Self::Context) -> Self::Result {
+        self.read(|data| data.get_bookmarks())
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "DbResult<Vec<TrashedFeed>>")]
+pub struct TrashRequest;
+
+impl Handler<TrashRequest> for Library {
+    type Result = ResponseActFuture<Self, DbResult<Vec<TrashedFeed>>>;
+
+    fn handle(&mut self, _msg: TrashRequest, _ctx: &mut Self::Context) -> Self::Result {
+        self.read(|data| data.get_trashed_feeds())
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "DbResult<ListeningStats>")]
+pub struct ListeningStatsRequest;
+
+impl Handler<ListeningStatsRequest> for Library {
+    type Result = ResponseActFuture<Self, DbResult<ListeningStats>>;
+
+    fn handle(&mut self, _msg: ListeningStatsRequest, _ctx: &mut Self::Context) -> Self::Result {
+        self.read(|data| data.get_listening_stats())
     }
 }
 
 #[derive(Message)]
-#[rtype(result = "Option<Episode>")]
+#[rtype(result = "DbResult<Option<Episode>>")]
 pub struct EpisodeRequest(pub EpisodeId);
 
 impl Handler<EpisodeRequest> for Library {
-    type Result = Option<Episode>;
+    type Result = ResponseActFuture<Self, DbResult<Option<Episode>>>;
 
     fn handle(&mut self, msg: EpisodeRequest, _ctx: &mut Self::Context) -> Self::Result {
-        match self.data_provider.get_episode(msg.0) {
-            Ok(result) => result,
-            Err(error) => {
-                log::error!(target: "sql", "cannot fetch episode, {}", error);
-                None
-            }
-        }
+        self.read(move |data| data.get_episode(msg.0))
     }
 }
 
 #[derive(Message)]
-#[rtype(result = "Option<Feed>")]
+#[rtype(result = "DbResult<Option<Feed>>")]
 pub struct FeedRequest(pub FeedId);
 
 impl Handler<FeedRequest> for Library {
-    type Result = Option<Feed>;
+    type Result = ResponseActFuture<Self, DbResult<Option<Feed>>>;
 
     fn handle(&mut self, msg: FeedRequest, _ctx: &mut Self::Context) -> Self::Result {
-        match self.data_provider.get_feed(msg.0) {
-            Ok(result) => result,
-            Err(error) => {
-                log::error!(target: "sql", "cannot fetch feed, {}", error);
-                None
-            }
-        }
+        self.read(move |data| data.get_feed(msg.0))
     }
 }
 
@@ -187,6 +303,169 @@ impl Library {
         }
     }
 
+    /// Reports the feed's update as failed when its result could not be read or written, so
+    /// that the listeners waiting for the update to finish are not left hanging.
+    fn notify_update_failed(&mut self, feed_id: FeedId) {
+        self.notify_update_listener(FeedUpdateNotification::UpdateFinished(
+            feed_id,
+            FeedUpdateResult::StatusChanged(FeedStatus::Error(FeedError::Unknown)),
+        ));
+    }
+
+    /// Notifies the listener about the external changes once the database is not changed for
+    /// one check, or at most once in `EXTERNAL_CHANGES_MAX_DELAY` if it keeps changing.
+    fn check_external_changes(&mut self) {
+        let changed = match self.data_provider.check_external_changes() {
+            Ok(changed) => changed,
+            Err(error) => {
+                log::error!(target: "sql", "cannot check for changes, {}", error);
+                return;
+            }
+        };
+        let changed_since = match self.external_changes_since {
+            Some(changed_since) => changed_since,
+            None => {
+                if changed {
+                    self.external_changes_since = Some(Instant::now());
+                }
+                return;
+            }
+        };
+        if !changed || changed_since.elapsed() >= EXTERNAL_CHANGES_MAX_DELAY {
+            self.external_changes_since = None;
+            self.notify_update_listener(FeedUpdateNotification::DatabaseChanged);
+        }
+    }
+
+    fn notify_queue_updated(&mut self) {
+        match self.data_provider.get_queue() {
+            Ok(queue) => self.notify_update_listener(FeedUpdateNotification::QueueUpdated(queue)),
+            Err(error) => log::error!(target: "sql", "cannot fetch queue, {}", error),
+        }
+    }
+
+    fn notify_bookmarks_updated(&mut self) {
+        match self.data_provider.get_bookmarks() {
+            Ok(bookmarks) => {
+                self.notify_update_listener(FeedUpdateNotification::BookmarksUpdated(bookmarks));
+            }
+            Err(error) => log::error!(target: "sql", "cannot fetch bookmarks, {}", error),
+        }
+    }
+
+    fn apply_change(&mut self, change: Change) -> Result<(), QueryError> {
+        let updated_feeds = self.journal.apply(&mut *self.data_provider, change)?;
+        if !updated_feeds.is_empty() {
+            let new_episodes_count = self.data_provider.get_new_episodes_count(updated_feeds)?;
+            self.notify_update_listener(FeedUpdateNotification::NewCountUpdated(
+                new_episodes_count,
+            ));
+        }
+        Ok(())
+    }
+
+    fn notify_tags_updated(&mut self) {
+        match self.data_provider.get_tags() {
+            Ok(tags) => self.notify_update_listener(FeedUpdateNotification::TagsUpdated(tags)),
+            Err(error) => log::error!(target: "sql", "cannot fetch tags, {}", error),
+        }
+    }
+
+    fn warn_if_duplicate(&mut self, feed_id: FeedId) {
+        match self.data_provider.find_duplicate_feed(feed_id) {
+            Ok(Some(title)) => {
+                log::warn!("This podcast seems to be already subscribed as '{}'", title);
+            }
+            Ok(None) => {}
+            Err(error) => log::error!(target: "sql", "cannot look for duplicate feeds, {}", error),
+        }
+    }
+
+    fn write_feed(
+        &mut self,
+        feed_id: FeedId,
+        mut feed: impl WritableFeed,
+        relink_episodes: bool,
+    ) -> Result<FeedSummary, QueryError> {
+        let mut writer = self.data_provider.writer(feed_id)?;
+        let feed_metadata = feed.feed_metadata();
+        let mut feed_summary = FeedSummary::from_metadata(feed_id, &feed_metadata, 0);
+        writer.set_feed_metadata(&feed_metadata)?;
+        while let Some(episode_metadata) = feed.next_episode_metadata() {
+            if episode_metadata.block {
+                writer.delete_episode(episode_metadata.guid)?;
+            } else {
+                if relink_episodes {
+                    writer.relink_episode(&episode_metadata)?;
+                }
+                writer.set_episode_metadata(&episode_metadata)?;
+            }
+        }
+        writer.close()?;
+
+        let new_episodes_query = EpisodesQuery::default()
+            .feed_id(feed_id)
+            .status(EpisodeSummaryStatus::New);
+        feed_summary.new_count = self.data_provider.count_episodes(new_episodes_query)?;
+
+        let feed = self.data_provider.get_feed(feed_id)?;
+        if let Some((overriden, title)) = feed.and_then(|feed| {
+            let overridden = feed.title_overriden;
+            feed.title.map(|title| (overridden, title))
+        }) {
+            if overriden {
+                feed_summary.title = title;
+            }
+        }
+        Ok(feed_summary)
+    }
+
+    fn set_source(
+        &mut self,
+        feed_id: FeedId,
+        source: String,
+        ctx: &mut <Library as Actor>::Context,
+    ) {
+        if !self.updating_feeds.insert(feed_id) {
+            log::warn!("The feed is being updated, try again later");
+            return;
+        }
+        log::info!("Checking the new source");
+        let source = feed_url::normalize(&source);
+
+        let future = wrap_future(async move {
+            let result = fetch_feed(&source).await;
+            (source, result)
+        })
+        .map(move |(source, result), library: &mut Library, _ctx| {
+            library.updating_feeds.remove(&feed_id);
+            let feed = match result {
+                Ok(feed) => feed,
+                Err(error) => {
+                    log::error!(target: "networking", "Cannot use the new source, {}", error);
+                    return;
+                }
+            };
+            let result: Result<(), QueryError> = (|| {
+                if !library.data_provider.set_feed_source(feed_id, &source)? {
+                    log::error!("Another feed is already subscribed to this source");
+                    return Ok(());
+                }
+                let feed_summary = library.write_feed(feed_id, feed, true)?;
+                library.notify_update_listener(FeedUpdateNotification::UpdateFinished(
+                    feed_id,
+                    FeedUpdateResult::Updated(feed_summary),
+                ));
+                log::info!("The source of the feed has been changed");
+                Ok(())
+            })();
+            if let Err(error) = result {
+                log::error!(target: "sql", "cannot change source, {}", error);
+            }
+        });
+        ctx.spawn(future);
+    }
+
     fn schedule_update(
         &mut self,
         mut feeds: Vec<(FeedId, String)>,
@@ -209,44 +488,23 @@ impl Library {
             })
             .map(move |result, library: &mut Library, _ctx| {
                 library.updating_feeds.remove(&feed_id);
-                let result: Result<_, QueryError> = match result {
-                    Ok(mut feed) => (|| {
-                        let mut writer = library.data_provider.writer(feed_id)?;
-                        let feed_metadata = feed.feed_metadata();
-                        let mut feed_summary =
-                            FeedSummary::from_metadata(feed_id, &feed_metadata, 0);
-                        writer.set_feed_metadata(&feed_metadata)?;
-                        while let Some(episode_metadata) = feed.next_episode_metadata() {
-                            if episode_metadata.block {
-                                writer.delete_episode(episode_metadata.guid)?;
-                            } else {
-                                writer.set_episode_metadata(&episode_metadata)?;
+                let is_added = library.added_feeds.remove(&feed_id);
+                match result {
+                    Ok(feed) => match library.write_feed(feed_id, feed, false) {
+                        Ok(feed_summary) => {
+                            library.notify_update_listener(FeedUpdateNotification::UpdateFinished(
+                                feed_id,
+                                FeedUpdateResult::Updated(feed_summary),
+                            ));
+                            if is_added {
+                                library.warn_if_duplicate(feed_id);
                             }
                         }
-                        writer.close()?;
-
-                        let new_episodes_query = EpisodesQuery::default()
-                            .feed_id(feed_id)
-                            .status(EpisodeSummaryStatus::New);
-                        feed_summary.new_count =
-                            library.data_provider.count_episodes(new_episodes_query)?;
-
-                        let feed = library.data_provider.get_feed(feed_id)?;
-                        if let Some((overriden, title)) = feed.and_then(|feed| {
-                            let overridden = feed.title_overriden;
-                            feed.title.map(|title| (overridden, title))
-                        }) {
-                            if overriden {
-                                feed_summary.title = title;
-                            }
+                        Err(error) => {
+                            log::error!(target: "sql", "cannot update, {}", error);
+                            library.notify_update_failed(feed_id);
                         }
-
-                        library.notify_update_listener(FeedUpdateNotification::UpdateFinished(
-                            feed_id,
-                            FeedUpdateResult::Updated(feed_summary),
-                        ));
-                        Ok(())
-                    })(),
+                    },
                     Err(err) => {
                         log::error!(target: "networking", "{}", err);
                         let new_status = FeedStatus::Error(err.as_feed_error());
@@ -259,13 +517,8 @@ impl Library {
                             feed_id,
                             FeedUpdateResult::StatusChanged(new_status),
                         ));
-                        Ok(())
                     }
-                };
-
-                if let Err(error) = result {
-                    log::error!(target: "sql", "cannot update, {}", error);
-                };
+                }
             });
             ctx.spawn(future);
         }
@@ -286,7 +539,13 @@ pub enum FeedUpdateNotification {
     FeedAdded(FeedSummary),
     FeedDeleted(FeedId),
     GroupAdded(GroupSummary),
+    ViewAdded(ViewSummary),
     NewCountUpdated(HashMap<FeedId, usize>),
+    QueueUpdated(Vec<EpisodeSummary>),
+    BookmarksUpdated(Vec<Bookmark>),
+    TagsUpdated(Vec<String>),
+    ChangesReplayed,
+    DatabaseChanged,
 }
 
 #[derive(Debug)]
@@ -302,11 +561,20 @@ pub enum FeedUpdateRequest {
     Subscribe(Recipient<FeedUpdateNotification>),
     AddFeed(NewFeedMetadata),
     AddGroup(String),
+    AddView(String, EpisodesQuery),
     DeleteFeed(FeedId),
+    MergeFeed(FeedId, FeedId),
+    RestoreFeed(FeedId),
+    PurgeTrash(Option<DateTime<Utc>>),
     DeleteGroup(GroupId),
+    DeleteView(ViewId),
     SetGroupPosition(GroupId, usize),
+    SetGroupParent(GroupId, Option<GroupId>),
+    SetGroupCollapsed(Option<GroupId>, bool),
     RenameFeed(FeedId, String),
+    SetSource(FeedId, String),
     RenameGroup(GroupId, String),
+    RenameView(ViewId, String),
     Update(UpdateQuery),
     SetGroup(Option<GroupId>, FeedId),
     AddArchive(FeedId, String),
@@ -314,6 +582,20 @@ pub enum FeedUpdateRequest {
     SetHidden(EpisodesQuery, bool),
     SetFeedEnabled(FeedId, bool),
     ReverseFeedOrder(FeedId),
+    Enqueue(EpisodeId),
+    Dequeue(EpisodeId),
+    SetQueuePosition(EpisodeId, usize),
+    ClearQueue,
+    AddBookmark(EpisodeId, Duration, Option<String>),
+    DeleteBookmark(BookmarkId),
+    SetFeedPlaybackRate(FeedId, Option<f64>),
+    SetFeedSkipIntro(FeedId, Duration),
+    SetFeedSkipOutro(FeedId, Duration),
+    AddTag(TagTarget, String),
+    RemoveTag(TagTarget, String),
+    SaveListeningSession(ListeningSession),
+    Undo,
+    Redo,
 }
 
 impl Handler<FeedUpdateRequest> for Library {
@@ -323,10 +605,17 @@ impl Handler<FeedUpdateRequest> for Library {
         match msg {
             FeedUpdateRequest::Subscribe(recipient) => self.update_listener = Some(recipient),
             FeedUpdateRequest::Update(query) => {
+                let feed_id = match query {
+                    UpdateQuery::Single(feed_id) => Some(feed_id),
+                    UpdateQuery::All | UpdateQuery::Pending => None,
+                };
                 match self.data_provider.get_update_sources(query) {
                     Ok(sources) => self.schedule_update(sources, ctx),
                     Err(error) => {
                         log::error!(target: "sql", "cannot update, {}", error);
+                        if let Some(feed_id) = feed_id {
+                            self.notify_update_failed(feed_id);
+                        }
                     }
                 }
             }
@@ -348,6 +637,7 @@ impl Handler<FeedUpdateRequest> for Library {
                 };
 
                 let source = data.source.clone();
+                self.added_feeds.insert(feed_id);
                 self.notify_update_listener(FeedUpdateNotification::FeedAdded(
                     FeedSummary::new_created(feed_id, data),
                 ));
@@ -355,7 +645,14 @@ impl Handler<FeedUpdateRequest> for Library {
             }
             FeedUpdateRequest::AddGroup(name) => match self.data_provider.create_group(&name) {
                 Ok(Some(group_id)) => {
-                    let summary = GroupSummary { id: group_id, name };
+                    let summary = GroupSummary {
+                        id: group_id,
+                        name,
+                        parent_id: None,
+                        depth: 0,
+                        collapsed: false,
+                        new_count: 0,
+                    };
                     self.notify_update_listener(FeedUpdateNotification::GroupAdded(summary));
                 }
                 Ok(None) => {
@@ -365,52 +662,129 @@ impl Handler<FeedUpdateRequest> for Library {
                     log::error!(target: "sql", "cannot create group, {}", error);
                 }
             },
+            FeedUpdateRequest::AddView(name, query) => {
+                match self.data_provider.create_view(&name, &query) {
+                    Ok(Some(view_id)) => {
+                        let summary = ViewSummary {
+                            id: view_id,
+                            name,
+                            query,
+                        };
+                        self.notify_update_listener(FeedUpdateNotification::ViewAdded(summary));
+                    }
+                    Ok(None) => {
+                        log::warn!("The view with this name already exists");
+                    }
+                    Err(error) => {
+                        log::error!(target: "sql", "cannot create view, {}", error);
+                    }
+                }
+            }
             FeedUpdateRequest::DeleteFeed(feed_id) => {
                 match self.data_provider.delete_feed(feed_id) {
                     Ok(_) => {
                         self.notify_update_listener(FeedUpdateNotification::FeedDeleted(feed_id));
+                        self.notify_queue_updated();
+                        self.notify_bookmarks_updated();
                     }
                     Err(error) => {
                         log::error!(target: "sql", "cannot delete feed, {}", error);
                     }
                 }
             }
+            FeedUpdateRequest::MergeFeed(feed_id, target_id) => {
+                if feed_id == target_id {
+                    log::warn!("The feed cannot be merged into itself");
+                    return;
+                }
+                let result: Result<(), QueryError> = (|| {
+                    let matched = self.data_provider.merge_feed(feed_id, target_id)?;
+                    log::info!("{} episode(s) merged", matched);
+                    self.notify_update_listener(FeedUpdateNotification::FeedDeleted(feed_id));
+                    let new_episodes_count = self
+                        .data_provider
+                        .get_new_episodes_count(HashSet::from([target_id]))?;
+                    self.notify_update_listener(FeedUpdateNotification::NewCountUpdated(
+                        new_episodes_count,
+                    ));
+                    Ok(())
+                })();
+                match result {
+                    Ok(()) => {
+                        self.notify_queue_updated();
+                        self.notify_bookmarks_updated();
+                    }
+                    Err(error) => log::error!(target: "sql", "cannot merge feeds, {}", error),
+                }
+            }
+            FeedUpdateRequest::RestoreFeed(feed_id) => {
+                match self.data_provider.restore_feed(feed_id) {
+                    Ok(()) => {
+                        self.notify_queue_updated();
+                        self.notify_bookmarks_updated();
+                    }
+                    Err(error) => log::error!(target: "sql", "cannot restore feed, {}", error),
+                }
+            }
+            FeedUpdateRequest::PurgeTrash(deleted_before) => {
+                match self.data_provider.purge_trash(deleted_before) {
+                    Ok(0) => {}
+                    Ok(count) => log::info!("Removed {} feed(s) from the trash", count),
+                    Err(error) => log::error!(target: "sql", "cannot empty trash, {}", error),
+                }
+            }
             FeedUpdateRequest::DeleteGroup(group_id) => {
                 if let Err(error) = self.data_provider.delete_group(group_id) {
                     log::error!(target: "sql", "cannot delete group, {}", error);
                 }
             }
+            FeedUpdateRequest::DeleteView(view_id) => {
+                if let Err(error) = self.data_provider.delete_view(view_id) {
+                    log::error!(target: "sql", "cannot delete view, {}", error);
+                }
+            }
             FeedUpdateRequest::SetGroupPosition(group_id, position) => {
                 if let Err(error) = self.data_provider.set_group_position(group_id, position) {
                     log::error!(target: "sql", "cannot change group position, {}", error);
                 }
             }
+            FeedUpdateRequest::SetGroupParent(group_id, parent_id) => {
+                match self.data_provider.set_group_parent(group_id, parent_id) {
+                    Ok(true) => {}
+                    Ok(false) => log::warn!("A group cannot be placed inside its own subgroup"),
+                    Err(error) => {
+                        log::error!(target: "sql", "cannot change group parent, {}", error);
+                    }
+                }
+            }
+            FeedUpdateRequest::SetGroupCollapsed(group_id, collapsed) => {
+                if let Err(error) = self.data_provider.set_group_collapsed(group_id, collapsed) {
+                    log::error!(target: "sql", "cannot collapse group, {}", error);
+                }
+            }
             FeedUpdateRequest::RenameFeed(feed_id, name) => {
-                if let Err(error) = self.data_provider.rename_feed(feed_id, name) {
+                if let Err(error) = self.apply_change(Change::RenameFeed(feed_id, name)) {
                     log::error!(target: "sql", "cannot rename feed, {}", error);
                 }
             }
+            FeedUpdateRequest::SetSource(feed_id, source) => self.set_source(feed_id, source, ctx),
             FeedUpdateRequest::RenameGroup(group_id, name) => {
                 if let Err(error) = self.data_provider.rename_group(group_id, name) {
                     log::error!(target: "sql", "cannot rename group, {}", error);
                 }
             }
+            FeedUpdateRequest::RenameView(view_id, name) => {
+                if let Err(error) = self.data_provider.rename_view(view_id, name) {
+                    log::error!(target: "sql", "cannot rename view, {}", error);
+                }
+            }
             FeedUpdateRequest::SetStatus(query, status) => {
-                let result: Result<(), QueryError> = (|| {
-                    let updated_feeds = self.data_provider.set_episode_status(query, status)?;
-                    let new_episodes_count =
-                        self.data_provider.get_new_episodes_count(updated_feeds)?;
-                    self.notify_update_listener(FeedUpdateNotification::NewCountUpdated(
-                        new_episodes_count,
-                    ));
-                    Ok(())
-                })();
-                if let Err(error) = result {
+                if let Err(error) = self.apply_change(Change::SetStatus(query, status)) {
                     log::error!(target: "sql", "cannot update status, {}", error);
                 }
             }
             FeedUpdateRequest::SetHidden(query, hidden) => {
-                if let Err(error) = self.data_provider.set_episode_hidden(query, hidden) {
+                if let Err(error) = self.apply_change(Change::SetHidden(query, hidden)) {
                     log::error!(target: "sql", "cannot update hidden flag, {}", error);
                 }
             }
@@ -420,15 +794,104 @@ impl Handler<FeedUpdateRequest> for Library {
                 }
             }
             FeedUpdateRequest::ReverseFeedOrder(feed_id) => {
-                if let Err(error) = self.data_provider.reverse_feed_order(feed_id) {
+                if let Err(error) = self.apply_change(Change::ReverseFeedOrder(feed_id)) {
                     log::error!(target: "sql", "cannot reverse order, {}", error);
                 }
             }
             FeedUpdateRequest::SetGroup(group_id, feed_id) => {
-                if let Err(error) = self.data_provider.set_feed_for_group(group_id, feed_id) {
+                if let Err(error) = self.apply_change(Change::SetGroup(group_id, feed_id)) {
                     log::error!(target: "sql", "cannot assign group, {}", error);
                 }
             }
+            FeedUpdateRequest::Enqueue(episode_id) => {
+                match self.data_provider.add_to_queue(episode_id) {
+                    Ok(()) => self.notify_queue_updated(),
+                    Err(error) => log::error!(target: "sql", "cannot add to queue, {}", error),
+                }
+            }
+            FeedUpdateRequest::Dequeue(episode_id) => {
+                match self.data_provider.remove_from_queue(episode_id) {
+                    Ok(()) => self.notify_queue_updated(),
+                    Err(error) => {
+                        log::error!(target: "sql", "cannot remove from queue, {}", error);
+                    }
+                }
+            }
+            FeedUpdateRequest::SetQueuePosition(episode_id, position) => {
+                match self.data_provider.set_queue_position(episode_id, position) {
+                    Ok(()) => self.notify_queue_updated(),
+                    Err(error) => {
+                        log::error!(target: "sql", "cannot change queue position, {}", error);
+                    }
+                }
+            }
+            FeedUpdateRequest::ClearQueue => match self.data_provider.clear_queue() {
+                Ok(()) => self.notify_queue_updated(),
+                Err(error) => log::error!(target: "sql", "cannot clear queue, {}", error),
+            },
+            FeedUpdateRequest::AddBookmark(episode_id, position, note) => {
+                let result = self
+                    .data_provider
+                    .create_bookmark(episode_id, position, note.as_deref());
+                match result {
+                    Ok(_) => self.notify_bookmarks_updated(),
+                    Err(error) => log::error!(target: "sql", "cannot add bookmark, {}", error),
+                }
+            }
+            FeedUpdateRequest::DeleteBookmark(bookmark_id) => {
+                match self.data_provider.delete_bookmark(bookmark_id) {
+                    Ok(()) => self.notify_bookmarks_updated(),
+                    Err(error) => log::error!(target: "sql", "cannot delete bookmark, {}", error),
+                }
+            }
+            FeedUpdateRequest::SetFeedPlaybackRate(feed_id, rate) => {
+                if let Err(error) = self.data_provider.set_feed_playback_rate(feed_id, rate) {
+                    log::error!(target: "sql", "cannot update playback rate, {}", error);
+                }
+            }
+            FeedUpdateRequest::SetFeedSkipIntro(feed_id, duration) => {
+                if let Err(error) = self.data_provider.set_feed_skip_intro(feed_id, duration) {
+                    log::error!(target: "sql", "cannot update intro duration, {}", error);
+                }
+            }
+            FeedUpdateRequest::SetFeedSkipOutro(feed_id, duration) => {
+                if let Err(error) = self.data_provider.set_feed_skip_outro(feed_id, duration) {
+                    log::error!(target: "sql", "cannot update outro duration, {}", error);
+                }
+            }
+            FeedUpdateRequest::AddTag(target, name) => {
+                match self.data_provider.add_tag(target, &name) {
+                    Ok(()) => self.notify_tags_updated(),
+                    Err(error) => log::error!(target: "sql", "cannot add tag, {}", error),
+                }
+            }
+            FeedUpdateRequest::RemoveTag(target, name) => {
+                match self.data_provider.remove_tag(target, &name) {
+                    Ok(()) => self.notify_tags_updated(),
+                    Err(error) => log::error!(target: "sql", "cannot remove tag, {}", error),
+                }
+            }
+            FeedUpdateRequest::SaveListeningSession(session) => {
+                if let Err(error) = self.data_provider.save_listening_session(&session) {
+                    log::error!(target: "sql", "cannot save listening session, {}", error);
+                }
+            }
+            FeedUpdateRequest::Undo => match self.journal.undo(&mut *self.data_provider) {
+                Ok(Some(change)) => {
+                    log::info!("Undone {}", change);
+                    self.notify_update_listener(FeedUpdateNotification::ChangesReplayed);
+                }
+                Ok(None) => log::warn!("Nothing to undo"),
+                Err(error) => log::error!(target: "sql", "cannot undo, {}", error),
+            },
+            FeedUpdateRequest::Redo => match self.journal.redo(&mut *self.data_provider) {
+                Ok(Some(change)) => {
+                    log::info!("Redone {}", change);
+                    self.notify_update_listener(FeedUpdateNotification::ChangesReplayed);
+                }
+                Ok(None) => log::warn!("Nothing to redo"),
+                Err(error) => log::error!(target: "sql", "cannot redo, {}", error),
+            },
         }
     }
 }
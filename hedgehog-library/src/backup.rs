@@ -0,0 +1,921 @@
+//! Backups of the whole library as JSON documents.
+//!
+//! Unlike OPML, a backup keeps everything needed to move the library to another machine: groups,
+//! feeds with their settings and tags, episodes with their status, playback position, hidden flag
+//! and tags, the queue, bookmarks, listening sessions, and saved views. The document is an object
+//! with the following fields:
+//!
+//! * `version` — the version of the format, currently `1`;
+//! * `groups` — groups in the order of the groups tree, every group refers to its parent by name;
+//! * `feeds` — feeds along with their episodes, a feed refers to its group by name;
+//! * `views` — saved views, which refer to feeds by their source URL and to groups by name.
+//!
+//! Durations and positions are written in seconds, dates are RFC 3339 strings. Feeds and episodes
+//! are identified by the source URL and GUID, so restoring a backup merges it into the library:
+//! missing groups, feeds, episodes, bookmarks, and views are added, while existing ones keep
+//! their settings unless the backup has more progress for an episode. The whole backup is
+//! checked before anything is written, so an invalid entry doesn't leave it half-restored.
+
+use crate::datasource::DataProvider;
+use crate::feed_url;
+use crate::metadata::EpisodeMetadata;
+use crate::model::{
+    EpisodeId, EpisodeSnapshot, EpisodeStatus, FeedId, FeedSnapshot, GroupId, ListeningSession,
+    TagTarget,
+};
+use crate::{EpisodesQuery, NewFeedMetadata, QueryError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::time::Duration;
+
+/// The version of the backups written by `build_backup`. Backups of newer versions are rejected.
+pub const BACKUP_VERSION: u32 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Db(#[from] QueryError),
+
+    #[error("The file is not a valid backup, {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("The file is not a backup of the library")]
+    NotABackup,
+
+    #[error("The backup was written by a newer version of Hedgehog (backup version: {version}, supported: {supported})")]
+    UnsupportedVersion { version: u64, supported: u32 },
+
+    #[error("The backup has an invalid entry, {0}")]
+    InvalidEntry(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Backup {
+    version: u32,
+    #[serde(default)]
+    groups: Vec<GroupEntry>,
+    #[serde(default)]
+    feeds: Vec<FeedEntry>,
+    #[serde(default)]
+    views: Vec<ViewEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GroupEntry {
+    name: String,
+    #[serde(default)]
+    parent: Option<String>,
+    #[serde(default)]
+    collapsed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FeedEntry {
+    source: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    title_override: Option<String>,
+    #[serde(default)]
+    link: Option<String>,
+    #[serde(default)]
+    group: Option<String>,
+    #[serde(default = "default_true")]
+    enabled: bool,
+    #[serde(default)]
+    reversed: bool,
+    #[serde(default)]
+    playback_rate: Option<f64>,
+    #[serde(default, with = "seconds")]
+    skip_intro: Duration,
+    #[serde(default, with = "seconds")]
+    skip_outro: Duration,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    episodes: Vec<EpisodeEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum StatusEntry {
+    New,
+    Seen,
+    Finished,
+    Started,
+    Error,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EpisodeEntry {
+    guid: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    link: Option<String>,
+    #[serde(default, with = "optional_seconds")]
+    duration: Option<Duration>,
+    #[serde(default, with = "optional_date")]
+    publication_date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    episode_number: Option<i64>,
+    #[serde(default)]
+    season_number: Option<i64>,
+    media_url: String,
+    status: StatusEntry,
+    #[serde(default, with = "seconds")]
+    position: Duration,
+    #[serde(default)]
+    hidden: bool,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    queue_position: Option<usize>,
+    #[serde(default)]
+    bookmarks: Vec<BookmarkEntry>,
+    #[serde(default)]
+    sessions: Vec<SessionEntry>,
+}
+
+impl EpisodeEntry {
+    fn status(&self) -> EpisodeStatus {
+        match self.status {
+            StatusEntry::New => EpisodeStatus::New,
+            StatusEntry::Seen => EpisodeStatus::NotStarted,
+            StatusEntry::Finished => EpisodeStatus::Finished,
+            StatusEntry::Started => EpisodeStatus::Started(self.position),
+            StatusEntry::Error => EpisodeStatus::Error(self.position),
+        }
+    }
+
+    fn metadata(&self) -> EpisodeMetadata<'_> {
+        EpisodeMetadata {
+            title: self.title.as_deref(),
+            description: self.description.as_deref(),
+            link: self.link.as_deref(),
+            guid: &self.guid,
+            duration: self.duration,
+            publication_date: self.publication_date,
+            episode_number: self.episode_number,
+            season_number: self.season_number,
+            media_url: &self.media_url,
+            block: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BookmarkEntry {
+    #[serde(with = "seconds")]
+    position: Duration,
+    #[serde(default)]
+    note: Option<String>,
+    #[serde(with = "date")]
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SessionEntry {
+    #[serde(with = "seconds")]
+    start_position: Duration,
+    #[serde(with = "seconds")]
+    end_position: Duration,
+    #[serde(with = "date")]
+    started_at: DateTime<Utc>,
+    #[serde(with = "date")]
+    ended_at: DateTime<Utc>,
+    rate: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ViewEntry {
+    name: String,
+    #[serde(default)]
+    feed: Option<String>,
+    #[serde(default)]
+    group: Option<String>,
+    query: EpisodesQuery,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn is_valid_rate(rate: f64) -> bool {
+    rate.is_finite() && rate > 0.0
+}
+
+impl Backup {
+    /// Checks the entries that cannot be written to the library as they are.
+    fn validate(&self) -> Result<(), Error> {
+        let invalid = |message: String| Err(Error::InvalidEntry(message));
+        for group in &self.groups {
+            if group.name.trim().is_empty() {
+                return invalid("a group has no name".to_string());
+            }
+        }
+        for feed in &self.feeds {
+            if !feed_url::is_valid(&feed.source) {
+                return invalid(format!("{} is not a feed URL", feed.source));
+            }
+            if !feed.playback_rate.map_or(true, is_valid_rate) {
+                return invalid(format!("{} has an invalid playback rate", feed.source));
+            }
+            for episode in &feed.episodes {
+                if episode.guid.is_empty() {
+                    return invalid(format!("an episode of {} has no GUID", feed.source));
+                }
+                for session in &episode.sessions {
+                    if !is_valid_rate(session.rate) || session.ended_at < session.started_at {
+                        return invalid(format!(
+                            "episode {} of {} has an invalid listening session",
+                            episode.guid, feed.source
+                        ));
+                    }
+                }
+            }
+        }
+        for view in &self.views {
+            if view.name.trim().is_empty() {
+                return invalid("a view has no name".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+mod seconds {
+    use serde::de::{self, Unexpected};
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    /// Converts the number of seconds to a duration, negative numbers are treated as zero.
+    /// Rejects the numbers that don't fit into a duration instead of panicking on them.
+    pub(super) fn from_f64<E: de::Error>(seconds: f64) -> Result<Duration, E> {
+        if !seconds.is_finite() || seconds >= u64::MAX as f64 {
+            return Err(E::invalid_value(
+                Unexpected::Float(seconds),
+                &"a number of seconds",
+            ));
+        }
+        Ok(Duration::from_secs_f64(seconds.max(0.0)))
+    }
+
+    pub(super) fn serialize<S: Serializer>(
+        value: &Duration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(value.as_secs_f64())
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Duration, D::Error> {
+        from_f64(f64::deserialize(deserializer)?)
+    }
+}
+
+mod optional_seconds {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub(super) fn serialize<S: Serializer>(
+        value: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.serialize_some(&value.as_secs_f64()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        let seconds = Option::<f64>::deserialize(deserializer)?;
+        seconds.map(super::seconds::from_f64).transpose()
+    }
+}
+
+mod date {
+    use chrono::{DateTime, Utc};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        value: &DateTime<Utc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_rfc3339())
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Utc>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        DateTime::parse_from_rfc3339(&value)
+            .map(|date| date.with_timezone(&Utc))
+            .map_err(de::Error::custom)
+    }
+}
+
+mod optional_date {
+    use chrono::{DateTime, Utc};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        value: &Option<DateTime<Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.serialize_some(&value.to_rfc3339()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<Utc>>, D::Error> {
+        let value = Option::<String>::deserialize(deserializer)?;
+        value
+            .map(|value| DateTime::parse_from_rfc3339(&value))
+            .transpose()
+            .map(|date| date.map(|date| date.with_timezone(&Utc)))
+            .map_err(de::Error::custom)
+    }
+}
+
+pub fn build_backup<W: io::Write, D: DataProvider>(write: W, data: &mut D) -> Result<(), Error> {
+    let groups = data.get_group_summaries()?;
+    let group_names: HashMap<GroupId, String> = (groups.iter())
+        .map(|group| (group.id, group.name.clone()))
+        .collect();
+    let feeds = data.get_feed_snapshots()?;
+    let feed_sources: HashMap<FeedId, String> = (feeds.iter())
+        .map(|feed| (feed.id, feed.source.clone()))
+        .collect();
+
+    let queue: HashMap<EpisodeId, usize> = (data.get_queue()?.into_iter())
+        .enumerate()
+        .map(|(index, episode)| (episode.id, index + 1))
+        .collect();
+    let mut bookmarks: HashMap<EpisodeId, Vec<BookmarkEntry>> = HashMap::new();
+    for bookmark in data.get_bookmarks()? {
+        bookmarks
+            .entry(bookmark.episode_id)
+            .or_default()
+            .push(BookmarkEntry {
+                position: bookmark.position,
+                note: bookmark.note,
+                created_at: bookmark.created_at,
+            });
+    }
+    let mut sessions: HashMap<EpisodeId, Vec<SessionEntry>> = HashMap::new();
+    for session in data.get_listening_sessions()? {
+        sessions
+            .entry(session.episode_id)
+            .or_default()
+            .push(SessionEntry {
+                start_position: session.start_position,
+                end_position: session.end_position,
+                started_at: session.started_at,
+                ended_at: session.ended_at,
+                rate: session.rate,
+            });
+    }
+
+    let mut feed_entries = Vec::with_capacity(feeds.len());
+    for feed in feeds {
+        let episodes = data.get_episode_snapshots(feed.id)?;
+        let episodes = (episodes.into_iter())
+            .map(|episode| EpisodeEntry {
+                queue_position: queue.get(&episode.id).cloned(),
+                bookmarks: bookmarks.remove(&episode.id).unwrap_or_default(),
+                sessions: sessions.remove(&episode.id).unwrap_or_default(),
+                ..episode_entry(episode)
+            })
+            .collect();
+        feed_entries.push(FeedEntry {
+            group: feed.group_id.and_then(|id| group_names.get(&id).cloned()),
+            episodes,
+            ..feed_entry(feed)
+        });
+    }
+
+    let views = (data.get_view_summaries()?.into_iter())
+        .map(|view| {
+            let mut query = view.query;
+            let feed = (query.feed_id.take()).and_then(|id| feed_sources.get(&id).cloned());
+            let group = (query.group_id.take()).and_then(|id| group_names.get(&id).cloned());
+            query.episode_id = None;
+            ViewEntry {
+                name: view.name,
+                feed,
+                group,
+                query,
+            }
+        })
+        .collect();
+
+    let backup = Backup {
+        version: BACKUP_VERSION,
+        groups: (groups.iter())
+            .map(|group| GroupEntry {
+                name: group.name.clone(),
+                parent: group.parent_id.and_then(|id| group_names.get(&id).cloned()),
+                collapsed: group.collapsed,
+            })
+            .collect(),
+        feeds: feed_entries,
+        views,
+    };
+    serde_json::to_writer_pretty(write, &backup)?;
+    Ok(())
+}
+
+fn feed_entry(feed: FeedSnapshot) -> FeedEntry {
+    FeedEntry {
+        source: feed.source,
+        title: feed.title,
+        title_override: feed.title_override,
+        link: feed.link,
+        group: None,
+        enabled: feed.enabled,
+        reversed: feed.reversed,
+        playback_rate: feed.playback_rate,
+        skip_intro: feed.skip_intro,
+        skip_outro: feed.skip_outro,
+        tags: feed.tags,
+        episodes: Vec::new(),
+    }
+}
+
+fn episode_entry(episode: EpisodeSnapshot) -> EpisodeEntry {
+    let (status, position) = match episode.status {
+        EpisodeStatus::New => (StatusEntry::New, Duration::ZERO),
+        EpisodeStatus::NotStarted => (StatusEntry::Seen, Duration::ZERO),
+        EpisodeStatus::Finished => (StatusEntry::Finished, Duration::ZERO),
+        EpisodeStatus::Started(position) => (StatusEntry::Started, position),
+        EpisodeStatus::Error(position) => (StatusEntry::Error, position),
+    };
+    EpisodeEntry {
+        guid: episode.guid,
+        title: episode.title,
+        description: episode.description,
+        link: episode.link,
+        duration: episode.duration,
+        publication_date: episode.publication_date,
+        episode_number: episode.episode_number,
+        season_number: episode.season_number,
+        media_url: episode.media_url,
+        status,
+        position,
+        hidden: episode.hidden,
+        tags: episode.tags,
+        queue_position: None,
+        bookmarks: Vec::new(),
+        sessions: Vec::new(),
+    }
+}
+
+/// Orders the statuses by listening progress: new, seen, started at an earlier position, started
+/// at a later position, finished. An error is ranked by its position like a started episode.
+fn progress(status: &EpisodeStatus) -> (u8, Duration) {
+    match status {
+        EpisodeStatus::New => (0, Duration::ZERO),
+        EpisodeStatus::NotStarted => (1, Duration::ZERO),
+        EpisodeStatus::Started(position) | EpisodeStatus::Error(position) => (2, *position),
+        EpisodeStatus::Finished => (3, Duration::ZERO),
+    }
+}
+
+/// Whether the restored status should replace the status of an existing episode. It does so only
+/// if it has more progress, so that progress is never lost.
+pub(crate) fn restores_status(current: &EpisodeStatus, restored: &EpisodeStatus) -> bool {
+    progress(restored) > progress(current)
+}
+
+pub fn restore_backup<R: io::Read, D: DataProvider>(read: R, data: &mut D) -> Result<(), Error> {
+    let document: serde_json::Value = serde_json::from_reader(read)?;
+    let version = (document.get("version"))
+        .and_then(serde_json::Value::as_u64)
+        .ok_or(Error::NotABackup)?;
+    if version > BACKUP_VERSION as u64 {
+        return Err(Error::UnsupportedVersion {
+            version,
+            supported: BACKUP_VERSION,
+        });
+    }
+    let backup: Backup = serde_json::from_value(document)?;
+    backup.validate()?;
+
+    let mut group_ids: HashMap<String, GroupId> = (data.get_group_summaries()?.into_iter())
+        .map(|group| (group.name, group.id))
+        .collect();
+    let mut created_groups = Vec::new();
+    for group in &backup.groups {
+        if group_ids.contains_key(&group.name) {
+            continue;
+        }
+        if let Some(group_id) = data.create_group(&group.name)? {
+            group_ids.insert(group.name.clone(), group_id);
+            created_groups.push((group_id, group));
+        }
+    }
+    for (group_id, group) in created_groups {
+        let parent_id = group.parent.as_ref().and_then(|name| group_ids.get(name));
+        if let Some(parent_id) = parent_id {
+            data.set_group_parent(group_id, Some(*parent_id))?;
+        }
+        if group.collapsed {
+            data.set_group_collapsed(Some(group_id), true)?;
+        }
+    }
+
+    let existing_feeds: HashMap<String, FeedId> = (data.get_feed_snapshots()?.into_iter())
+        .map(|feed| (feed_url::comparison_key(&feed.source), feed.id))
+        .collect();
+    let existing_bookmarks: HashSet<(EpisodeId, u128)> = (data.get_bookmarks()?.into_iter())
+        .map(|bookmark| (bookmark.episode_id, bookmark.position.as_millis()))
+        .collect();
+    let existing_sessions: HashSet<(EpisodeId, DateTime<Utc>)> =
+        (data.get_listening_sessions()?.into_iter())
+            .map(|session| (session.episode_id, session.started_at))
+            .collect();
+    let mut feed_ids = HashMap::new();
+    let mut queue = Vec::new();
+    for feed in &backup.feeds {
+        let feed_id = match existing_feeds.get(&feed_url::comparison_key(&feed.source)) {
+            Some(feed_id) => *feed_id,
+            None => {
+                let metadata = NewFeedMetadata::new(feed.source.clone())
+                    .with_title(feed.title.clone())
+                    .with_link(feed.link.clone());
+                let feed_id = match data.create_feed_pending(&metadata)? {
+                    Some(feed_id) => feed_id,
+                    None => continue,
+                };
+                restore_feed_settings(data, feed_id, feed, &group_ids)?;
+                feed_id
+            }
+        };
+        feed_ids.insert(feed.source.as_str(), feed_id);
+        for tag in &feed.tags {
+            data.add_tag(TagTarget::Feed(feed_id), tag)?;
+        }
+
+        let existing: HashMap<String, EpisodeSnapshot> = (data.get_episode_snapshots(feed_id)?)
+            .into_iter()
+            .map(|episode| (episode.guid.clone(), episode))
+            .collect();
+        let mut created = HashMap::new();
+        let mut writer = data.writer(feed_id)?;
+        for episode in &feed.episodes {
+            if !existing.contains_key(&episode.guid) {
+                let episode_id = writer.set_episode_metadata(&episode.metadata())?;
+                created.insert(episode.guid.as_str(), episode_id);
+            }
+        }
+        writer.close()?;
+
+        for episode in &feed.episodes {
+            let status = episode.status();
+            let created_id = created.get(episode.guid.as_str());
+            let episode_id = match (existing.get(&episode.guid), created_id) {
+                (Some(current), _) => {
+                    if restores_status(&current.status, &status) {
+                        let query = EpisodesQuery::default().id(current.id);
+                        data.set_episode_status(query, status)?;
+                    }
+                    if episode.hidden && !current.hidden {
+                        data.set_episode_hidden(EpisodesQuery::default().id(current.id), true)?;
+                    }
+                    current.id
+                }
+                (None, Some(episode_id)) => {
+                    let query = EpisodesQuery::default().id(*episode_id);
+                    data.set_episode_status(query.clone(), status)?;
+                    if episode.hidden {
+                        data.set_episode_hidden(query, true)?;
+                    }
+                    *episode_id
+                }
+                (None, None) => continue,
+            };
+            restore_episode_extras(
+                data,
+                episode_id,
+                episode,
+                &existing_bookmarks,
+                &existing_sessions,
+            )?;
+            if let Some(position) = episode.queue_position {
+                queue.push((position, episode_id));
+            }
+        }
+    }
+
+    queue.sort_by_key(|(position, _)| *position);
+    for (_, episode_id) in queue {
+        data.add_to_queue(episode_id)?;
+    }
+
+    for view in &backup.views {
+        let mut query = view.query.clone();
+        query.episode_id = None;
+        query.feed_id = view.feed.as_deref().and_then(|source| feed_ids.get(source).cloned());
+        query.group_id = view.group.as_ref().and_then(|name| group_ids.get(name).cloned());
+        if view.feed.is_some() && query.feed_id.is_none()
+            || view.group.is_some() && query.group_id.is_none()
+        {
+            continue;
+        }
+        data.create_view(&view.name, &query)?;
+    }
+    Ok(())
+}
+
+fn restore_feed_settings<D: DataProvider>(
+    data: &mut D,
+    feed_id: FeedId,
+    feed: &FeedEntry,
+    group_ids: &HashMap<String, GroupId>,
+) -> Result<(), Error> {
+    if feed.title_override.is_some() {
+        data.rename_feed(feed_id, feed.title_override.clone())?;
+    }
+    if let Some(group_id) = feed.group.as_ref().and_then(|name| group_ids.get(name)) {
+        data.set_feed_for_group(Some(*group_id), feed_id)?;
+    }
+    if !feed.enabled {
+        data.set_feed_enabled(feed_id, false)?;
+    }
+    if feed.reversed {
+        data.reverse_feed_order(feed_id)?;
+    }
+    if feed.playback_rate.is_some() {
+        data.set_feed_playback_rate(feed_id, feed.playback_rate)?;
+    }
+    if !feed.skip_intro.is_zero() {
+        data.set_feed_skip_intro(feed_id, feed.skip_intro)?;
+    }
+    if !feed.skip_outro.is_zero() {
+        data.set_feed_skip_outro(feed_id, feed.skip_outro)?;
+    }
+    Ok(())
+}
+
+fn restore_episode_extras<D: DataProvider>(
+    data: &mut D,
+    episode_id: EpisodeId,
+    episode: &EpisodeEntry,
+    existing_bookmarks: &HashSet<(EpisodeId, u128)>,
+    existing_sessions: &HashSet<(EpisodeId, DateTime<Utc>)>,
+) -> Result<(), Error> {
+    for tag in &episode.tags {
+        data.add_tag(TagTarget::Episode(episode_id), tag)?;
+    }
+    for bookmark in &episode.bookmarks {
+        // Positions are compared in milliseconds as they lose precision when written in seconds.
+        if !existing_bookmarks.contains(&(episode_id, bookmark.position.as_millis())) {
+            data.create_bookmark(episode_id, bookmark.position, bookmark.note.as_deref())?;
+        }
+    }
+    for session in &episode.sessions {
+        if existing_sessions.contains(&(episode_id, session.started_at)) {
+            continue;
+        }
+        data.save_listening_session(&ListeningSession {
+            episode_id,
+            start_position: session.start_position,
+            end_position: session.end_position,
+            started_at: session.started_at,
+            ended_at: session.ended_at,
+            rate: session.rate,
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_backup, restore_backup, restores_status, Error};
+    use crate::conformance::feed_with_episodes;
+    use crate::datasource::DataProvider;
+    use crate::model::{
+        EpisodeId, EpisodeSnapshot, EpisodeStatus, FeedId, ListeningSession, TagTarget,
+    };
+    use crate::{EpisodesQuery, SqliteDataProvider};
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    fn set_status(provider: &mut SqliteDataProvider, episode_id: EpisodeId, status: EpisodeStatus) {
+        let query = EpisodesQuery::default().id(episode_id);
+        provider.set_episode_status(query, status).unwrap();
+    }
+
+    fn create_feed(provider: &mut SqliteDataProvider) -> (FeedId, Vec<EpisodeId>) {
+        let day = |day| Some(Utc.ymd(2022, 1, day).and_hms(12, 0, 0));
+        let episodes = [("ep1", day(1)), ("ep2", day(2)), ("ep3", day(3))];
+        feed_with_episodes(provider, "http://example.com/feed.xml", &episodes)
+    }
+
+    fn backup(provider: &mut SqliteDataProvider) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        build_backup(&mut buffer, provider).unwrap();
+        buffer
+    }
+
+    fn episode_states(provider: &mut SqliteDataProvider) -> Vec<(String, EpisodeStatus, bool)> {
+        let feed_id = provider.get_feed_snapshots().unwrap()[0].id;
+        (provider.get_episode_snapshots(feed_id).unwrap().into_iter())
+            .map(|episode: EpisodeSnapshot| (episode.guid, episode.status, episode.hidden))
+            .collect()
+    }
+
+    #[test]
+    fn restores_library() {
+        let mut source = SqliteDataProvider::connect(":memory:").unwrap();
+        let news = source.create_group("News").unwrap().unwrap();
+        let tech = source.create_group("Tech").unwrap().unwrap();
+        source.set_group_parent(tech, Some(news)).unwrap();
+        source.set_group_collapsed(Some(news), true).unwrap();
+
+        let (feed_id, episodes) = create_feed(&mut source);
+        source.set_feed_for_group(Some(tech), feed_id).unwrap();
+        source.rename_feed(feed_id, Some("My podcast".to_string())).unwrap();
+        source.reverse_feed_order(feed_id).unwrap();
+        source.set_feed_enabled(feed_id, false).unwrap();
+        source.set_feed_playback_rate(feed_id, Some(1.5)).unwrap();
+        source.set_feed_skip_intro(feed_id, Duration::from_secs(30)).unwrap();
+        source.add_tag(TagTarget::Feed(feed_id), "daily").unwrap();
+        source.add_tag(TagTarget::Episode(episodes[2]), "favorite").unwrap();
+        set_status(&mut source, episodes[0], EpisodeStatus::Finished);
+        set_status(&mut source, episodes[1], EpisodeStatus::Started(Duration::from_secs(90)));
+        let hidden = EpisodesQuery::default().id(episodes[1]);
+        source.set_episode_hidden(hidden, true).unwrap();
+        source.add_to_queue(episodes[2]).unwrap();
+        source.add_to_queue(episodes[1]).unwrap();
+        source
+            .create_bookmark(episodes[1], Duration::from_secs(60), Some("quote"))
+            .unwrap();
+        let session = ListeningSession {
+            episode_id: episodes[1],
+            start_position: Duration::ZERO,
+            end_position: Duration::from_secs(90),
+            started_at: Utc.ymd(2022, 1, 4).and_hms(8, 0, 0),
+            ended_at: Utc.ymd(2022, 1, 4).and_hms(8, 1, 30),
+            rate: 1.0,
+        };
+        source.save_listening_session(&session).unwrap();
+        let query = EpisodesQuery::default().feed_id(feed_id).with_hidden(false);
+        source.create_view("Visible", &query).unwrap();
+
+        let mut target = SqliteDataProvider::connect(":memory:").unwrap();
+        restore_backup(&backup(&mut source)[..], &mut target).unwrap();
+
+        let groups = target.get_group_summaries().unwrap();
+        let groups: Vec<_> = (groups.iter())
+            .map(|group| (group.name.as_str(), group.depth, group.collapsed))
+            .collect();
+        assert_eq!(groups, vec![("News", 0, true), ("Tech", 1, false)]);
+
+        let mut expected = source.get_feed_snapshots().unwrap();
+        let restored = target.get_feed_snapshots().unwrap();
+        assert_eq!(restored.len(), 1);
+        let restored_feed_id = restored[0].id;
+        expected[0].id = restored_feed_id;
+        expected[0].group_id = restored[0].group_id;
+        assert_eq!(restored, expected);
+        assert_eq!(episode_states(&mut target), episode_states(&mut source));
+
+        let restored_episodes = target.get_episode_snapshots(restored_feed_id).unwrap();
+        assert_eq!(restored_episodes[2].tags, vec!["favorite".to_string()]);
+        let queue: Vec<_> = (target.get_queue().unwrap().into_iter())
+            .map(|episode| episode.title.unwrap())
+            .collect();
+        assert_eq!(queue, vec!["ep3", "ep2"]);
+        let bookmarks = target.get_bookmarks().unwrap();
+        assert_eq!(bookmarks.len(), 1);
+        assert_eq!(bookmarks[0].episode_id, restored_episodes[1].id);
+        assert_eq!(bookmarks[0].position, Duration::from_secs(60));
+        assert_eq!(bookmarks[0].note.as_deref(), Some("quote"));
+        let sessions = target.get_listening_sessions().unwrap();
+        assert_eq!(
+            sessions,
+            vec![ListeningSession {
+                episode_id: restored_episodes[1].id,
+                ..session
+            }]
+        );
+        let views = target.get_view_summaries().unwrap();
+        assert_eq!(views.len(), 1);
+        assert_eq!(
+            views[0].query,
+            EpisodesQuery::default()
+                .feed_id(restored_feed_id)
+                .with_hidden(false)
+        );
+    }
+
+    #[test]
+    fn merges_into_existing_library() {
+        let mut source = SqliteDataProvider::connect(":memory:").unwrap();
+        let (_, episodes) = create_feed(&mut source);
+        set_status(&mut source, episodes[0], EpisodeStatus::Finished);
+        set_status(&mut source, episodes[1], EpisodeStatus::Started(Duration::from_secs(90)));
+        source.add_to_queue(episodes[2]).unwrap();
+        source
+            .create_bookmark(episodes[2], Duration::from_millis(12345), None)
+            .unwrap();
+        let session = ListeningSession::new(episodes[1], Duration::ZERO, 1.0);
+        source.save_listening_session(&session).unwrap();
+        let backup = backup(&mut source);
+
+        let mut target = SqliteDataProvider::connect(":memory:").unwrap();
+        let (feed_id, episodes) = create_feed(&mut target);
+        set_status(&mut target, episodes[1], EpisodeStatus::Finished);
+        set_status(&mut target, episodes[2], EpisodeStatus::NotStarted);
+        target.rename_feed(feed_id, Some("Renamed".to_string())).unwrap();
+
+        restore_backup(&backup[..], &mut target).unwrap();
+        restore_backup(&backup[..], &mut target).unwrap();
+
+        assert_eq!(
+            episode_states(&mut target),
+            vec![
+                ("ep1".to_string(), EpisodeStatus::Finished, false),
+                ("ep2".to_string(), EpisodeStatus::Finished, false),
+                ("ep3".to_string(), EpisodeStatus::NotStarted, false),
+            ]
+        );
+        let feeds = target.get_feed_snapshots().unwrap();
+        assert_eq!(feeds.len(), 1);
+        assert_eq!(feeds[0].title_override.as_deref(), Some("Renamed"));
+        assert_eq!(target.get_queue().unwrap().len(), 1);
+        assert_eq!(target.get_bookmarks().unwrap().len(), 1);
+        assert_eq!(target.get_listening_sessions().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn restores_status_with_more_progress() {
+        let started = |seconds| EpisodeStatus::Started(Duration::from_secs(seconds));
+        assert!(restores_status(&EpisodeStatus::New, &EpisodeStatus::NotStarted));
+        assert!(restores_status(&EpisodeStatus::NotStarted, &started(10)));
+        assert!(restores_status(&started(10), &started(90)));
+        assert!(restores_status(&started(90), &EpisodeStatus::Finished));
+        assert!(!restores_status(&started(90), &started(10)));
+        assert!(!restores_status(&EpisodeStatus::Finished, &started(90)));
+        assert!(!restores_status(&EpisodeStatus::NotStarted, &EpisodeStatus::New));
+    }
+
+    #[test]
+    fn rejects_invalid_entries_before_writing() {
+        let mut provider = SqliteDataProvider::connect(":memory:").unwrap();
+        let backup = br#"{
+            "version": 1,
+            "groups": [{"name": "News"}],
+            "feeds": [
+                {"source": "http://example.com/feed.xml"},
+                {"source": "not a feed"}
+            ]
+        }"#;
+        let result = restore_backup(&backup[..], &mut provider);
+        assert!(matches!(result, Err(Error::InvalidEntry(_))));
+        assert!(provider.get_group_summaries().unwrap().is_empty());
+        assert!(provider.get_feed_snapshots().unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_huge_durations() {
+        let mut provider = SqliteDataProvider::connect(":memory:").unwrap();
+        let backup = br#"{
+            "version": 1,
+            "feeds": [{"source": "http://example.com/feed.xml", "skip_intro": 1e30}]
+        }"#;
+        let result = restore_backup(&backup[..], &mut provider);
+        assert!(matches!(result, Err(Error::Json(_))));
+        assert!(provider.get_feed_snapshots().unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_files() {
+        let mut provider = SqliteDataProvider::connect(":memory:").unwrap();
+        let result = restore_backup(&br#"{"version": 2, "feeds": []}"#[..], &mut provider);
+        assert!(matches!(
+            result,
+            Err(Error::UnsupportedVersion { version: 2, .. })
+        ));
+        let result = restore_backup(&br#"{"feeds": []}"#[..], &mut provider);
+        assert!(matches!(result, Err(Error::NotABackup)));
+        let result = restore_backup(&b"<opml></opml>"[..], &mut provider);
+        assert!(matches!(result, Err(Error::Json(_))));
+    }
+}
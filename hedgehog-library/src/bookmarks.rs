@@ -0,0 +1,148 @@
+use crate::datasource::DataProvider;
+use crate::model::Bookmark;
+use crate::QueryError;
+use std::io;
+use std::time::Duration;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Db(#[from] QueryError),
+}
+
+pub fn build_markdown<W: io::Write, D: DataProvider>(write: W, data: &mut D) -> Result<(), Error> {
+    let bookmarks = data.get_bookmarks()?;
+    write_markdown(write, &bookmarks)?;
+    Ok(())
+}
+
+pub fn write_markdown<W: io::Write>(mut write: W, bookmarks: &[Bookmark]) -> io::Result<()> {
+    writeln!(write, "# Bookmarks")?;
+
+    let mut previous_episode = None;
+    for bookmark in bookmarks {
+        if previous_episode != Some(bookmark.episode_id) {
+            previous_episode = Some(bookmark.episode_id);
+            let episode_title = bookmark.episode_title.as_deref().unwrap_or("Untitled");
+            writeln!(write)?;
+            match bookmark.feed_title.as_deref() {
+                Some(feed_title) => writeln!(
+                    write,
+                    "## {} — {}",
+                    MarkdownText(feed_title),
+                    MarkdownText(episode_title)
+                )?,
+                None => writeln!(write, "## {}", MarkdownText(episode_title))?,
+            }
+            writeln!(write)?;
+        }
+
+        write!(write, "- `{}`", PositionFormatter(bookmark.position))?;
+        if let Some(note) = bookmark.note.as_deref() {
+            write!(write, " {}", MarkdownText(note))?;
+        }
+        writeln!(write)?;
+    }
+    Ok(())
+}
+
+/// Writes the text escaping the characters that Markdown would interpret as formatting. Line
+/// breaks are replaced with spaces, so that the text stays within its heading or list item.
+struct MarkdownText<'a>(&'a str);
+
+impl<'a> std::fmt::Display for MarkdownText<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = self.0.trim();
+        for (index, ch) in text.char_indices() {
+            let escape = match ch {
+                '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '~' => true,
+                '#' | '+' | '-' => index == 0,
+                '.' | ')' => index > 0 && text[..index].bytes().all(|ch| ch.is_ascii_digit()),
+                _ => false,
+            };
+            match ch {
+                '\r' | '\n' => f.write_str(" ")?,
+                ch if escape => write!(f, "\\{}", ch)?,
+                ch => write!(f, "{}", ch)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+struct PositionFormatter(Duration);
+
+impl std::fmt::Display for PositionFormatter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let total_seconds = self.0.as_secs();
+        let seconds = total_seconds % 60;
+        let minutes = total_seconds / 60 % 60;
+        let hours = total_seconds / 3600;
+        write!(f, "{}:{:0>2}:{:0>2}", hours, minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_markdown;
+    use crate::model::{Bookmark, BookmarkId, EpisodeId};
+    use chrono::Utc;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    fn bookmark(id: i64, episode_id: i64, position: u64, note: Option<&str>) -> Bookmark {
+        Bookmark {
+            id: BookmarkId(id),
+            episode_id: EpisodeId(episode_id),
+            episode_title: Some(format!("Episode {}", episode_id)),
+            feed_title: Some("Podcast".to_string()),
+            position: Duration::from_secs(position),
+            note: note.map(ToString::to_string),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn groups_bookmarks_by_episode() {
+        let bookmarks = [
+            bookmark(1, 1, 65, Some("A quote")),
+            bookmark(2, 1, 3725, None),
+            bookmark(3, 2, 10, Some("A reference")),
+        ];
+        let mut output = Vec::new();
+        write_markdown(&mut output, &bookmarks).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "# Bookmarks\n\
+            \n\
+            ## Podcast — Episode 1\n\
+            \n\
+            - `0:01:05` A quote\n\
+            - `1:02:05`\n\
+            \n\
+            ## Podcast — Episode 2\n\
+            \n\
+            - `0:00:10` A reference\n"
+        );
+    }
+
+    #[test]
+    fn escapes_markdown() {
+        let mut first = bookmark(1, 1, 0, Some("# not a *heading*\nsee [link](url)"));
+        first.feed_title = Some("snake_case".to_string());
+        first.episode_title = Some("1. Intro".to_string());
+        let mut output = Vec::new();
+        write_markdown(&mut output, &[first]).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "# Bookmarks\n\
+            \n\
+            ## snake\\_case — 1\\. Intro\n\
+            \n\
+            - `0:00:00` \\# not a \\*heading\\* see \\[link\\](url)\n"
+        );
+    }
+}
@@ -1,30 +1,139 @@
 use crate::datasource::{DataProvider, DbResult, EpisodeWriter};
 use crate::model::{
-    Episode, EpisodeId, EpisodePlaybackData, EpisodeStatus, EpisodeSummary, EpisodesListMetadata,
-    Feed, FeedId, FeedOMPLEntry, FeedStatus, FeedSummary, GroupId,
+    Bookmark, BookmarkId, Episode, EpisodeId, EpisodePlaybackData, EpisodeSnapshot, EpisodeState,
+    EpisodeStatus, EpisodeSummary, EpisodeSummaryStatus, EpisodesListMetadata, Feed, FeedId,
+    FeedOMPLEntry, FeedSnapshot, FeedStatus, FeedSummary, GroupId, ListeningSession,
+    ListeningStats, TagTarget, TrashedFeed, ViewId, ViewSummary,
 };
 use crate::{EpisodesQuery, NewFeedMetadata, UpdateQuery};
+use chrono::{DateTime, Utc};
 use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
 use std::ops::Range;
+use std::time::Duration;
+
+/// Cached results of the episodes list queries.
+pub trait EpisodesCache {
+    /// Changes every time the cached data is invalidated, the results of the queries that started
+    /// before that are not stored.
+    fn generation(&self) -> u64;
+    fn cached_episodes_list_metadata(
+        &mut self,
+        query: &EpisodesQuery,
+    ) -> Option<EpisodesListMetadata>;
+    fn cached_episode_summaries(
+        &mut self,
+        query: &EpisodesQuery,
+        range: &Range<usize>,
+    ) -> Option<Vec<EpisodeSummary>>;
+    fn store_episodes_list_metadata(
+        &mut self,
+        generation: u64,
+        query: EpisodesQuery,
+        metadata: EpisodesListMetadata,
+    );
+    fn store_episode_summaries(
+        &mut self,
+        generation: u64,
+        query: EpisodesQuery,
+        range: Range<usize>,
+        summaries: Vec<EpisodeSummary>,
+    );
+}
+
+/// The maximum number of cached pages of the episodes lists.
+const PAGES_CAPACITY: usize = 256;
+/// The maximum number of the episodes lists whose metadata is cached.
+const LISTS_CAPACITY: usize = 64;
+/// The number of lookups after which the cache statistics are logged.
+const STATS_LOG_INTERVAL: usize = 500;
+
+/// A map that evicts its least recently used entry when it is full.
+struct LruMap<K, V> {
+    capacity: usize,
+    clock: u64,
+    entries: HashMap<K, (V, u64)>,
+}
+
+impl<K: Clone + Eq + Hash, V> LruMap<K, V> {
+    fn new(capacity: usize) -> Self {
+        LruMap {
+            capacity,
+            clock: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn get(&mut self, key: &K) -> Option<&V> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.entries.get_mut(key).map(|(value, last_used)| {
+            *last_used = clock;
+            &*value
+        })
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            let least_recently_used = (self.entries.iter())
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(key) = least_recently_used {
+                self.entries.remove(&key);
+            }
+        }
+        self.clock += 1;
+        self.entries.insert(key, (value, self.clock));
+    }
+
+    fn retain(&mut self, pred: impl Fn(&K) -> bool) {
+        self.entries.retain(|key, _| pred(key));
+    }
+
+    fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.entries.values_mut().map(|(value, _)| value)
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Returns `false` for the queries whose results depend on the current time, as the episodes
+/// published within a period leave the list without any change in the database.
+fn is_cacheable(query: &EpisodesQuery) -> bool {
+    query.published_within.is_none()
+}
 
 pub struct InMemoryCache<D> {
     data_provider: D,
-    episodes_list_metadata: HashMap<EpisodesQuery, EpisodesListMetadata>,
-    episodes_summaries: HashMap<EpisodesQuery, HashMap<Range<usize>, Vec<EpisodeSummary>>>,
+    generation: u64,
+    hits: usize,
+    misses: usize,
+    episodes_list_metadata: LruMap<EpisodesQuery, EpisodesListMetadata>,
+    episodes_summaries: LruMap<(EpisodesQuery, Range<usize>), Vec<EpisodeSummary>>,
 }
 
 impl<D> InMemoryCache<D> {
     pub fn new(data_provider: D) -> Self {
         InMemoryCache {
             data_provider,
-            episodes_list_metadata: HashMap::new(),
-            episodes_summaries: HashMap::new(),
+            generation: 0,
+            hits: 0,
+            misses: 0,
+            episodes_list_metadata: LruMap::new(LISTS_CAPACITY),
+            episodes_summaries: LruMap::new(PAGES_CAPACITY),
         }
     }
 
     fn invalidate_where(&mut self, pred: impl Fn(&EpisodesQuery) -> bool) {
-        self.episodes_list_metadata.retain(|key, _| !pred(key));
-        self.episodes_summaries.retain(|key, _| !pred(key));
+        self.generation += 1;
+        self.episodes_list_metadata.retain(|key| !pred(key));
+        self.episodes_summaries.retain(|(key, _)| !pred(key));
     }
 
     fn invalidate_feed(&mut self, feed_id: FeedId) {
@@ -35,12 +144,110 @@ impl<D> InMemoryCache<D> {
     }
 
     fn invalidate_all(&mut self) {
+        self.generation += 1;
         self.episodes_list_metadata.clear();
         self.episodes_summaries.clear();
     }
+
+    /// Updates the status of the episode in the cached pages. The lists filtered by status, which
+    /// the episode may enter or leave, are invalidated instead.
+    fn patch_episode_status(
+        &mut self,
+        episode_id: EpisodeId,
+        feed_ids: &HashSet<FeedId>,
+        status: EpisodeSummaryStatus,
+    ) {
+        self.invalidate_where(|query| {
+            let filtered = query.status.is_some() || query.excluded_status.is_some();
+            filtered && query.feed_id.map_or(true, |feed_id| feed_ids.contains(&feed_id))
+        });
+        for summaries in self.episodes_summaries.values_mut() {
+            for summary in summaries.iter_mut() {
+                if summary.id == episode_id {
+                    summary.status = status;
+                }
+            }
+        }
+    }
+
+    fn record_lookup<T>(&mut self, result: Option<T>) -> Option<T> {
+        match result {
+            Some(_) => self.hits += 1,
+            None => self.misses += 1,
+        }
+        if (self.hits + self.misses) % STATS_LOG_INTERVAL == 0 {
+            log::info!(
+                target: "cache",
+                "Episodes cache: {} hits, {} misses, {} pages cached",
+                self.hits,
+                self.misses,
+                self.episodes_summaries.len(),
+            );
+        }
+        result
+    }
+}
+
+impl<D> EpisodesCache for InMemoryCache<D> {
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn cached_episodes_list_metadata(
+        &mut self,
+        query: &EpisodesQuery,
+    ) -> Option<EpisodesListMetadata> {
+        let metadata = self.episodes_list_metadata.get(query).cloned();
+        self.record_lookup(metadata)
+    }
+
+    fn cached_episode_summaries(
+        &mut self,
+        query: &EpisodesQuery,
+        range: &Range<usize>,
+    ) -> Option<Vec<EpisodeSummary>> {
+        let key = (query.clone(), range.clone());
+        let summaries = self.episodes_summaries.get(&key).cloned();
+        self.record_lookup(summaries)
+    }
+
+    fn store_episodes_list_metadata(
+        &mut self,
+        generation: u64,
+        query: EpisodesQuery,
+        metadata: EpisodesListMetadata,
+    ) {
+        if generation == self.generation && is_cacheable(&query) {
+            self.episodes_list_metadata.insert(query, metadata);
+        }
+    }
+
+    fn store_episode_summaries(
+        &mut self,
+        generation: u64,
+        query: EpisodesQuery,
+        range: Range<usize>,
+        summaries: Vec<EpisodeSummary>,
+    ) {
+        if generation == self.generation && is_cacheable(&query) {
+            self.episodes_summaries.insert((query, range), summaries);
+        }
+    }
 }
 
 impl<D: DataProvider> DataProvider for InMemoryCache<D> {
+    fn check_external_changes(&mut self) -> DbResult<bool> {
+        let changed = self.data_provider.check_external_changes()?;
+        if changed {
+            self.invalidate_all();
+        }
+        Ok(changed)
+    }
+
+    fn episodes_cache(&mut self) -> Option<&mut dyn EpisodesCache> {
+        Some(self)
+    }
+
     fn get_feed(&mut self, id: FeedId) -> DbResult<Option<Feed>> {
         self.data_provider.get_feed(id)
     }
@@ -53,6 +260,14 @@ impl<D: DataProvider> DataProvider for InMemoryCache<D> {
         self.data_provider.get_feed_opml_entries()
     }
 
+    fn get_feed_snapshots(&mut self) -> DbResult<Vec<FeedSnapshot>> {
+        self.data_provider.get_feed_snapshots()
+    }
+
+    fn get_episode_snapshots(&mut self, feed_id: FeedId) -> DbResult<Vec<EpisodeSnapshot>> {
+        self.data_provider.get_episode_snapshots(feed_id)
+    }
+
     fn get_update_sources(&mut self, update: UpdateQuery) -> DbResult<Vec<(FeedId, String)>> {
         self.data_provider.get_update_sources(update)
     }
@@ -64,7 +279,7 @@ impl<D: DataProvider> DataProvider for InMemoryCache<D> {
         self.data_provider.get_new_episodes_count(feed_ids)
     }
 
-    fn rename_feed(&mut self, feed_id: FeedId, name: String) -> DbResult<()> {
+    fn rename_feed(&mut self, feed_id: FeedId, name: Option<String>) -> DbResult<()> {
         self.data_provider.rename_feed(feed_id, name)
     }
 
@@ -77,6 +292,7 @@ impl<D: DataProvider> DataProvider for InMemoryCache<D> {
     }
 
     fn set_feed_for_group(&mut self, group_id: Option<GroupId>, feed_id: FeedId) -> DbResult<()> {
+        self.invalidate_where(|query| query.group_id.is_some());
         self.data_provider.set_feed_for_group(group_id, feed_id)
     }
 
@@ -85,6 +301,7 @@ impl<D: DataProvider> DataProvider for InMemoryCache<D> {
     }
 
     fn delete_group(&mut self, group_id: GroupId) -> DbResult<()> {
+        self.invalidate_where(|query| query.group_id.is_some());
         self.data_provider.delete_group(group_id)
     }
 
@@ -92,6 +309,99 @@ impl<D: DataProvider> DataProvider for InMemoryCache<D> {
         self.data_provider.set_group_position(group_id, position)
     }
 
+    fn set_group_parent(
+        &mut self,
+        group_id: GroupId,
+        parent_id: Option<GroupId>,
+    ) -> DbResult<bool> {
+        self.invalidate_where(|query| query.group_id.is_some());
+        self.data_provider.set_group_parent(group_id, parent_id)
+    }
+
+    fn set_group_collapsed(&mut self, group_id: Option<GroupId>, collapsed: bool) -> DbResult<()> {
+        self.data_provider.set_group_collapsed(group_id, collapsed)
+    }
+
+    fn create_view(&mut self, name: &str, query: &EpisodesQuery) -> DbResult<Option<ViewId>> {
+        self.data_provider.create_view(name, query)
+    }
+
+    fn get_view_summaries(&mut self) -> DbResult<Vec<ViewSummary>> {
+        self.data_provider.get_view_summaries()
+    }
+
+    fn rename_view(&mut self, view_id: ViewId, name: String) -> DbResult<()> {
+        self.data_provider.rename_view(view_id, name)
+    }
+
+    fn delete_view(&mut self, view_id: ViewId) -> DbResult<()> {
+        self.data_provider.delete_view(view_id)
+    }
+
+    fn get_queue(&mut self) -> DbResult<Vec<EpisodeSummary>> {
+        self.data_provider.get_queue()
+    }
+
+    fn add_to_queue(&mut self, episode_id: EpisodeId) -> DbResult<()> {
+        self.data_provider.add_to_queue(episode_id)
+    }
+
+    fn remove_from_queue(&mut self, episode_id: EpisodeId) -> DbResult<()> {
+        self.data_provider.remove_from_queue(episode_id)
+    }
+
+    fn set_queue_position(&mut self, episode_id: EpisodeId, position: usize) -> DbResult<()> {
+        self.data_provider.set_queue_position(episode_id, position)
+    }
+
+    fn clear_queue(&mut self) -> DbResult<()> {
+        self.data_provider.clear_queue()
+    }
+
+    fn create_bookmark(
+        &mut self,
+        episode_id: EpisodeId,
+        position: Duration,
+        note: Option<&str>,
+    ) -> DbResult<BookmarkId> {
+        self.data_provider
+            .create_bookmark(episode_id, position, note)
+    }
+
+    fn get_bookmarks(&mut self) -> DbResult<Vec<Bookmark>> {
+        self.data_provider.get_bookmarks()
+    }
+
+    fn delete_bookmark(&mut self, bookmark_id: BookmarkId) -> DbResult<()> {
+        self.data_provider.delete_bookmark(bookmark_id)
+    }
+
+    fn get_tags(&mut self) -> DbResult<Vec<String>> {
+        self.data_provider.get_tags()
+    }
+
+    fn add_tag(&mut self, target: TagTarget, name: &str) -> DbResult<()> {
+        self.invalidate_where(|query| query.tag.is_some());
+        self.data_provider.add_tag(target, name)
+    }
+
+    fn remove_tag(&mut self, target: TagTarget, name: &str) -> DbResult<()> {
+        self.invalidate_where(|query| query.tag.is_some());
+        self.data_provider.remove_tag(target, name)
+    }
+
+    fn save_listening_session(&mut self, session: &ListeningSession) -> DbResult<()> {
+        self.data_provider.save_listening_session(session)
+    }
+
+    fn get_listening_sessions(&mut self) -> DbResult<Vec<ListeningSession>> {
+        self.data_provider.get_listening_sessions()
+    }
+
+    fn get_listening_stats(&mut self) -> DbResult<ListeningStats> {
+        self.data_provider.get_listening_stats()
+    }
+
     fn get_episode(&mut self, episode_id: EpisodeId) -> DbResult<Option<Episode>> {
         self.data_provider.get_episode(episode_id)
     }
@@ -107,16 +417,15 @@ impl<D: DataProvider> DataProvider for InMemoryCache<D> {
         &mut self,
         query: EpisodesQuery,
     ) -> DbResult<EpisodesListMetadata> {
-        match self.episodes_list_metadata.get(&query).cloned() {
-            Some(metadata) => Ok(metadata),
-            None => {
-                let metadata = self
-                    .data_provider
-                    .get_episodes_list_metadata(query.clone())?;
-                self.episodes_list_metadata.insert(query, metadata.clone());
-                Ok(metadata)
-            }
+        if let Some(metadata) = self.cached_episodes_list_metadata(&query) {
+            return Ok(metadata);
         }
+        let generation = self.generation;
+        let metadata = self
+            .data_provider
+            .get_episodes_list_metadata(query.clone())?;
+        self.store_episodes_list_metadata(generation, query, metadata.clone());
+        Ok(metadata)
     }
 
     fn get_episode_summaries(
@@ -124,35 +433,43 @@ impl<D: DataProvider> DataProvider for InMemoryCache<D> {
         query: EpisodesQuery,
         range: Range<usize>,
     ) -> DbResult<Vec<EpisodeSummary>> {
-        match self.episodes_summaries.get_mut(&query) {
-            Some(list_items) => match list_items.get(&range) {
-                Some(items) => Ok(items.clone()),
-                None => {
-                    let summaries = self
-                        .data_provider
-                        .get_episode_summaries(query.clone(), range.clone())?;
-                    list_items.insert(range, summaries.clone());
-                    Ok(summaries)
-                }
-            },
-            None => {
-                let summaries = self
-                    .data_provider
-                    .get_episode_summaries(query.clone(), range.clone())?;
-                let mut items = HashMap::new();
-                items.insert(range, summaries.clone());
-                self.episodes_summaries.insert(query, items);
-                Ok(summaries)
-            }
+        if let Some(summaries) = self.cached_episode_summaries(&query, &range) {
+            return Ok(summaries);
         }
+        let generation = self.generation;
+        let summaries = self
+            .data_provider
+            .get_episode_summaries(query.clone(), range.clone())?;
+        self.store_episode_summaries(generation, query, range, summaries.clone());
+        Ok(summaries)
     }
 
     fn count_episodes(&mut self, query: EpisodesQuery) -> DbResult<usize> {
         self.data_provider.count_episodes(query)
     }
 
+    fn get_next_episode(
+        &mut self,
+        query: EpisodesQuery,
+        episode_id: EpisodeId,
+    ) -> DbResult<Option<EpisodeId>> {
+        self.data_provider.get_next_episode(query, episode_id)
+    }
+
     fn create_feed_pending(&mut self, data: &NewFeedMetadata) -> DbResult<Option<FeedId>> {
-        self.data_provider.create_feed_pending(data)
+        let feed_id = self.data_provider.create_feed_pending(data)?;
+        if let Some(feed_id) = feed_id {
+            self.invalidate_feed(feed_id);
+        }
+        Ok(feed_id)
+    }
+
+    fn find_duplicate_feed(&mut self, feed_id: FeedId) -> DbResult<Option<String>> {
+        self.data_provider.find_duplicate_feed(feed_id)
+    }
+
+    fn set_feed_source(&mut self, feed_id: FeedId, source: &str) -> DbResult<bool> {
+        self.data_provider.set_feed_source(feed_id, source)
     }
 
     fn delete_feed(&mut self, id: FeedId) -> DbResult<()> {
@@ -161,6 +478,26 @@ impl<D: DataProvider> DataProvider for InMemoryCache<D> {
         Ok(())
     }
 
+    fn merge_feed(&mut self, feed_id: FeedId, target_id: FeedId) -> DbResult<usize> {
+        self.invalidate_all();
+        self.data_provider.merge_feed(feed_id, target_id)
+    }
+
+    fn restore_feed(&mut self, id: FeedId) -> DbResult<()> {
+        self.data_provider.restore_feed(id)?;
+        self.invalidate_feed(id);
+        Ok(())
+    }
+
+    fn get_trashed_feeds(&mut self) -> DbResult<Vec<TrashedFeed>> {
+        self.data_provider.get_trashed_feeds()
+    }
+
+    fn purge_trash(&mut self, deleted_before: Option<DateTime<Utc>>) -> DbResult<usize> {
+        self.invalidate_all();
+        self.data_provider.purge_trash(deleted_before)
+    }
+
     fn set_feed_status(&mut self, feed_id: FeedId, status: FeedStatus) -> DbResult<()> {
         self.data_provider.set_feed_status(feed_id, status)
     }
@@ -169,6 +506,18 @@ impl<D: DataProvider> DataProvider for InMemoryCache<D> {
         self.data_provider.set_feed_enabled(feed_id, enabled)
     }
 
+    fn set_feed_playback_rate(&mut self, feed_id: FeedId, rate: Option<f64>) -> DbResult<()> {
+        self.data_provider.set_feed_playback_rate(feed_id, rate)
+    }
+
+    fn set_feed_skip_intro(&mut self, feed_id: FeedId, duration: Duration) -> DbResult<()> {
+        self.data_provider.set_feed_skip_intro(feed_id, duration)
+    }
+
+    fn set_feed_skip_outro(&mut self, feed_id: FeedId, duration: Duration) -> DbResult<()> {
+        self.data_provider.set_feed_skip_outro(feed_id, duration)
+    }
+
     fn reverse_feed_order(&mut self, feed_id: FeedId) -> DbResult<()> {
         self.invalidate_feed(feed_id);
         self.data_provider.reverse_feed_order(feed_id)
@@ -179,9 +528,16 @@ impl<D: DataProvider> DataProvider for InMemoryCache<D> {
         query: EpisodesQuery,
         status: EpisodeStatus,
     ) -> DbResult<HashSet<FeedId>> {
+        let summary_status = EpisodeSummaryStatus::from(&status);
+        let episode_id = query.episode_id;
         let ids = self.data_provider.set_episode_status(query, status)?;
-        for id in &ids {
-            self.invalidate_feed(*id);
+        match episode_id {
+            Some(episode_id) => self.patch_episode_status(episode_id, &ids, summary_status),
+            None => {
+                for id in &ids {
+                    self.invalidate_feed(*id);
+                }
+            }
         }
         Ok(ids)
     }
@@ -191,8 +547,81 @@ impl<D: DataProvider> DataProvider for InMemoryCache<D> {
         self.data_provider.set_episode_hidden(query, hidden)
     }
 
+    fn get_episode_states(&mut self, query: EpisodesQuery) -> DbResult<Vec<EpisodeState>> {
+        self.data_provider.get_episode_states(query)
+    }
+
+    fn restore_episode_states(&mut self, states: &[EpisodeState]) -> DbResult<HashSet<FeedId>> {
+        self.invalidate_all();
+        self.data_provider.restore_episode_states(states)
+    }
+
     fn writer<'a>(&'a mut self, feed_id: FeedId) -> DbResult<Box<dyn EpisodeWriter + 'a>> {
         self.invalidate_feed(feed_id);
         self.data_provider.writer(feed_id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{EpisodesCache, InMemoryCache, LruMap};
+    use crate::conformance::feed_with_episodes;
+    use crate::datasource::DataProvider;
+    use crate::model::{EpisodeStatus, EpisodeSummaryStatus};
+    use crate::{EpisodesQuery, SqliteDataProvider};
+    use std::time::Duration;
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut map = LruMap::new(2);
+        map.insert("first", 1);
+        map.insert("second", 2);
+        assert_eq!(map.get(&"first"), Some(&1));
+        map.insert("third", 3);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&"second"), None);
+        assert_eq!(map.get(&"first"), Some(&1));
+        assert_eq!(map.get(&"third"), Some(&3));
+    }
+
+    #[test]
+    fn patches_episode_status() {
+        let mut provider = SqliteDataProvider::connect(":memory:").unwrap();
+        let (feed_id, _) = feed_with_episodes(
+            &mut provider,
+            "http://example.com/feed.xml",
+            &[("first", None), ("second", None)],
+        );
+
+        let mut cache = InMemoryCache::new(provider);
+        let all_query = EpisodesQuery::default().feed_id(feed_id);
+        let new_query = all_query.clone().status(EpisodeSummaryStatus::New);
+        let summaries = cache.get_episode_summaries(all_query.clone(), 0..10).unwrap();
+        cache.get_episode_summaries(new_query.clone(), 0..10).unwrap();
+        let episode_id = summaries[0].id;
+
+        cache
+            .set_episode_status(EpisodesQuery::default().id(episode_id), EpisodeStatus::Finished)
+            .unwrap();
+        let patched = cache.cached_episode_summaries(&all_query, &(0..10)).unwrap();
+        assert_eq!(patched[0].status, EpisodeSummaryStatus::Finished);
+        assert_eq!(patched[1].status, EpisodeSummaryStatus::New);
+        assert!(cache.cached_episode_summaries(&new_query, &(0..10)).is_none());
+
+        cache
+            .set_episode_status(EpisodesQuery::default(), EpisodeStatus::NotStarted)
+            .unwrap();
+        assert!(cache.cached_episode_summaries(&all_query, &(0..10)).is_none());
+    }
+
+    #[test]
+    fn skips_relative_queries() {
+        let provider = SqliteDataProvider::connect(":memory:").unwrap();
+        let mut cache = InMemoryCache::new(provider);
+        let query = EpisodesQuery::default().published_within(Duration::from_secs(86400 * 7));
+        cache.get_episode_summaries(query.clone(), 0..10).unwrap();
+        cache.get_episodes_list_metadata(query.clone()).unwrap();
+        assert!(cache.cached_episode_summaries(&query, &(0..10)).is_none());
+        assert!(cache.cached_episodes_list_metadata(&query).is_none());
+    }
+}
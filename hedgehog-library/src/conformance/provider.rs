@@ -0,0 +1,914 @@
+//! Checks of the `DataProvider` queries that are run against the provider directly, without
+//! starting a `Library`.
+
+use super::feed_with_episodes;
+use crate::datasource::{DataProvider, NewFeedMetadata};
+use crate::metadata::{EpisodeMetadata, FeedMetadata};
+use crate::model::{
+    EpisodeStatus, EpisodeSummary, EpisodeSummaryStatus, FeedListeningStats, FeedStatus,
+    ListeningSession, TagTarget, ViewSummary, WeekListeningStats,
+};
+use crate::EpisodesQuery;
+use chrono::{NaiveDate, TimeZone, Utc};
+use std::collections::HashSet;
+use std::time::Duration;
+
+pub fn feed_update(mut provider: impl DataProvider) {
+    let id = provider
+        .create_feed_pending(&NewFeedMetadata::new(
+            "http://example.com/feed.xml".to_string(),
+        ))
+        .unwrap()
+        .unwrap();
+
+    let feed_summaries = provider.get_feed_summaries().unwrap();
+    assert_eq!(feed_summaries.len(), 1);
+    assert_eq!(feed_summaries[0].id, id);
+    assert_eq!(feed_summaries[0].title, "http://example.com/feed.xml");
+    assert!(!feed_summaries[0].has_title);
+    assert_eq!(feed_summaries[0].status, FeedStatus::Pending);
+
+    let mut writer = provider.writer(id).unwrap();
+    writer
+        .set_feed_metadata(&FeedMetadata {
+            title: "Title",
+            description: "Description",
+            link: "http://example.com",
+            author: Some("Author"),
+            copyright: Some("Copyright"),
+        })
+        .unwrap();
+    writer.close().unwrap();
+
+    let feed = provider.get_feed(id).unwrap().unwrap();
+    assert_eq!(feed.title.as_deref(), Some("Title"));
+    assert_eq!(feed.description.as_deref(), Some("Description"));
+    assert_eq!(feed.link.as_deref(), Some("http://example.com"));
+    assert_eq!(feed.author.as_deref(), Some("Author"));
+    assert_eq!(feed.copyright.as_deref(), Some("Copyright"));
+    assert_eq!(&feed.source, "http://example.com/feed.xml");
+    assert_eq!(feed.status, FeedStatus::Loaded);
+}
+
+pub fn does_not_create_duplicate(mut provider: impl DataProvider) {
+    let id1 = provider
+        .create_feed_pending(&NewFeedMetadata::new(
+            "http://example.com/feed.xml".to_string(),
+        ))
+        .unwrap();
+    let id2 = provider
+        .create_feed_pending(&NewFeedMetadata::new(
+            "http://example.com/feed.xml".to_string(),
+        ))
+        .unwrap();
+    let id3 = provider
+        .create_feed_pending(&NewFeedMetadata::new(
+            "https://example.com/feed.xml/?utm_source=share#top".to_string(),
+        ))
+        .unwrap();
+
+    assert!(id1.is_some());
+    assert!(id2.is_none());
+    assert!(id3.is_none());
+}
+
+pub fn finds_duplicate_feed(mut provider: impl DataProvider) {
+    let mut feed_ids = Vec::new();
+    for (source, title, link) in [
+        (
+            "http://example.com/feed.xml",
+            "Podcast",
+            "http://example.com/",
+        ),
+        (
+            "http://example.com/other.xml",
+            "Other",
+            "http://example.com/other",
+        ),
+        (
+            "http://mirror.example.com/feed.xml",
+            "podcast",
+            "https://example.com",
+        ),
+    ] {
+        let feed_id = provider
+            .create_feed_pending(&NewFeedMetadata::new(source.to_string()))
+            .unwrap()
+            .unwrap();
+        let mut writer = provider.writer(feed_id).unwrap();
+        writer
+            .set_feed_metadata(&FeedMetadata {
+                title,
+                description: "",
+                link,
+                author: None,
+                copyright: None,
+            })
+            .unwrap();
+        writer.close().unwrap();
+        feed_ids.push(feed_id);
+    }
+
+    let duplicate = provider.find_duplicate_feed(feed_ids[2]).unwrap();
+    assert_eq!(duplicate.as_deref(), Some("Podcast"));
+    assert_eq!(provider.find_duplicate_feed(feed_ids[1]).unwrap(), None);
+
+    provider
+        .rename_feed(feed_ids[0], Some("Renamed".to_string()))
+        .unwrap();
+    let duplicate = provider.find_duplicate_feed(feed_ids[2]).unwrap();
+    assert_eq!(duplicate.as_deref(), Some("Renamed"));
+
+    provider.delete_feed(feed_ids[0]).unwrap();
+    assert_eq!(provider.find_duplicate_feed(feed_ids[2]).unwrap(), None);
+}
+
+pub fn episode_update(mut provider: impl DataProvider) {
+    let feed_id = provider
+        .create_feed_pending(&NewFeedMetadata::new(
+            "http://example.com/feed.xml".to_string(),
+        ))
+        .unwrap()
+        .unwrap();
+
+    let mut writer = provider.writer(feed_id).unwrap();
+    let episode_id = writer
+        .set_episode_metadata(&EpisodeMetadata {
+            title: Some("title"),
+            description: Some("description"),
+            link: Some("link"),
+            guid: "guid-1",
+            duration: None,
+            publication_date: None,
+            episode_number: Some(3),
+            season_number: Some(4),
+            media_url: "http://example.com/feed.xml",
+            block: false,
+        })
+        .unwrap();
+    writer.close().unwrap();
+
+    let retrieved = provider.get_episode(episode_id).unwrap().unwrap();
+    assert_eq!(retrieved.id, episode_id);
+    assert_eq!(retrieved.feed_id, feed_id);
+    assert_eq!(retrieved.episode_number, Some(3));
+    assert_eq!(retrieved.season_number, Some(4));
+    assert_eq!(retrieved.title.as_deref(), Some("title"));
+    assert_eq!(retrieved.description.as_deref(), Some("description"));
+    assert_eq!(retrieved.link.as_deref(), Some("link"));
+    assert_eq!(retrieved.status, EpisodeStatus::New);
+    assert_eq!(retrieved.duration, None);
+    assert_eq!(retrieved.publication_date, None);
+    assert_eq!(&retrieved.media_url, "http://example.com/feed.xml");
+
+    let mut writer = provider.writer(feed_id).unwrap();
+    let episode_id_1 = writer
+        .set_episode_metadata(&EpisodeMetadata {
+            title: Some("title-upd"),
+            description: Some("description-upd"),
+            link: Some("link-upd"),
+            guid: "guid-1",
+            duration: Some(Duration::from_secs(300)),
+            publication_date: None,
+            episode_number: Some(8),
+            season_number: None,
+            media_url: "http://example.com/feed2.xml",
+            block: false,
+        })
+        .unwrap();
+    assert_eq!(episode_id, episode_id_1);
+    writer.close().unwrap();
+
+    let retrieved = provider.get_episode(episode_id).unwrap().unwrap();
+    assert_eq!(retrieved.id, episode_id);
+    assert_eq!(retrieved.feed_id, feed_id);
+    assert_eq!(retrieved.episode_number, Some(8));
+    assert_eq!(retrieved.season_number, None);
+    assert_eq!(retrieved.title.as_deref(), Some("title-upd"));
+    assert_eq!(retrieved.description.as_deref(), Some("description-upd"));
+    assert_eq!(retrieved.link.as_deref(), Some("link-upd"));
+    assert_eq!(retrieved.status, EpisodeStatus::New);
+    assert_eq!(retrieved.duration, Some(Duration::from_secs(300)));
+    assert_eq!(retrieved.publication_date, None);
+    assert_eq!(&retrieved.media_url, "http://example.com/feed2.xml");
+
+    let mut writer = provider.writer(feed_id).unwrap();
+    let episode_id_2 = writer
+        .set_episode_metadata(&EpisodeMetadata {
+            title: Some("second-title"),
+            description: Some("second-description"),
+            link: None,
+            guid: "guid-2",
+            duration: None,
+            publication_date: None,
+            episode_number: None,
+            season_number: None,
+            media_url: "http://example.com/feed3.xml",
+            block: false,
+        })
+        .unwrap();
+    writer.close().unwrap();
+
+    let mut episodes = provider
+        .get_episode_summaries(EpisodesQuery::default().feed_id(feed_id), 0..100)
+        .unwrap();
+    episodes.sort_by_key(|episode| episode.id.0);
+    assert_eq!(
+        episodes[0],
+        EpisodeSummary {
+            id: episode_id_1,
+            feed_id,
+            episode_number: Some(8),
+            season_number: None,
+            title: Some("title-upd".to_string()),
+            feed_title: None,
+            status: EpisodeSummaryStatus::New,
+            duration: Some(Duration::from_secs(300)),
+            publication_date: None,
+            is_hidden: false,
+        }
+    );
+    assert_eq!(
+        episodes[1],
+        EpisodeSummary {
+            id: episode_id_2,
+            feed_id,
+            episode_number: None,
+            season_number: None,
+            title: Some("second-title".to_string()),
+            feed_title: None,
+            status: EpisodeSummaryStatus::New,
+            duration: None,
+            publication_date: None,
+            is_hidden: false,
+        }
+    );
+}
+
+pub fn views(mut provider: impl DataProvider) {
+    let group_id = provider.create_group("News").unwrap().unwrap();
+    let query = EpisodesQuery::default()
+        .group_id(group_id)
+        .excluded_status(EpisodeSummaryStatus::Finished)
+        .published_within(Duration::from_secs(7 * 24 * 60 * 60));
+
+    let view_id = provider.create_view("Recent", &query).unwrap().unwrap();
+    assert!(provider.create_view("Recent", &query).unwrap().is_none());
+    assert_eq!(
+        provider.get_view_summaries().unwrap(),
+        vec![ViewSummary {
+            id: view_id,
+            name: "Recent".to_string(),
+            query,
+        }]
+    );
+
+    provider.rename_view(view_id, "Latest".to_string()).unwrap();
+    assert_eq!(provider.get_view_summaries().unwrap()[0].name, "Latest");
+
+    provider.delete_view(view_id).unwrap();
+    assert!(provider.get_view_summaries().unwrap().is_empty());
+}
+
+pub fn view_query_filters(mut provider: impl DataProvider) {
+    let days_ago = |days| Some(Utc::now() - chrono::Duration::days(days));
+    let (_, episode_ids) = feed_with_episodes(
+        &mut provider,
+        "http://example.com/feed.xml",
+        &[
+            ("recent", days_ago(1)),
+            ("old", days_ago(30)),
+            ("finished", days_ago(2)),
+        ],
+    );
+    provider
+        .set_episode_status(
+            EpisodesQuery::default().id(episode_ids[2]),
+            EpisodeStatus::Finished,
+        )
+        .unwrap();
+
+    let query = EpisodesQuery::default()
+        .excluded_status(EpisodeSummaryStatus::Finished)
+        .published_within(Duration::from_secs(7 * 24 * 60 * 60));
+    let episodes = provider.get_episode_summaries(query, 0..100).unwrap();
+    let ids: Vec<_> = episodes.iter().map(|episode| episode.id).collect();
+    assert_eq!(ids, vec![episode_ids[0]]);
+}
+
+pub fn queue(mut provider: impl DataProvider) {
+    let (_, episode_ids) = feed_with_episodes(
+        &mut provider,
+        "http://example.com/feed.xml",
+        &[("first", None), ("second", None), ("third", None)],
+    );
+
+    for episode_id in &episode_ids {
+        provider.add_to_queue(*episode_id).unwrap();
+    }
+    provider.add_to_queue(episode_ids[0]).unwrap();
+    provider.set_queue_position(episode_ids[2], 1).unwrap();
+    provider.remove_from_queue(episode_ids[0]).unwrap();
+
+    let queue = provider.get_queue().unwrap();
+    let ids: Vec<_> = queue.iter().map(|episode| episode.id).collect();
+    assert_eq!(ids, vec![episode_ids[2], episode_ids[1]]);
+    assert_eq!(queue[0].title.as_deref(), Some("third"));
+
+    provider.clear_queue().unwrap();
+    assert!(provider.get_queue().unwrap().is_empty());
+}
+
+pub fn next_episode<P: DataProvider>(mut provider: P) {
+    let days_ago = |days| Some(Utc::now() - chrono::Duration::days(days));
+    let (feed_id, mut episode_ids) = feed_with_episodes(
+        &mut provider,
+        "http://example.com/feed.xml",
+        &[
+            ("first", days_ago(10)),
+            ("second", days_ago(9)),
+            ("third", days_ago(8)),
+            ("fourth", days_ago(7)),
+            ("fifth", days_ago(6)),
+            ("undated", None),
+        ],
+    );
+    let undated_id = episode_ids.pop().unwrap();
+    provider
+        .set_episode_status(
+            EpisodesQuery::default().id(episode_ids[1]),
+            EpisodeStatus::Finished,
+        )
+        .unwrap();
+    provider
+        .set_episode_hidden(EpisodesQuery::default().id(episode_ids[3]), true)
+        .unwrap();
+
+    let query = EpisodesQuery::default().feed_id(feed_id);
+    let next = |provider: &mut P, query: &EpisodesQuery, index: usize| {
+        provider
+            .get_next_episode(query.clone(), episode_ids[index])
+            .unwrap()
+    };
+    assert_eq!(next(&mut provider, &query, 4), Some(episode_ids[2]));
+    assert_eq!(next(&mut provider, &query, 2), Some(episode_ids[0]));
+    assert_eq!(next(&mut provider, &query, 0), Some(undated_id));
+    let after_undated = provider.get_next_episode(query.clone(), undated_id);
+    assert_eq!(after_undated.unwrap(), None);
+
+    let query = query.reversed_order(true);
+    let after_undated = provider.get_next_episode(query.clone(), undated_id);
+    assert_eq!(after_undated.unwrap(), Some(episode_ids[0]));
+    assert_eq!(next(&mut provider, &query, 0), Some(episode_ids[2]));
+    assert_eq!(next(&mut provider, &query, 2), Some(episode_ids[4]));
+    assert_eq!(next(&mut provider, &query, 4), None);
+}
+
+pub fn bookmarks(mut provider: impl DataProvider) {
+    let (feed_id, episode_ids) = feed_with_episodes(
+        &mut provider,
+        "http://example.com/feed.xml",
+        &[("episode", None)],
+    );
+    let episode_id = episode_ids[0];
+    provider
+        .rename_feed(feed_id, Some("Feed title".to_string()))
+        .unwrap();
+
+    let later = provider
+        .create_bookmark(episode_id, Duration::from_secs(300), Some("later"))
+        .unwrap();
+    let earlier = provider
+        .create_bookmark(episode_id, Duration::from_secs(60), None)
+        .unwrap();
+
+    let bookmarks = provider.get_bookmarks().unwrap();
+    assert_eq!(
+        bookmarks
+            .iter()
+            .map(|bookmark| (bookmark.id, bookmark.position, bookmark.note.as_deref()))
+            .collect::<Vec<_>>(),
+        vec![
+            (earlier, Duration::from_secs(60), None),
+            (later, Duration::from_secs(300), Some("later")),
+        ]
+    );
+    assert_eq!(bookmarks[0].episode_title.as_deref(), Some("episode"));
+    assert_eq!(bookmarks[0].feed_title.as_deref(), Some("Feed title"));
+
+    provider.delete_bookmark(earlier).unwrap();
+    assert_eq!(provider.get_bookmarks().unwrap().len(), 1);
+
+    provider.delete_feed(feed_id).unwrap();
+    assert!(provider.get_bookmarks().unwrap().is_empty());
+}
+
+pub fn listening_stats(mut provider: impl DataProvider) {
+    let (feed_id, episode_ids) = feed_with_episodes(
+        &mut provider,
+        "http://example.com/feed.xml",
+        &[("first", None), ("second", None)],
+    );
+    provider
+        .set_episode_status(
+            EpisodesQuery::default().id(episode_ids[0]),
+            EpisodeStatus::Finished,
+        )
+        .unwrap();
+
+    let mut session = ListeningSession {
+        episode_id: episode_ids[0],
+        start_position: Duration::ZERO,
+        end_position: Duration::from_secs(1800),
+        started_at: Utc.ymd(2022, 1, 9).and_hms(10, 0, 0),
+        ended_at: Utc.ymd(2022, 1, 9).and_hms(10, 30, 0),
+        rate: 1.0,
+    };
+    provider.save_listening_session(&session).unwrap();
+    // The sessions are only appended, saving the same session again doesn't replace it
+    session.end_position = Duration::from_secs(600);
+    session.ended_at = Utc.ymd(2022, 1, 9).and_hms(10, 10, 0);
+    provider.save_listening_session(&session).unwrap();
+    provider
+        .save_listening_session(&ListeningSession {
+            episode_id: episode_ids[1],
+            start_position: Duration::ZERO,
+            end_position: Duration::from_secs(3600),
+            started_at: Utc.ymd(2022, 1, 10).and_hms(8, 0, 0),
+            ended_at: Utc.ymd(2022, 1, 10).and_hms(8, 30, 0),
+            rate: 2.0,
+        })
+        .unwrap();
+
+    let stats = provider.get_listening_stats().unwrap();
+    assert_eq!(
+        stats.feeds,
+        vec![FeedListeningStats {
+            feed_id,
+            title: "http://example.com/feed.xml".to_string(),
+            listened: Duration::from_secs(3600),
+            episodes: 2,
+            sessions: 2,
+        }]
+    );
+    assert_eq!(
+        stats.weeks,
+        vec![
+            WeekListeningStats {
+                week_start: NaiveDate::from_ymd(2022, 1, 3),
+                listened: Duration::from_secs(1800),
+            },
+            WeekListeningStats {
+                week_start: NaiveDate::from_ymd(2022, 1, 10),
+                listened: Duration::from_secs(1800),
+            },
+        ]
+    );
+    assert_eq!(stats.started_episodes, 2);
+    assert_eq!(stats.finished_episodes, 1);
+}
+
+pub fn feed_playback_settings(mut provider: impl DataProvider) {
+    let (feed_id, episode_ids) = feed_with_episodes(
+        &mut provider,
+        "http://example.com/feed.xml",
+        &[("episode", None)],
+    );
+    let episode_id = episode_ids[0];
+
+    let playback_data = provider
+        .get_episode_playback_data(episode_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(playback_data.playback_rate, None);
+    assert_eq!(playback_data.skip_intro, Duration::ZERO);
+    assert_eq!(playback_data.skip_outro, Duration::ZERO);
+
+    provider.set_feed_playback_rate(feed_id, Some(1.5)).unwrap();
+    provider
+        .set_feed_skip_intro(feed_id, Duration::from_secs(30))
+        .unwrap();
+    provider
+        .set_feed_skip_outro(feed_id, Duration::from_secs(60))
+        .unwrap();
+    let playback_data = provider
+        .get_episode_playback_data(episode_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(playback_data.playback_rate, Some(1.5));
+    assert_eq!(playback_data.skip_intro, Duration::from_secs(30));
+    assert_eq!(playback_data.skip_outro, Duration::from_secs(60));
+}
+
+pub fn tags<P: DataProvider>(mut provider: P) {
+    let mut feed_ids = Vec::new();
+    let mut episode_ids = Vec::new();
+    for (source, guids) in [
+        ("http://example.com/first.xml", ["first-a", "first-b"]),
+        ("http://example.com/second.xml", ["second-a", "second-b"]),
+    ] {
+        let episodes = [(guids[0], None), (guids[1], None)];
+        let (feed_id, ids) = feed_with_episodes(&mut provider, source, &episodes);
+        feed_ids.push(feed_id);
+        episode_ids.extend(ids);
+    }
+
+    provider
+        .add_tag(TagTarget::Feed(feed_ids[0]), "science")
+        .unwrap();
+    provider
+        .add_tag(TagTarget::Episode(episode_ids[3]), "science")
+        .unwrap();
+    provider
+        .add_tag(TagTarget::Episode(episode_ids[2]), "history")
+        .unwrap();
+    assert_eq!(
+        provider.get_tags().unwrap(),
+        vec!["history".to_string(), "science".to_string()]
+    );
+
+    let tagged = |provider: &mut P, tag: &str| {
+        let query = EpisodesQuery::default().tag(tag.to_string());
+        let mut ids: Vec<_> = provider
+            .get_episode_summaries(query, 0..100)
+            .unwrap()
+            .into_iter()
+            .map(|episode| episode.id)
+            .collect();
+        ids.sort_by_key(|id| id.as_i64());
+        ids
+    };
+    assert_eq!(
+        tagged(&mut provider, "science"),
+        vec![episode_ids[0], episode_ids[1], episode_ids[3]]
+    );
+    assert_eq!(tagged(&mut provider, "history"), vec![episode_ids[2]]);
+
+    provider
+        .remove_tag(TagTarget::Episode(episode_ids[2]), "history")
+        .unwrap();
+    assert_eq!(provider.get_tags().unwrap(), vec!["science".to_string()]);
+    assert_eq!(tagged(&mut provider, "history"), vec![]);
+}
+
+pub fn nested_groups<P: DataProvider>(mut provider: P) {
+    let tech_id = provider.create_group("Tech").unwrap().unwrap();
+    let news_id = provider.create_group("News").unwrap().unwrap();
+    let rust_id = provider.create_group("Rust").unwrap().unwrap();
+    assert!(provider.set_group_parent(rust_id, Some(tech_id)).unwrap());
+    assert!(!provider.set_group_parent(tech_id, Some(rust_id)).unwrap());
+    assert!(!provider.set_group_parent(tech_id, Some(tech_id)).unwrap());
+
+    let groups: Vec<_> = (provider.get_group_summaries().unwrap().into_iter())
+        .map(|group| (group.id, group.parent_id, group.depth))
+        .collect();
+    assert_eq!(
+        groups,
+        vec![
+            (tech_id, None, 0),
+            (rust_id, Some(tech_id), 1),
+            (news_id, None, 0)
+        ]
+    );
+
+    let (feed_id, _) = feed_with_episodes(
+        &mut provider,
+        "http://example.com/feed.xml",
+        &[("episode", None)],
+    );
+    provider.set_feed_for_group(Some(rust_id), feed_id).unwrap();
+
+    let count = |provider: &mut P, group_id| {
+        provider
+            .get_episode_summaries(EpisodesQuery::default().group_id(group_id), 0..100)
+            .unwrap()
+            .len()
+    };
+    assert_eq!(count(&mut provider, tech_id), 1);
+    assert_eq!(count(&mut provider, rust_id), 1);
+    assert_eq!(count(&mut provider, news_id), 0);
+
+    provider.set_group_collapsed(None, true).unwrap();
+    provider.set_group_collapsed(Some(news_id), false).unwrap();
+    let groups: Vec<_> = (provider.get_group_summaries().unwrap().into_iter())
+        .map(|group| (group.id, group.collapsed, group.new_count))
+        .collect();
+    assert_eq!(
+        groups,
+        vec![(tech_id, true, 1), (rust_id, true, 1), (news_id, false, 0)]
+    );
+
+    provider.delete_group(rust_id).unwrap();
+    let feeds = provider.get_feed_summaries().unwrap();
+    assert_eq!(feeds[0].group_id, Some(tech_id));
+}
+
+pub fn trash(mut provider: impl DataProvider) {
+    let feed_ids: Vec<_> = ["first", "second"]
+        .iter()
+        .map(|&feed| {
+            let source = format!("http://example.com/{}.xml", feed);
+            feed_with_episodes(&mut provider, &source, &[(feed, None)]).0
+        })
+        .collect();
+
+    provider.delete_feed(feed_ids[0]).unwrap();
+    let feeds = provider.get_feed_summaries().unwrap();
+    assert_eq!(feeds.len(), 1);
+    assert_eq!(feeds[0].id, feed_ids[1]);
+    let episodes = provider
+        .get_episode_summaries(EpisodesQuery::default(), 0..100)
+        .unwrap();
+    assert_eq!(episodes.len(), 1);
+    let trashed = provider.get_trashed_feeds().unwrap();
+    assert_eq!(trashed.len(), 1);
+    assert_eq!(trashed[0].id, feed_ids[0]);
+
+    provider.restore_feed(feed_ids[0]).unwrap();
+    assert_eq!(provider.get_feed_summaries().unwrap().len(), 2);
+    assert!(provider.get_trashed_feeds().unwrap().is_empty());
+
+    provider.delete_feed(feed_ids[0]).unwrap();
+    let restored = provider
+        .create_feed_pending(&NewFeedMetadata::new(
+            "http://example.com/first.xml".to_string(),
+        ))
+        .unwrap();
+    assert_eq!(restored, Some(feed_ids[0]));
+
+    provider.delete_feed(feed_ids[0]).unwrap();
+    let week_ago = Utc::now() - chrono::Duration::days(7);
+    assert_eq!(provider.purge_trash(Some(week_ago)).unwrap(), 0);
+    assert_eq!(provider.purge_trash(None).unwrap(), 1);
+    assert!(provider.get_trashed_feeds().unwrap().is_empty());
+    assert!(provider.get_feed(feed_ids[0]).unwrap().is_none());
+}
+
+pub fn set_source_relinks_episodes(mut provider: impl DataProvider) {
+    let feed_id = provider
+        .create_feed_pending(&NewFeedMetadata::new("http://example.com/old.xml".into()))
+        .unwrap()
+        .unwrap();
+    let other_id = provider
+        .create_feed_pending(&NewFeedMetadata::new("http://example.com/other.xml".into()))
+        .unwrap()
+        .unwrap();
+    let metadata = EpisodeMetadata {
+        title: Some("Episode"),
+        description: None,
+        link: None,
+        guid: "old-guid",
+        duration: None,
+        publication_date: None,
+        episode_number: None,
+        season_number: None,
+        media_url: "http://example.com/episode.mp3",
+        block: false,
+    };
+    let mut writer = provider.writer(feed_id).unwrap();
+    let episode_id = writer.set_episode_metadata(&metadata).unwrap();
+    writer.close().unwrap();
+    provider
+        .set_episode_status(
+            EpisodesQuery::default().id(episode_id),
+            EpisodeStatus::Finished,
+        )
+        .unwrap();
+
+    assert!(!provider
+        .set_feed_source(feed_id, "http://example.com/other.xml")
+        .unwrap());
+    assert!(provider
+        .set_feed_source(feed_id, "http://example.com/new.xml")
+        .unwrap());
+    assert_eq!(
+        provider.get_feed(feed_id).unwrap().unwrap().source,
+        "http://example.com/new.xml"
+    );
+    assert_eq!(
+        provider.get_feed(other_id).unwrap().unwrap().source,
+        "http://example.com/other.xml"
+    );
+    provider.delete_feed(other_id).unwrap();
+    assert!(provider
+        .set_feed_source(other_id, "http://example.com/trashed.xml")
+        .unwrap());
+    assert!(provider
+        .set_feed_source(feed_id, "http://example.com/trashed.xml")
+        .unwrap());
+    assert!(provider
+        .set_feed_source(feed_id, "http://example.com/new.xml")
+        .unwrap());
+
+    let new_metadata = EpisodeMetadata {
+        guid: "new-guid",
+        ..metadata
+    };
+    let mut writer = provider.writer(feed_id).unwrap();
+    writer.relink_episode(&new_metadata).unwrap();
+    let relinked_id = writer.set_episode_metadata(&new_metadata).unwrap();
+    writer.close().unwrap();
+
+    assert_eq!(relinked_id, episode_id);
+    let episode = provider.get_episode(episode_id).unwrap().unwrap();
+    assert_eq!(episode.status, EpisodeStatus::Finished);
+    assert_eq!(
+        provider
+            .count_episodes(EpisodesQuery::default().feed_id(feed_id))
+            .unwrap(),
+        1
+    );
+}
+
+pub fn merge_feed(mut provider: impl DataProvider) {
+    fn episode<'a>(guid: &'a str, media_url: &'a str) -> EpisodeMetadata<'a> {
+        EpisodeMetadata {
+            title: Some(guid),
+            description: None,
+            link: None,
+            guid,
+            duration: None,
+            publication_date: None,
+            episode_number: None,
+            season_number: None,
+            media_url,
+            block: false,
+        }
+    }
+
+    let feed_id = provider
+        .create_feed_pending(&NewFeedMetadata::new("http://example.com/feed.xml".into()))
+        .unwrap()
+        .unwrap();
+    let target_id = provider
+        .create_feed_pending(&NewFeedMetadata::new("https://example.org/feed.xml".into()))
+        .unwrap()
+        .unwrap();
+    let group_id = provider.create_group("group").unwrap().unwrap();
+    provider
+        .set_feed_for_group(Some(group_id), feed_id)
+        .unwrap();
+
+    let mut writer = provider.writer(feed_id).unwrap();
+    let by_guid = writer
+        .set_episode_metadata(&episode("1", "http://a/1.mp3"))
+        .unwrap();
+    let by_media = writer
+        .set_episode_metadata(&episode("2", "http://a/2.mp3"))
+        .unwrap();
+    let unmatched_id = writer
+        .set_episode_metadata(&episode("3", "http://a/3.mp3"))
+        .unwrap();
+    writer.close().unwrap();
+
+    let mut writer = provider.writer(target_id).unwrap();
+    let target_by_guid = writer
+        .set_episode_metadata(&episode("1", "http://b/1.mp3"))
+        .unwrap();
+    let target_by_media = writer
+        .set_episode_metadata(&episode("b2", "http://a/2.mp3"))
+        .unwrap();
+    writer
+        .set_episode_metadata(&episode("b4", "http://b/4.mp3"))
+        .unwrap();
+    writer.close().unwrap();
+
+    provider
+        .set_episode_status(
+            EpisodesQuery::default().id(by_guid),
+            EpisodeStatus::Finished,
+        )
+        .unwrap();
+    let started = EpisodeStatus::Started(Duration::from_secs(30));
+    provider
+        .set_episode_status(EpisodesQuery::default().id(by_media), started.clone())
+        .unwrap();
+    provider
+        .create_bookmark(by_media, Duration::from_secs(10), Some("note"))
+        .unwrap();
+    provider
+        .set_episode_hidden(EpisodesQuery::default().id(by_guid), true)
+        .unwrap();
+
+    assert_eq!(provider.merge_feed(feed_id, target_id).unwrap(), 2);
+
+    let trashed = provider.get_trashed_feeds().unwrap();
+    assert_eq!(trashed.len(), 1);
+    assert_eq!(trashed[0].id, feed_id);
+    let unmatched = provider.get_episode(unmatched_id).unwrap().unwrap();
+    assert_eq!(unmatched.feed_id, feed_id);
+    let target = provider.get_feed(target_id).unwrap().unwrap();
+    assert_eq!(target.group_id, Some(group_id));
+    let episode = provider.get_episode(target_by_guid).unwrap().unwrap();
+    assert_eq!(episode.status, EpisodeStatus::Finished);
+    let states = provider
+        .get_episode_states(EpisodesQuery::default().feed_id(target_id))
+        .unwrap();
+    let hidden: Vec<_> = (states.into_iter())
+        .filter(|state| state.hidden)
+        .map(|state| state.id)
+        .collect();
+    assert_eq!(hidden, vec![target_by_guid]);
+    let episode = provider.get_episode(target_by_media).unwrap().unwrap();
+    assert_eq!(episode.status, started);
+    let bookmarks = provider.get_bookmarks().unwrap();
+    assert_eq!(bookmarks.len(), 1);
+    assert_eq!(bookmarks[0].episode_id, target_by_media);
+    assert_eq!(
+        provider
+            .count_episodes(EpisodesQuery::default().feed_id(target_id))
+            .unwrap(),
+        3
+    );
+}
+
+pub fn merge_feed_keeps_progress(mut provider: impl DataProvider) {
+    let guids = [("finished", None), ("later", None), ("earlier", None)];
+    let (feed_id, source_ids) =
+        feed_with_episodes(&mut provider, "http://example.com/feed.xml", &guids);
+    let (target_id, target_ids) =
+        feed_with_episodes(&mut provider, "https://example.org/feed.xml", &guids);
+
+    let started = |minutes: u64| EpisodeStatus::Started(Duration::from_secs(minutes * 60));
+    let statuses = [
+        (EpisodeStatus::Finished, started(1)),
+        (started(30), started(1)),
+        (started(1), started(30)),
+    ];
+    for ((source_status, target_status), (source_id, target_id)) in
+        statuses.iter().zip(source_ids.iter().zip(&target_ids))
+    {
+        provider
+            .set_episode_status(EpisodesQuery::default().id(*source_id), source_status.clone())
+            .unwrap();
+        provider
+            .set_episode_status(EpisodesQuery::default().id(*target_id), target_status.clone())
+            .unwrap();
+    }
+
+    assert_eq!(provider.merge_feed(feed_id, target_id).unwrap(), 3);
+    let merged: Vec<_> = (target_ids.iter())
+        .map(|id| provider.get_episode(*id).unwrap().unwrap().status)
+        .collect();
+    assert_eq!(merged, vec![EpisodeStatus::Finished, started(30), started(30)]);
+}
+
+pub fn episode_snapshots(mut provider: impl DataProvider) {
+    let (feed_id, episode_ids) = feed_with_episodes(
+        &mut provider,
+        "http://example.com/feed.xml",
+        &[("first", None), ("second", None)],
+    );
+    provider
+        .rename_feed(feed_id, Some("Renamed".to_string()))
+        .unwrap();
+    provider
+        .add_tag(TagTarget::Episode(episode_ids[1]), "science")
+        .unwrap();
+    provider
+        .set_episode_status(
+            EpisodesQuery::default().id(episode_ids[0]),
+            EpisodeStatus::Started(Duration::from_secs(90)),
+        )
+        .unwrap();
+    provider
+        .set_episode_hidden(EpisodesQuery::default().id(episode_ids[1]), true)
+        .unwrap();
+
+    let feeds = provider.get_feed_snapshots().unwrap();
+    assert_eq!(feeds.len(), 1);
+    assert_eq!(feeds[0].id, feed_id);
+    assert_eq!(feeds[0].source, "http://example.com/feed.xml");
+    assert_eq!(feeds[0].title_override.as_deref(), Some("Renamed"));
+
+    let mut episodes: Vec<_> = (provider.get_episode_snapshots(feed_id).unwrap().into_iter())
+        .map(|episode| (episode.id, episode.guid, episode.hidden, episode.tags))
+        .collect();
+    episodes.sort_by_key(|episode| episode.0.as_i64());
+    assert_eq!(
+        episodes,
+        vec![
+            (episode_ids[0], "first".to_string(), false, vec![]),
+            (
+                episode_ids[1],
+                "second".to_string(),
+                true,
+                vec!["science".to_string()]
+            ),
+        ]
+    );
+
+    let query = EpisodesQuery::default().feed_id(feed_id);
+    let mut states = provider.get_episode_states(query.clone()).unwrap();
+    states.sort_by_key(|state| state.id.as_i64());
+    provider
+        .set_episode_status(query.clone(), EpisodeStatus::Finished)
+        .unwrap();
+    provider.set_episode_hidden(query.clone(), false).unwrap();
+
+    let updated = provider.restore_episode_states(&states).unwrap();
+    assert_eq!(updated, vec![feed_id].into_iter().collect::<HashSet<_>>());
+    let mut restored = provider.get_episode_states(query).unwrap();
+    restored.sort_by_key(|state| state.id.as_i64());
+    assert_eq!(restored, states);
+}
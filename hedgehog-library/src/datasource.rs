@@ -1,30 +1,89 @@
 use crate::actor::UpdateQuery;
+use crate::cache::EpisodesCache;
+use crate::feed_url;
 use crate::metadata::{EpisodeMetadata, FeedMetadata};
 use crate::model::{
-    Episode, EpisodeId, EpisodePlaybackData, EpisodeStatus, EpisodeSummary, EpisodeSummaryStatus,
-    EpisodesListMetadata, Feed, FeedId, FeedOMPLEntry, FeedStatus, FeedSummary, FeedView, GroupId,
-    GroupSummary,
+    Bookmark, BookmarkId, Episode, EpisodeId, EpisodePlaybackData, EpisodeSnapshot, EpisodeState,
+    EpisodeStatus, EpisodeSummary, EpisodeSummaryStatus, EpisodesListMetadata, Feed, FeedId,
+    FeedOMPLEntry, FeedSnapshot, FeedStatus, FeedSummary, FeedView, GroupId, GroupSummary,
+    ListeningSession, ListeningStats, TagTarget, TrashedFeed, ViewId, ViewSummary,
 };
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::marker::Unpin;
 use std::ops::Range;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 #[error(transparent)]
 pub enum QueryError {
     #[error(transparent)]
-    SqliteError(#[from] rusqlite::Error),
+    SqliteError(rusqlite::Error),
+
+    #[error("constraint violation, {0}")]
+    ConstraintViolation(rusqlite::Error),
+
+    #[error("database is corrupted, {0}")]
+    Corrupted(rusqlite::Error),
+
+    #[error("database is locked by another process, {0}")]
+    Busy(rusqlite::Error),
+
+    #[error("requested item does not exist")]
+    NotFound,
+
+    #[error("I/O error, {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("database reader is not available")]
+    ReaderUnavailable(#[from] actix::MailboxError),
+}
+
+impl QueryError {
+    /// Returns true if the same query may succeed when repeated later.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            QueryError::Busy(_) | QueryError::Io(_) | QueryError::ReaderUnavailable(_)
+        )
+    }
+}
+
+impl From<rusqlite::Error> for QueryError {
+    fn from(error: rusqlite::Error) -> Self {
+        use rusqlite::ErrorCode;
+
+        let code = match &error {
+            rusqlite::Error::SqliteFailure(failure, _) => failure.code,
+            rusqlite::Error::QueryReturnedNoRows => return QueryError::NotFound,
+            _ => return QueryError::SqliteError(error),
+        };
+        match code {
+            ErrorCode::ConstraintViolation => QueryError::ConstraintViolation(error),
+            ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase => QueryError::Corrupted(error),
+            ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked => QueryError::Busy(error),
+            ErrorCode::SystemIoFailure | ErrorCode::DiskFull | ErrorCode::CannotOpen => {
+                QueryError::Io(std::io::Error::new(std::io::ErrorKind::Other, error))
+            }
+            _ => QueryError::SqliteError(error),
+        }
+    }
 }
 
 pub type DbResult<T> = Result<T, QueryError>;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
 pub struct EpisodesQuery {
     pub(crate) episode_id: Option<EpisodeId>,
     pub(crate) feed_id: Option<FeedId>,
     pub(crate) group_id: Option<GroupId>,
     pub(crate) status: Option<EpisodeSummaryStatus>,
+    pub(crate) excluded_status: Option<EpisodeSummaryStatus>,
+    pub(crate) published_within: Option<Duration>,
+    pub(crate) tag: Option<String>,
     pub(crate) with_hidden: bool,
     pub(crate) include_feed_title: bool,
     pub(crate) reversed_order: bool,
@@ -37,6 +96,9 @@ impl Default for EpisodesQuery {
             feed_id: None,
             group_id: None,
             status: None,
+            excluded_status: None,
+            published_within: None,
+            tag: None,
             with_hidden: true,
             include_feed_title: false,
             reversed_order: false,
@@ -65,6 +127,21 @@ impl EpisodesQuery {
         self
     }
 
+    pub fn excluded_status(mut self, status: EpisodeSummaryStatus) -> Self {
+        self.excluded_status = Some(status);
+        self
+    }
+
+    pub fn published_within(mut self, duration: Duration) -> Self {
+        self.published_within = Some(duration);
+        self
+    }
+
+    pub fn tag(mut self, tag: String) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
     pub fn include_feed_title(mut self) -> Self {
         self.include_feed_title = true;
         self
@@ -80,12 +157,13 @@ impl EpisodesQuery {
         self
     }
 
-    pub fn from_feed_view(feed_id: FeedView<FeedId, GroupId>) -> Self {
+    pub fn from_feed_view(feed_id: FeedView<FeedId, GroupId, EpisodesQuery>) -> Self {
         match feed_id {
             FeedView::All => EpisodesQuery::default().include_feed_title(),
             FeedView::New => EpisodesQuery::default()
                 .status(EpisodeSummaryStatus::New)
                 .include_feed_title(),
+            FeedView::View(query) => query.include_feed_title(),
             FeedView::Feed(feed_id) => EpisodesQuery::default().feed_id(feed_id),
             FeedView::Group(feed_id) => EpisodesQuery::default()
                 .group_id(feed_id)
@@ -104,7 +182,7 @@ pub struct NewFeedMetadata {
 impl NewFeedMetadata {
     pub fn new(source: String) -> Self {
         NewFeedMetadata {
-            source,
+            source: feed_url::normalize(&source),
             title: None,
             link: None,
         }
@@ -122,15 +200,29 @@ impl NewFeedMetadata {
 }
 
 pub trait DataProvider: Unpin {
+    /// Returns `true` if the data was modified by another process since the last check. Updates
+    /// of the playback positions alone are not reported.
+    fn check_external_changes(&mut self) -> DbResult<bool>;
+    /// Returns the cache of the episodes lists, which can be filled with data read through
+    /// another connection.
+    fn episodes_cache(&mut self) -> Option<&mut dyn EpisodesCache> {
+        None
+    }
+
     fn get_feed(&mut self, id: FeedId) -> DbResult<Option<Feed>>;
     fn get_feed_summaries(&mut self) -> DbResult<Vec<FeedSummary>>;
     fn get_feed_opml_entries(&mut self) -> DbResult<Vec<FeedOMPLEntry>>;
+    /// Returns the feeds outside of the trash with all of their settings and tags.
+    fn get_feed_snapshots(&mut self) -> DbResult<Vec<FeedSnapshot>>;
+    /// Returns every episode of the feed, including hidden ones, with its state and tags.
+    fn get_episode_snapshots(&mut self, feed_id: FeedId) -> DbResult<Vec<EpisodeSnapshot>>;
     fn get_update_sources(&mut self, update: UpdateQuery) -> DbResult<Vec<(FeedId, String)>>;
     fn get_new_episodes_count(
         &mut self,
         feed_ids: HashSet<FeedId>,
     ) -> DbResult<HashMap<FeedId, usize>>;
-    fn rename_feed(&mut self, feed_id: FeedId, name: String) -> DbResult<()>;
+    /// Sets the title override of a feed, `None` restores the title from the feed's metadata.
+    fn rename_feed(&mut self, feed_id: FeedId, name: Option<String>) -> DbResult<()>;
 
     fn create_group(&mut self, name: &str) -> DbResult<Option<GroupId>>;
     fn get_group_summaries(&mut self) -> DbResult<Vec<GroupSummary>>;
@@ -138,6 +230,37 @@ pub trait DataProvider: Unpin {
     fn rename_group(&mut self, group_id: GroupId, name: String) -> DbResult<()>;
     fn delete_group(&mut self, group_id: GroupId) -> DbResult<()>;
     fn set_group_position(&mut self, group_id: GroupId, position: usize) -> DbResult<()>;
+    fn set_group_parent(&mut self, group_id: GroupId, parent_id: Option<GroupId>) -> DbResult<bool>;
+    /// Updates the collapsed state of a group, or of every group if `group_id` is `None`.
+    fn set_group_collapsed(&mut self, group_id: Option<GroupId>, collapsed: bool) -> DbResult<()>;
+
+    fn create_view(&mut self, name: &str, query: &EpisodesQuery) -> DbResult<Option<ViewId>>;
+    fn get_view_summaries(&mut self) -> DbResult<Vec<ViewSummary>>;
+    fn rename_view(&mut self, view_id: ViewId, name: String) -> DbResult<()>;
+    fn delete_view(&mut self, view_id: ViewId) -> DbResult<()>;
+
+    fn get_queue(&mut self) -> DbResult<Vec<EpisodeSummary>>;
+    fn add_to_queue(&mut self, episode_id: EpisodeId) -> DbResult<()>;
+    fn remove_from_queue(&mut self, episode_id: EpisodeId) -> DbResult<()>;
+    fn set_queue_position(&mut self, episode_id: EpisodeId, position: usize) -> DbResult<()>;
+    fn clear_queue(&mut self) -> DbResult<()>;
+
+    fn create_bookmark(
+        &mut self,
+        episode_id: EpisodeId,
+        position: Duration,
+        note: Option<&str>,
+    ) -> DbResult<BookmarkId>;
+    fn get_bookmarks(&mut self) -> DbResult<Vec<Bookmark>>;
+    fn delete_bookmark(&mut self, bookmark_id: BookmarkId) -> DbResult<()>;
+
+    fn get_tags(&mut self) -> DbResult<Vec<String>>;
+    fn add_tag(&mut self, target: TagTarget, name: &str) -> DbResult<()>;
+    fn remove_tag(&mut self, target: TagTarget, name: &str) -> DbResult<()>;
+
+    fn save_listening_session(&mut self, session: &ListeningSession) -> DbResult<()>;
+    fn get_listening_sessions(&mut self) -> DbResult<Vec<ListeningSession>>;
+    fn get_listening_stats(&mut self) -> DbResult<ListeningStats>;
 
     fn get_episode(&mut self, episode_id: EpisodeId) -> DbResult<Option<Episode>>;
     fn get_episode_playback_data(
@@ -154,11 +277,35 @@ pub trait DataProvider: Unpin {
         range: Range<usize>,
     ) -> DbResult<Vec<EpisodeSummary>>;
     fn count_episodes(&mut self, query: EpisodesQuery) -> DbResult<usize>;
+    fn get_next_episode(
+        &mut self,
+        query: EpisodesQuery,
+        episode_id: EpisodeId,
+    ) -> DbResult<Option<EpisodeId>>;
 
+    /// Creates a feed that is yet to be fetched. Returns `None` if the feed with the same source,
+    /// up to URL normalization, already exists.
     fn create_feed_pending(&mut self, data: &NewFeedMetadata) -> DbResult<Option<FeedId>>;
+    /// Looks for another feed with the same title and link as the feed. Returns the title of the
+    /// existing feed if there is one.
+    fn find_duplicate_feed(&mut self, feed_id: FeedId) -> DbResult<Option<String>>;
+    /// Changes the URL from which the feed is fetched. Returns `false` if another feed in the
+    /// library already uses this URL, feeds in the trash are not taken into account.
+    fn set_feed_source(&mut self, feed_id: FeedId, source: &str) -> DbResult<bool>;
     fn delete_feed(&mut self, id: FeedId) -> DbResult<()>;
+    /// Moves episodes' state, bookmarks, tags, and the group of the feed into the target feed
+    /// and then moves the feed to the trash. Returns the number of matched episodes.
+    fn merge_feed(&mut self, feed_id: FeedId, target_id: FeedId) -> DbResult<usize>;
+    fn restore_feed(&mut self, id: FeedId) -> DbResult<()>;
+    fn get_trashed_feeds(&mut self) -> DbResult<Vec<TrashedFeed>>;
+    /// Permanently removes trashed feeds deleted before `deleted_before`, or all of them if it's
+    /// `None`. Returns the number of removed feeds.
+    fn purge_trash(&mut self, deleted_before: Option<DateTime<Utc>>) -> DbResult<usize>;
     fn set_feed_status(&mut self, feed_id: FeedId, status: FeedStatus) -> DbResult<()>;
     fn set_feed_enabled(&mut self, feed_id: FeedId, enabled: bool) -> DbResult<()>;
+    fn set_feed_playback_rate(&mut self, feed_id: FeedId, rate: Option<f64>) -> DbResult<()>;
+    fn set_feed_skip_intro(&mut self, feed_id: FeedId, duration: Duration) -> DbResult<()>;
+    fn set_feed_skip_outro(&mut self, feed_id: FeedId, duration: Duration) -> DbResult<()>;
     fn reverse_feed_order(&mut self, feed_id: FeedId) -> DbResult<()>;
 
     fn set_episode_status(
@@ -167,6 +314,10 @@ pub trait DataProvider: Unpin {
         status: EpisodeStatus,
     ) -> DbResult<HashSet<FeedId>>;
     fn set_episode_hidden(&mut self, query: EpisodesQuery, hidden: bool) -> DbResult<()>;
+    fn get_episode_states(&mut self, query: EpisodesQuery) -> DbResult<Vec<EpisodeState>>;
+    /// Overwrites the status and the hidden flag of episodes. Returns the ids of the feeds
+    /// containing updated episodes.
+    fn restore_episode_states(&mut self, states: &[EpisodeState]) -> DbResult<HashSet<FeedId>>;
 
     fn writer<'a>(&'a mut self, feed_id: FeedId) -> DbResult<Box<dyn EpisodeWriter + 'a>>;
 }
@@ -174,6 +325,9 @@ pub trait DataProvider: Unpin {
 pub trait EpisodeWriter {
     fn set_feed_metadata(&mut self, metadata: &FeedMetadata) -> DbResult<()>;
     fn set_episode_metadata(&mut self, metadata: &EpisodeMetadata) -> DbResult<EpisodeId>;
+    /// Assigns the episode's GUID to an existing episode with the same media URL, or the same
+    /// title and publication date, so that its state is kept when the GUIDs differ.
+    fn relink_episode(&mut self, metadata: &EpisodeMetadata) -> DbResult<()>;
     fn delete_episode(&mut self, guid: &str) -> DbResult<()>;
     fn close(self: Box<Self>) -> DbResult<()>;
 }
@@ -0,0 +1,139 @@
+use reqwest::Url;
+
+const FEED_SCHEMES: &[&str] = &["feed", "itpc", "pcast", "podcast"];
+
+const TRACKING_PARAMETERS: &[&str] = &["fbclid", "gclid", "igshid", "mc_cid", "mc_eid"];
+
+const REDIRECT_HOSTS: &[(&str, &str)] = &[
+    ("feedproxy.google.com", "feeds.feedburner.com"),
+    ("feeds2.feedburner.com", "feeds.feedburner.com"),
+];
+
+fn is_tracking_parameter(name: &str) -> bool {
+    name.starts_with("utm_") || TRACKING_PARAMETERS.contains(&name)
+}
+
+fn replace_feed_scheme(source: &str) -> String {
+    if let Some((scheme, rest)) = source.split_once(':') {
+        if FEED_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str()) {
+            if rest.starts_with("//") {
+                return format!("http:{}", rest);
+            }
+            return rest.to_string();
+        }
+    }
+    source.to_string()
+}
+
+fn parse(source: &str) -> Option<Url> {
+    let mut url = Url::parse(&replace_feed_scheme(source.trim())).ok()?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return None;
+    }
+    url.set_fragment(None);
+
+    let redirect = (url.host_str())
+        .and_then(|host| REDIRECT_HOSTS.iter().find(|(from, _)| *from == host))
+        .map(|(_, to)| *to);
+    if let Some(host) = redirect {
+        url.set_host(Some(host)).ok()?;
+    }
+
+    // Only the tracking parameters are cut out of the query, the rest of it is kept as written
+    // since re-encoding the other parameters may change the URL in a way the server doesn't
+    // expect.
+    if let Some(query) = url.query() {
+        let parameters: Vec<&str> = (query.split('&'))
+            .filter(|parameter| {
+                let name = parameter.split('=').next().unwrap_or_default();
+                !is_tracking_parameter(name)
+            })
+            .collect();
+        if parameters.len() != query.split('&').count() {
+            let query = parameters.join("&");
+            url.set_query(Some(query.as_str()).filter(|query| !query.is_empty()));
+        }
+    }
+    Some(url)
+}
+
+/// Brings the feed URL to the canonical form: podcast-specific schemes are replaced with HTTP,
+/// tracking parameters and the fragment are removed, and known redirecting hosts are replaced
+/// with their targets. Sources that are not HTTP URLs are only trimmed.
+pub(crate) fn normalize(source: &str) -> String {
+    match parse(source) {
+        Some(url) => url.to_string(),
+        None => source.trim().to_string(),
+    }
+}
+
+/// Whether the source is an HTTP URL or can be turned into one.
+pub(crate) fn is_valid(source: &str) -> bool {
+    parse(source).is_some()
+}
+
+/// Returns a string which is equal for URLs that most likely point to the same feed, ignoring the
+/// scheme and the trailing slash.
+pub(crate) fn comparison_key(source: &str) -> String {
+    let url = match parse(source) {
+        Some(url) => url,
+        None => return source.trim().to_string(),
+    };
+    let mut key = url.host_str().unwrap_or_default().to_string();
+    if let Some(port) = url.port() {
+        key.push_str(&format!(":{}", port));
+    }
+    key.push_str(url.path().trim_end_matches('/'));
+    if let Some(query) = url.query() {
+        key.push('?');
+        key.push_str(query);
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{comparison_key, is_valid, normalize};
+
+    #[test]
+    fn normalizes_feed_urls() {
+        assert_eq!(
+            normalize(" feed://example.com/feed.xml#latest "),
+            "http://example.com/feed.xml"
+        );
+        assert_eq!(
+            normalize("feed:https://example.com/feed.xml"),
+            "https://example.com/feed.xml"
+        );
+        assert_eq!(
+            normalize("https://EXAMPLE.com/feed?utm_source=share&format=xml&fbclid=1"),
+            "https://example.com/feed?format=xml"
+        );
+        assert_eq!(
+            normalize("https://example.com/feed?key=a%2Fb+c&utm_source=share&token=x%3D"),
+            "https://example.com/feed?key=a%2Fb+c&token=x%3D"
+        );
+        assert_eq!(
+            normalize("http://feedproxy.google.com/podcast?utm_medium=rss"),
+            "http://feeds.feedburner.com/podcast"
+        );
+        assert_eq!(normalize("not a url"), "not a url");
+    }
+
+    #[test]
+    fn validates_feed_urls() {
+        assert!(is_valid("https://example.com/feed.xml"));
+        assert!(is_valid("podcast://example.com/feed.xml"));
+        assert!(!is_valid("ftp://example.com/feed.xml"));
+        assert!(!is_valid("not a url"));
+    }
+
+    #[test]
+    fn compares_url_variants() {
+        let key = comparison_key("https://example.com/podcast/");
+        assert_eq!(comparison_key("http://example.com/podcast"), key);
+        assert_eq!(comparison_key("itpc://example.com/podcast?utm_campaign=x"), key);
+        assert_ne!(comparison_key("https://example.com/other"), key);
+        assert_ne!(comparison_key("https://example.com:8080/podcast"), key);
+    }
+}
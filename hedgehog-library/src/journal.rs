@@ -0,0 +1,243 @@
+use crate::datasource::{DataProvider, DbResult};
+use crate::model::{EpisodeState, EpisodeStatus, FeedId, GroupId};
+use crate::EpisodesQuery;
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+
+const JOURNAL_CAPACITY: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Change {
+    SetStatus(EpisodesQuery, EpisodeStatus),
+    SetHidden(EpisodesQuery, bool),
+    SetGroup(Option<GroupId>, FeedId),
+    RenameFeed(FeedId, String),
+    ReverseFeedOrder(FeedId),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::SetStatus(_, _) => f.write_str("status change"),
+            Change::SetHidden(_, true) => f.write_str("hiding episodes"),
+            Change::SetHidden(_, false) => f.write_str("unhiding episodes"),
+            Change::SetGroup(_, _) => f.write_str("group change"),
+            Change::RenameFeed(_, _) => f.write_str("feed rename"),
+            Change::ReverseFeedOrder(_) => f.write_str("episodes order reversal"),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum PreviousState {
+    Episodes(Vec<EpisodeState>),
+    Group(Option<GroupId>),
+    Title(Option<String>),
+    Nothing,
+}
+
+#[derive(Debug)]
+struct JournalEntry {
+    change: Change,
+    previous: PreviousState,
+}
+
+impl JournalEntry {
+    fn apply(data: &mut dyn DataProvider, change: Change) -> DbResult<(Self, HashSet<FeedId>)> {
+        let mut updated_feeds = HashSet::new();
+        let previous = match &change {
+            Change::SetStatus(query, status) => {
+                let previous = data.get_episode_states(query.clone())?;
+                updated_feeds = data.set_episode_status(query.clone(), status.clone())?;
+                PreviousState::Episodes(previous)
+            }
+            Change::SetHidden(query, hidden) => {
+                let previous = data.get_episode_states(query.clone())?;
+                data.set_episode_hidden(query.clone(), *hidden)?;
+                PreviousState::Episodes(previous)
+            }
+            Change::SetGroup(group_id, feed_id) => {
+                let previous = data.get_feed(*feed_id)?.and_then(|feed| feed.group_id);
+                data.set_feed_for_group(*group_id, *feed_id)?;
+                PreviousState::Group(previous)
+            }
+            Change::RenameFeed(feed_id, name) => {
+                let previous = data
+                    .get_feed(*feed_id)?
+                    .filter(|feed| feed.title_overriden)
+                    .and_then(|feed| feed.title);
+                data.rename_feed(*feed_id, Some(name.clone()))?;
+                PreviousState::Title(previous)
+            }
+            Change::ReverseFeedOrder(feed_id) => {
+                data.reverse_feed_order(*feed_id)?;
+                PreviousState::Nothing
+            }
+        };
+        Ok((JournalEntry { change, previous }, updated_feeds))
+    }
+
+    fn revert(&self, data: &mut dyn DataProvider) -> DbResult<()> {
+        match (&self.change, &self.previous) {
+            (_, PreviousState::Episodes(states)) => {
+                data.restore_episode_states(states)?;
+            }
+            (Change::SetGroup(_, feed_id), PreviousState::Group(group_id)) => {
+                data.set_feed_for_group(*group_id, *feed_id)?;
+            }
+            (Change::RenameFeed(feed_id, _), PreviousState::Title(title)) => {
+                data.rename_feed(*feed_id, title.clone())?;
+            }
+            (Change::ReverseFeedOrder(feed_id), _) => data.reverse_feed_order(*feed_id)?,
+            (_, _) => {}
+        }
+        Ok(())
+    }
+}
+
+/// Keeps track of the recent changes to the library so that they can be reverted and
+/// re-applied.
+#[derive(Debug, Default)]
+pub(crate) struct Journal {
+    undo: VecDeque<JournalEntry>,
+    redo: Vec<JournalEntry>,
+}
+
+impl Journal {
+    fn push_undo(&mut self, entry: JournalEntry) {
+        if self.undo.len() == JOURNAL_CAPACITY {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(entry);
+    }
+
+    /// Applies the change and records it. Returns the ids of the feeds in which episodes' status
+    /// was updated.
+    pub(crate) fn apply(
+        &mut self,
+        data: &mut dyn DataProvider,
+        change: Change,
+    ) -> DbResult<HashSet<FeedId>> {
+        let (entry, updated_feeds) = JournalEntry::apply(data, change)?;
+        self.push_undo(entry);
+        self.redo.clear();
+        Ok(updated_feeds)
+    }
+
+    pub(crate) fn undo(&mut self, data: &mut dyn DataProvider) -> DbResult<Option<Change>> {
+        let entry = match self.undo.pop_back() {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        if let Err(error) = entry.revert(data) {
+            self.undo.push_back(entry);
+            return Err(error);
+        }
+        let change = entry.change.clone();
+        self.redo.push(entry);
+        Ok(Some(change))
+    }
+
+    pub(crate) fn redo(&mut self, data: &mut dyn DataProvider) -> DbResult<Option<Change>> {
+        let entry = match self.redo.pop() {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        match JournalEntry::apply(data, entry.change.clone()) {
+            Ok((entry, _)) => {
+                let change = entry.change.clone();
+                self.push_undo(entry);
+                Ok(Some(change))
+            }
+            Err(error) => {
+                self.redo.push(entry);
+                Err(error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Change, Journal};
+    use crate::conformance::feed_with_episodes;
+    use crate::datasource::DataProvider;
+    use crate::model::{EpisodeStatus, EpisodeSummaryStatus, FeedId};
+    use crate::{EpisodesQuery, SqliteDataProvider};
+    use std::time::Duration;
+
+    fn create_feed(provider: &mut SqliteDataProvider, guids: &[&str]) -> FeedId {
+        let episodes: Vec<_> = guids.iter().map(|&guid| (guid, None)).collect();
+        feed_with_episodes(provider, "http://example.com/feed.xml", &episodes).0
+    }
+
+    fn statuses(provider: &mut SqliteDataProvider) -> Vec<EpisodeStatus> {
+        let mut states = provider
+            .get_episode_states(EpisodesQuery::default())
+            .unwrap();
+        states.sort_by_key(|state| state.id.as_i64());
+        states.into_iter().map(|state| state.status).collect()
+    }
+
+    #[test]
+    fn undo_and_redo_status() {
+        let mut provider = SqliteDataProvider::connect(":memory:").unwrap();
+        let feed_id = create_feed(&mut provider, &["a", "b"]);
+        let first_query = EpisodesQuery::default()
+            .feed_id(feed_id)
+            .status(EpisodeSummaryStatus::New);
+        let first_id = provider.get_episode_states(first_query).unwrap()[0].id;
+        let query = EpisodesQuery::default().id(first_id);
+        provider
+            .set_episode_status(query, EpisodeStatus::Started(Duration::from_secs(5)))
+            .unwrap();
+        let before = statuses(&mut provider);
+
+        let mut journal = Journal::default();
+        let change = Change::SetStatus(EpisodesQuery::default(), EpisodeStatus::Finished);
+        let updated = journal.apply(&mut provider, change.clone()).unwrap();
+        assert!(updated.contains(&feed_id));
+        assert_eq!(statuses(&mut provider), vec![EpisodeStatus::Finished; 2]);
+
+        assert_eq!(journal.undo(&mut provider).unwrap(), Some(change.clone()));
+        assert_eq!(statuses(&mut provider), before);
+        assert_eq!(journal.undo(&mut provider).unwrap(), None);
+
+        assert_eq!(journal.redo(&mut provider).unwrap(), Some(change));
+        assert_eq!(statuses(&mut provider), vec![EpisodeStatus::Finished; 2]);
+        assert_eq!(journal.redo(&mut provider).unwrap(), None);
+    }
+
+    #[test]
+    fn undo_rename_and_group() {
+        let mut provider = SqliteDataProvider::connect(":memory:").unwrap();
+        let feed_id = create_feed(&mut provider, &["a"]);
+        let group_id = provider.create_group("group").unwrap().unwrap();
+
+        let mut journal = Journal::default();
+        journal
+            .apply(&mut provider, Change::RenameFeed(feed_id, "renamed".to_string()))
+            .unwrap();
+        journal
+            .apply(&mut provider, Change::SetGroup(Some(group_id), feed_id))
+            .unwrap();
+        let feed = provider.get_feed(feed_id).unwrap().unwrap();
+        assert_eq!(feed.title.as_deref(), Some("renamed"));
+        assert_eq!(feed.group_id, Some(group_id));
+
+        journal.undo(&mut provider).unwrap();
+        let feed = provider.get_feed(feed_id).unwrap().unwrap();
+        assert_eq!(feed.group_id, None);
+        assert_eq!(feed.title.as_deref(), Some("renamed"));
+
+        journal.undo(&mut provider).unwrap();
+        let feed = provider.get_feed(feed_id).unwrap().unwrap();
+        assert!(!feed.title_overriden);
+
+        journal.redo(&mut provider).unwrap();
+        journal
+            .apply(&mut provider, Change::ReverseFeedOrder(feed_id))
+            .unwrap();
+        assert_eq!(journal.redo(&mut provider).unwrap(), None);
+    }
+}
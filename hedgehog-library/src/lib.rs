@@ -1,21 +1,36 @@
 mod actor;
+pub mod backup;
+pub mod bookmarks;
 mod cache;
+#[cfg(any(test, feature = "conformance"))]
+pub mod conformance;
 pub mod datasource;
+mod feed_url;
+mod journal;
+#[cfg(any(test, feature = "memory"))]
+mod memory;
 pub mod metadata;
+pub mod migration;
 pub mod model;
 pub mod opml;
+mod reader;
 mod rss_client;
 pub mod search;
 mod search_query;
 mod sqlite;
+pub mod stats;
 pub mod status_writer;
 mod tests;
 
 pub use actor::{
-    EpisodePlaybackDataRequest, EpisodeRequest, EpisodeSummariesRequest,
+    BookmarksRequest, EpisodePlaybackDataRequest, EpisodeRequest, EpisodeSummariesRequest,
     EpisodesListMetadataRequest, FeedRequest, FeedSummariesRequest, FeedSummariesResponse,
-    FeedUpdateNotification, FeedUpdateRequest, FeedUpdateResult, Library, UpdateQuery,
+    FeedUpdateNotification, FeedUpdateRequest, FeedUpdateResult, Library, ListeningStatsRequest,
+    NextEpisodeRequest, QueueRequest, TrashRequest, UpdateQuery,
 };
-pub use cache::InMemoryCache;
+pub use cache::{EpisodesCache, InMemoryCache};
 pub use datasource::{EpisodesQuery, NewFeedMetadata, QueryError};
+#[cfg(any(test, feature = "memory"))]
+pub use memory::MemoryDataProvider;
+pub use reader::DatabaseReader;
 pub use sqlite::SqliteDataProvider;
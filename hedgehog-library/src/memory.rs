@@ -0,0 +1,1394 @@
+use crate::actor::UpdateQuery;
+use crate::backup::restores_status;
+use crate::datasource::{
+    DataProvider, DbResult, EpisodeWriter, EpisodesQuery, NewFeedMetadata, QueryError,
+};
+use crate::feed_url;
+use crate::metadata::{EpisodeMetadata, FeedMetadata};
+use crate::model::{
+    Bookmark, BookmarkId, Episode, EpisodeId, EpisodePlaybackData, EpisodeSnapshot, EpisodeState,
+    EpisodeStatus, EpisodeSummary, EpisodeSummaryStatus, EpisodesListMetadata, Feed, FeedId,
+    FeedListeningStats, FeedOMPLEntry, FeedSnapshot, FeedStatus, FeedSummary, GroupId,
+    GroupSummary, ListeningSession, ListeningStats, TagTarget, TrashedFeed, ViewId, ViewSummary,
+    WeekListeningStats,
+};
+use chrono::{DateTime, Datelike, Utc};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::Range;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+struct FeedRecord {
+    id: FeedId,
+    title: Option<String>,
+    title_override: Option<String>,
+    description: Option<String>,
+    link: Option<String>,
+    author: Option<String>,
+    copyright: Option<String>,
+    source: String,
+    status: FeedStatus,
+    enabled: bool,
+    reversed: bool,
+    group_id: Option<GroupId>,
+    deleted_at: Option<DateTime<Utc>>,
+    playback_rate: Option<f64>,
+    skip_intro: Duration,
+    skip_outro: Duration,
+    tags: BTreeSet<String>,
+}
+
+impl FeedRecord {
+    fn display_title(&self) -> String {
+        (self.title_override.as_ref())
+            .or(self.title.as_ref())
+            .unwrap_or(&self.source)
+            .clone()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct EpisodeRecord {
+    id: EpisodeId,
+    feed_id: FeedId,
+    guid: String,
+    title: Option<String>,
+    description: Option<String>,
+    link: Option<String>,
+    duration: Option<Duration>,
+    publication_date: Option<DateTime<Utc>>,
+    episode_number: Option<i64>,
+    season_number: Option<i64>,
+    media_url: String,
+    status: EpisodeStatus,
+    hidden: bool,
+    tags: BTreeSet<String>,
+}
+
+impl EpisodeRecord {
+    fn summary_status(&self) -> EpisodeSummaryStatus {
+        EpisodeSummaryStatus::from(&self.status)
+    }
+
+    fn summary(&self, feed_title: Option<String>) -> EpisodeSummary {
+        EpisodeSummary {
+            id: self.id,
+            feed_id: self.feed_id,
+            episode_number: self.episode_number,
+            season_number: self.season_number,
+            title: self.title.clone(),
+            feed_title,
+            status: self.summary_status(),
+            duration: self.duration,
+            publication_date: self.publication_date,
+            is_hidden: self.hidden,
+        }
+    }
+
+    /// Whether the episode has the same title and publication date as the metadata. Like SQL
+    /// comparisons, missing values never match.
+    fn same_title_and_date(&self, title: Option<&str>, date: Option<DateTime<Utc>>) -> bool {
+        let same_title = matches!((self.title.as_deref(), title), (Some(a), Some(b)) if a == b);
+        let same_date = matches!((self.publication_date, date), (Some(a), Some(b)) if a == b);
+        same_title && same_date
+    }
+}
+
+#[derive(Debug, Clone)]
+struct GroupRecord {
+    id: GroupId,
+    name: String,
+    ordering: usize,
+    parent_id: Option<GroupId>,
+    collapsed: bool,
+}
+
+#[derive(Debug, Clone)]
+struct ViewRecord {
+    id: ViewId,
+    name: String,
+    query: EpisodesQuery,
+}
+
+#[derive(Debug, Clone)]
+struct BookmarkRecord {
+    id: BookmarkId,
+    episode_id: EpisodeId,
+    position: Duration,
+    note: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Data {
+    last_id: i64,
+    feeds: Vec<FeedRecord>,
+    episodes: Vec<EpisodeRecord>,
+    groups: Vec<GroupRecord>,
+    views: Vec<ViewRecord>,
+    queue: Vec<EpisodeId>,
+    bookmarks: Vec<BookmarkRecord>,
+    sessions: Vec<ListeningSession>,
+}
+
+impl Data {
+    fn next_id(&mut self) -> i64 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    fn feed(&self, feed_id: FeedId) -> Option<&FeedRecord> {
+        self.feeds.iter().find(|feed| feed.id == feed_id)
+    }
+
+    fn feed_mut(&mut self, feed_id: FeedId) -> Option<&mut FeedRecord> {
+        self.feeds.iter_mut().find(|feed| feed.id == feed_id)
+    }
+
+    fn episode(&self, episode_id: EpisodeId) -> Option<&EpisodeRecord> {
+        self.episodes.iter().find(|episode| episode.id == episode_id)
+    }
+
+    fn group_mut(&mut self, group_id: GroupId) -> Option<&mut GroupRecord> {
+        self.groups.iter_mut().find(|group| group.id == group_id)
+    }
+
+    fn is_deleted(&self, feed_id: FeedId) -> bool {
+        self.feed(feed_id).map_or(true, |feed| feed.deleted_at.is_some())
+    }
+
+    fn new_count(&self, feed_id: FeedId) -> usize {
+        (self.episodes.iter())
+            .filter(|episode| episode.feed_id == feed_id && episode.status == EpisodeStatus::New)
+            .count()
+    }
+
+    /// Returns the groups in the order of the tree traversal along with their depth, every
+    /// group is followed by its subgroups.
+    fn group_tree(&self) -> Vec<(GroupId, usize)> {
+        fn visit(
+            data: &Data,
+            parent_id: Option<GroupId>,
+            depth: usize,
+            tree: &mut Vec<(GroupId, usize)>,
+        ) {
+            let mut children: Vec<&GroupRecord> = (data.groups.iter())
+                .filter(|group| group.parent_id == parent_id)
+                .collect();
+            children.sort_by_key(|group| group.ordering);
+            for group in children {
+                tree.push((group.id, depth));
+                visit(data, Some(group.id), depth + 1, tree);
+            }
+        }
+
+        let mut tree = Vec::with_capacity(self.groups.len());
+        visit(self, None, 0, &mut tree);
+        tree
+    }
+
+    fn group_descendants(&self, group_id: GroupId) -> HashSet<GroupId> {
+        let mut descendants = HashSet::from([group_id]);
+        loop {
+            let children: Vec<GroupId> = (self.groups.iter())
+                .filter(|group| {
+                    group.parent_id.map_or(false, |id| descendants.contains(&id))
+                        && !descendants.contains(&group.id)
+                })
+                .map(|group| group.id)
+                .collect();
+            if children.is_empty() {
+                return descendants;
+            }
+            descendants.extend(children);
+        }
+    }
+
+    fn fix_group_ordering(&mut self) {
+        let mut orderings: Vec<usize> = self.groups.iter().map(|group| group.ordering).collect();
+        orderings.sort_unstable();
+        for group in &mut self.groups {
+            group.ordering = orderings.iter().position(|o| *o == group.ordering).unwrap_or(0) + 1;
+        }
+    }
+
+    fn matches(
+        &self,
+        episode: &EpisodeRecord,
+        query: &EpisodesQuery,
+        filter: &QueryFilter,
+    ) -> bool {
+        let feed = self.feed(episode.feed_id);
+        if query.episode_id.map_or(false, |id| id != episode.id)
+            || query.feed_id.map_or(false, |id| id != episode.feed_id)
+        {
+            return false;
+        }
+        if let Some(groups) = &filter.groups {
+            let group_id = feed.and_then(|feed| feed.group_id);
+            if !group_id.map_or(false, |group_id| groups.contains(&group_id)) {
+                return false;
+            }
+        }
+        let status = episode.summary_status();
+        if query.status.map_or(false, |expected| expected != status)
+            || query.excluded_status == Some(status)
+        {
+            return false;
+        }
+        if let Some(published_after) = filter.published_after {
+            if episode.publication_date.map_or(true, |date| date < published_after) {
+                return false;
+            }
+        }
+        if query.episode_id.is_none() && query.feed_id.is_none() && self.is_deleted(episode.feed_id)
+        {
+            return false;
+        }
+        if let Some(tag) = &query.tag {
+            let feed_tagged = feed.map_or(false, |feed| feed.tags.contains(tag));
+            if !episode.tags.contains(tag) && !feed_tagged {
+                return false;
+            }
+        }
+        query.with_hidden || !episode.hidden
+    }
+
+    fn query_ids(&self, query: &EpisodesQuery) -> Vec<EpisodeId> {
+        let filter = QueryFilter::new(self, query);
+        (self.episodes.iter())
+            .filter(|episode| self.matches(episode, query, &filter))
+            .map(|episode| episode.id)
+            .collect()
+    }
+
+    fn query_episodes(&self, query: &EpisodesQuery) -> Vec<&EpisodeRecord> {
+        let filter = QueryFilter::new(self, query);
+        (self.episodes.iter())
+            .filter(|episode| self.matches(episode, query, &filter))
+            .collect()
+    }
+
+    fn delete_episodes(&mut self, pred: impl Fn(&EpisodeRecord) -> bool) {
+        let deleted: HashSet<EpisodeId> = (self.episodes.iter())
+            .filter(|episode| pred(episode))
+            .map(|episode| episode.id)
+            .collect();
+        if deleted.is_empty() {
+            return;
+        }
+        self.episodes.retain(|episode| !deleted.contains(&episode.id));
+        self.queue.retain(|episode_id| !deleted.contains(episode_id));
+        (self.bookmarks).retain(|bookmark| !deleted.contains(&bookmark.episode_id));
+        (self.sessions).retain(|session| !deleted.contains(&session.episode_id));
+    }
+
+    fn delete_feeds(&mut self, pred: impl Fn(&FeedRecord) -> bool) -> usize {
+        let deleted: HashSet<FeedId> = (self.feeds.iter())
+            .filter(|feed| pred(feed))
+            .map(|feed| feed.id)
+            .collect();
+        self.feeds.retain(|feed| !deleted.contains(&feed.id));
+        self.delete_episodes(|episode| deleted.contains(&episode.feed_id));
+        deleted.len()
+    }
+
+    /// Finds a feed other than `except_id` whose source is equivalent to the given one. Feeds in
+    /// the trash are only returned if there is no matching feed in the library.
+    fn find_feed_by_source(
+        &self,
+        source: &str,
+        except_id: Option<FeedId>,
+    ) -> Option<(FeedId, bool)> {
+        let key = feed_url::comparison_key(source);
+        let mut trashed = None;
+        for feed in &self.feeds {
+            if Some(feed.id) == except_id || feed_url::comparison_key(&feed.source) != key {
+                continue;
+            }
+            if feed.deleted_at.is_none() {
+                return Some((feed.id, false));
+            }
+            trashed = trashed.or(Some((feed.id, true)));
+        }
+        trashed
+    }
+}
+
+/// The parts of the query that are computed once for all episodes.
+struct QueryFilter {
+    groups: Option<HashSet<GroupId>>,
+    published_after: Option<DateTime<Utc>>,
+}
+
+impl QueryFilter {
+    fn new(data: &Data, query: &EpisodesQuery) -> Self {
+        QueryFilter {
+            groups: query.group_id.map(|group_id| data.group_descendants(group_id)),
+            published_after: query.published_within.map(|duration| {
+                chrono::Duration::from_std(duration)
+                    .ok()
+                    .and_then(|duration| Utc::now().checked_sub_signed(duration))
+                    .unwrap_or(chrono::DateTime::<Utc>::MIN_UTC)
+            }),
+        }
+    }
+}
+
+/// Orders episodes by the publication date, from the oldest to the newest. Episodes without a
+/// publication date go first, as they do in SQL.
+fn publication_order(a: &EpisodeRecord, b: &EpisodeRecord) -> Ordering {
+    (a.publication_date, a.id.as_i64()).cmp(&(b.publication_date, b.id.as_i64()))
+}
+
+fn session_seconds(sessions: &[&ListeningSession]) -> f64 {
+    let total: f64 = (sessions.iter())
+        .map(|session| (session.ended_at - session.started_at).num_milliseconds() as f64 / 1000.0)
+        .sum();
+    total.round().max(0.0)
+}
+
+/// Keeps the library in memory. It behaves the same way as `SqliteDataProvider` and is meant to
+/// be used in tests of the code built on top of the library.
+#[derive(Debug, Default)]
+pub struct MemoryDataProvider {
+    data: Data,
+}
+
+impl MemoryDataProvider {
+    pub fn new() -> Self {
+        MemoryDataProvider::default()
+    }
+}
+
+impl DataProvider for MemoryDataProvider {
+    fn check_external_changes(&mut self) -> DbResult<bool> {
+        Ok(false)
+    }
+
+    fn get_feed(&mut self, id: FeedId) -> DbResult<Option<Feed>> {
+        Ok(self.data.feed(id).map(|feed| Feed {
+            id: feed.id,
+            title: feed.title_override.clone().or_else(|| feed.title.clone()),
+            title_overriden: feed.title_override.is_some(),
+            description: feed.description.clone(),
+            link: feed.link.clone(),
+            author: feed.author.clone(),
+            copyright: feed.copyright.clone(),
+            source: feed.source.clone(),
+            status: feed.status,
+            group_id: feed.group_id,
+        }))
+    }
+
+    fn get_feed_summaries(&mut self) -> DbResult<Vec<FeedSummary>> {
+        let tree = self.data.group_tree();
+        let mut feeds: Vec<&FeedRecord> = (self.data.feeds.iter())
+            .filter(|feed| feed.deleted_at.is_none())
+            .collect();
+        feeds.sort_by_cached_key(|feed| {
+            let group_index = (feed.group_id)
+                .and_then(|group_id| tree.iter().position(|(id, _)| *id == group_id));
+            let title = feed.title_override.clone().or_else(|| feed.title.clone());
+            (group_index, title, feed.source.clone())
+        });
+        Ok(feeds
+            .into_iter()
+            .map(|feed| FeedSummary {
+                id: feed.id,
+                title: feed.display_title(),
+                has_title: feed.title.is_some(),
+                status: feed.status,
+                new_count: self.data.new_count(feed.id),
+                group_id: feed.group_id,
+            })
+            .collect())
+    }
+
+    fn get_feed_opml_entries(&mut self) -> DbResult<Vec<FeedOMPLEntry>> {
+        Ok((self.data.feeds.iter())
+            .filter(|feed| feed.deleted_at.is_none())
+            .map(|feed| FeedOMPLEntry {
+                title: feed.title.clone(),
+                title_override: feed.title_override.clone(),
+                feed_source: feed.source.clone(),
+                link: feed.link.clone(),
+                group_id: feed.group_id,
+                enabled: feed.enabled,
+                reversed: feed.reversed,
+            })
+            .collect())
+    }
+
+    fn get_feed_snapshots(&mut self) -> DbResult<Vec<FeedSnapshot>> {
+        Ok((self.data.feeds.iter())
+            .filter(|feed| feed.deleted_at.is_none())
+            .map(|feed| FeedSnapshot {
+                id: feed.id,
+                source: feed.source.clone(),
+                title: feed.title.clone(),
+                title_override: feed.title_override.clone(),
+                link: feed.link.clone(),
+                group_id: feed.group_id,
+                enabled: feed.enabled,
+                reversed: feed.reversed,
+                playback_rate: feed.playback_rate,
+                skip_intro: feed.skip_intro,
+                skip_outro: feed.skip_outro,
+                tags: feed.tags.iter().cloned().collect(),
+            })
+            .collect())
+    }
+
+    fn get_episode_snapshots(&mut self, feed_id: FeedId) -> DbResult<Vec<EpisodeSnapshot>> {
+        let mut episodes: Vec<&EpisodeRecord> = (self.data.episodes.iter())
+            .filter(|episode| episode.feed_id == feed_id)
+            .collect();
+        episodes.sort_by(|a, b| publication_order(a, b));
+        Ok((episodes.into_iter())
+            .map(|episode| EpisodeSnapshot {
+                id: episode.id,
+                guid: episode.guid.clone(),
+                title: episode.title.clone(),
+                description: episode.description.clone(),
+                link: episode.link.clone(),
+                duration: episode.duration,
+                publication_date: episode.publication_date,
+                episode_number: episode.episode_number,
+                season_number: episode.season_number,
+                media_url: episode.media_url.clone(),
+                status: episode.status.clone(),
+                hidden: episode.hidden,
+                tags: episode.tags.iter().cloned().collect(),
+            })
+            .collect())
+    }
+
+    fn get_update_sources(&mut self, update: UpdateQuery) -> DbResult<Vec<(FeedId, String)>> {
+        let sources = match update {
+            UpdateQuery::Single(feed_id) => {
+                let feed = self.data.feed(feed_id).ok_or(QueryError::NotFound)?;
+                vec![(feed_id, feed.source.clone())]
+            }
+            UpdateQuery::All | UpdateQuery::Pending => {
+                let pending_only = matches!(update, UpdateQuery::Pending);
+                (self.data.feeds.iter())
+                    .filter(|feed| feed.enabled && feed.deleted_at.is_none())
+                    .filter(|feed| !pending_only || feed.status == FeedStatus::Pending)
+                    .map(|feed| (feed.id, feed.source.clone()))
+                    .collect()
+            }
+        };
+        Ok(sources)
+    }
+
+    fn get_new_episodes_count(
+        &mut self,
+        feed_ids: HashSet<FeedId>,
+    ) -> DbResult<HashMap<FeedId, usize>> {
+        Ok((feed_ids.into_iter())
+            .filter(|feed_id| self.data.feed(*feed_id).is_some())
+            .map(|feed_id| (feed_id, self.data.new_count(feed_id)))
+            .collect())
+    }
+
+    fn rename_feed(&mut self, feed_id: FeedId, name: Option<String>) -> DbResult<()> {
+        if let Some(feed) = self.data.feed_mut(feed_id) {
+            feed.title_override = name;
+        }
+        Ok(())
+    }
+
+    fn create_group(&mut self, name: &str) -> DbResult<Option<GroupId>> {
+        if self.data.groups.iter().any(|group| group.name == name) {
+            return Ok(None);
+        }
+        let ordering = self.data.groups.iter().map(|group| group.ordering).max();
+        let id = GroupId(self.data.next_id());
+        self.data.groups.push(GroupRecord {
+            id,
+            name: name.to_string(),
+            ordering: ordering.map_or(1, |ordering| ordering + 1),
+            parent_id: None,
+            collapsed: false,
+        });
+        Ok(Some(id))
+    }
+
+    fn get_group_summaries(&mut self) -> DbResult<Vec<GroupSummary>> {
+        let data = &self.data;
+        let summaries = (data.group_tree().into_iter())
+            .filter_map(|(group_id, depth)| {
+                let group = data.groups.iter().find(|group| group.id == group_id)?;
+                let descendants = data.group_descendants(group_id);
+                let new_count = (data.feeds.iter())
+                    .filter(|feed| feed.deleted_at.is_none())
+                    .filter(|feed| feed.group_id.map_or(false, |id| descendants.contains(&id)))
+                    .map(|feed| data.new_count(feed.id))
+                    .sum();
+                Some(GroupSummary {
+                    id: group.id,
+                    name: group.name.clone(),
+                    parent_id: group.parent_id,
+                    depth,
+                    collapsed: group.collapsed,
+                    new_count,
+                })
+            })
+            .collect();
+        Ok(summaries)
+    }
+
+    fn set_feed_for_group(&mut self, group_id: Option<GroupId>, feed_id: FeedId) -> DbResult<()> {
+        if let Some(feed) = self.data.feed_mut(feed_id) {
+            feed.group_id = group_id;
+        }
+        Ok(())
+    }
+
+    fn rename_group(&mut self, group_id: GroupId, name: String) -> DbResult<()> {
+        if let Some(group) = self.data.group_mut(group_id) {
+            group.name = name;
+        }
+        Ok(())
+    }
+
+    fn delete_group(&mut self, group_id: GroupId) -> DbResult<()> {
+        let parent_id = match self.data.group_mut(group_id) {
+            Some(group) => group.parent_id,
+            None => return Ok(()),
+        };
+        for group in &mut self.data.groups {
+            if group.parent_id == Some(group_id) {
+                group.parent_id = parent_id;
+            }
+        }
+        for feed in &mut self.data.feeds {
+            if feed.group_id == Some(group_id) {
+                feed.group_id = parent_id;
+            }
+        }
+        self.data.groups.retain(|group| group.id != group_id);
+        self.data.fix_group_ordering();
+        Ok(())
+    }
+
+    fn set_group_position(&mut self, group_id: GroupId, position: usize) -> DbResult<()> {
+        let current = self.data.group_mut(group_id).ok_or(QueryError::NotFound)?.ordering;
+        let direction = current.cmp(&position);
+        if direction == Ordering::Equal {
+            return Ok(());
+        }
+        for group in &mut self.data.groups {
+            let ordering = group.ordering;
+            group.ordering = match direction {
+                _ if ordering == current => position,
+                Ordering::Less if ordering > current && ordering <= position => ordering - 1,
+                Ordering::Greater if ordering >= position && ordering < current => ordering + 1,
+                _ => ordering,
+            };
+        }
+        self.data.fix_group_ordering();
+        Ok(())
+    }
+
+    fn set_group_parent(
+        &mut self,
+        group_id: GroupId,
+        parent_id: Option<GroupId>,
+    ) -> DbResult<bool> {
+        if let Some(parent_id) = parent_id {
+            if self.data.group_descendants(group_id).contains(&parent_id) {
+                return Ok(false);
+            }
+        }
+        if let Some(group) = self.data.group_mut(group_id) {
+            group.parent_id = parent_id;
+        }
+        Ok(true)
+    }
+
+    fn set_group_collapsed(&mut self, group_id: Option<GroupId>, collapsed: bool) -> DbResult<()> {
+        for group in &mut self.data.groups {
+            if group_id.map_or(true, |group_id| group.id == group_id) {
+                group.collapsed = collapsed;
+            }
+        }
+        Ok(())
+    }
+
+    fn create_view(&mut self, name: &str, query: &EpisodesQuery) -> DbResult<Option<ViewId>> {
+        if self.data.views.iter().any(|view| view.name == name) {
+            return Ok(None);
+        }
+        let id = ViewId(self.data.next_id());
+        self.data.views.push(ViewRecord {
+            id,
+            name: name.to_string(),
+            query: query.clone(),
+        });
+        Ok(Some(id))
+    }
+
+    fn get_view_summaries(&mut self) -> DbResult<Vec<ViewSummary>> {
+        let mut views: Vec<ViewSummary> = (self.data.views.iter())
+            .map(|view| ViewSummary {
+                id: view.id,
+                name: view.name.clone(),
+                query: view.query.clone(),
+            })
+            .collect();
+        views.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(views)
+    }
+
+    fn rename_view(&mut self, view_id: ViewId, name: String) -> DbResult<()> {
+        if let Some(view) = self.data.views.iter_mut().find(|view| view.id == view_id) {
+            view.name = name;
+        }
+        Ok(())
+    }
+
+    fn delete_view(&mut self, view_id: ViewId) -> DbResult<()> {
+        self.data.views.retain(|view| view.id != view_id);
+        Ok(())
+    }
+
+    fn get_queue(&mut self) -> DbResult<Vec<EpisodeSummary>> {
+        let data = &self.data;
+        Ok((data.queue.iter())
+            .filter_map(|episode_id| data.episode(*episode_id))
+            .filter_map(|episode| {
+                let feed = data.feed(episode.feed_id)?;
+                match feed.deleted_at {
+                    Some(_) => None,
+                    None => Some(episode.summary(feed.title.clone())),
+                }
+            })
+            .collect())
+    }
+
+    fn add_to_queue(&mut self, episode_id: EpisodeId) -> DbResult<()> {
+        if self.data.episode(episode_id).is_none() {
+            return Err(QueryError::NotFound);
+        }
+        if !self.data.queue.contains(&episode_id) {
+            self.data.queue.push(episode_id);
+        }
+        Ok(())
+    }
+
+    fn remove_from_queue(&mut self, episode_id: EpisodeId) -> DbResult<()> {
+        self.data.queue.retain(|id| *id != episode_id);
+        Ok(())
+    }
+
+    fn set_queue_position(&mut self, episode_id: EpisodeId, position: usize) -> DbResult<()> {
+        let queue = &mut self.data.queue;
+        let index = (queue.iter().position(|id| *id == episode_id)).ok_or(QueryError::NotFound)?;
+        queue.remove(index);
+        let index = position.saturating_sub(1).min(queue.len());
+        queue.insert(index, episode_id);
+        Ok(())
+    }
+
+    fn clear_queue(&mut self) -> DbResult<()> {
+        self.data.queue.clear();
+        Ok(())
+    }
+
+    fn create_bookmark(
+        &mut self,
+        episode_id: EpisodeId,
+        position: Duration,
+        note: Option<&str>,
+    ) -> DbResult<BookmarkId> {
+        if self.data.episode(episode_id).is_none() {
+            return Err(QueryError::NotFound);
+        }
+        let id = BookmarkId(self.data.next_id());
+        self.data.bookmarks.push(BookmarkRecord {
+            id,
+            episode_id,
+            position,
+            note: note.map(str::to_string),
+            created_at: Utc::now(),
+        });
+        Ok(id)
+    }
+
+    fn get_bookmarks(&mut self) -> DbResult<Vec<Bookmark>> {
+        let data = &self.data;
+        let mut bookmarks: Vec<(&BookmarkRecord, &EpisodeRecord, &FeedRecord)> = (data.bookmarks)
+            .iter()
+            .filter_map(|bookmark| {
+                let episode = data.episode(bookmark.episode_id)?;
+                let feed = data.feed(episode.feed_id)?;
+                match feed.deleted_at {
+                    Some(_) => None,
+                    None => Some((bookmark, episode, feed)),
+                }
+            })
+            .collect();
+        bookmarks.sort_by(|(a, a_episode, a_feed), (b, b_episode, b_feed)| {
+            (a_feed.id.as_i64().cmp(&b_feed.id.as_i64()))
+                .then_with(|| publication_order(a_episode, b_episode))
+                .then_with(|| a.position.cmp(&b.position))
+        });
+        Ok(bookmarks
+            .into_iter()
+            .map(|(bookmark, episode, feed)| Bookmark {
+                id: bookmark.id,
+                episode_id: bookmark.episode_id,
+                episode_title: episode.title.clone(),
+                feed_title: feed.title_override.clone().or_else(|| feed.title.clone()),
+                position: bookmark.position,
+                note: bookmark.note.clone(),
+                created_at: bookmark.created_at,
+            })
+            .collect())
+    }
+
+    fn delete_bookmark(&mut self, bookmark_id: BookmarkId) -> DbResult<()> {
+        self.data.bookmarks.retain(|bookmark| bookmark.id != bookmark_id);
+        Ok(())
+    }
+
+    fn get_tags(&mut self) -> DbResult<Vec<String>> {
+        let feed_tags = self.data.feeds.iter().flat_map(|feed| feed.tags.iter());
+        let episode_tags = self.data.episodes.iter().flat_map(|episode| episode.tags.iter());
+        let tags: BTreeSet<&String> = feed_tags.chain(episode_tags).collect();
+        Ok(tags.into_iter().cloned().collect())
+    }
+
+    fn add_tag(&mut self, target: TagTarget, name: &str) -> DbResult<()> {
+        let tags = match target {
+            TagTarget::Feed(feed_id) => self.data.feed_mut(feed_id).map(|feed| &mut feed.tags),
+            TagTarget::Episode(episode_id) => (self.data.episodes.iter_mut())
+                .find(|episode| episode.id == episode_id)
+                .map(|episode| &mut episode.tags),
+        };
+        tags.ok_or(QueryError::NotFound)?.insert(name.to_string());
+        Ok(())
+    }
+
+    fn remove_tag(&mut self, target: TagTarget, name: &str) -> DbResult<()> {
+        let tags = match target {
+            TagTarget::Feed(feed_id) => self.data.feed_mut(feed_id).map(|feed| &mut feed.tags),
+            TagTarget::Episode(episode_id) => (self.data.episodes.iter_mut())
+                .find(|episode| episode.id == episode_id)
+                .map(|episode| &mut episode.tags),
+        };
+        if let Some(tags) = tags {
+            tags.remove(name);
+        }
+        Ok(())
+    }
+
+    fn save_listening_session(&mut self, session: &ListeningSession) -> DbResult<()> {
+        if self.data.episode(session.episode_id).is_none() {
+            return Err(QueryError::NotFound);
+        }
+        let exists = self.data.sessions.iter().any(|existing| {
+            existing.episode_id == session.episode_id && existing.started_at == session.started_at
+        });
+        if !exists {
+            self.data.sessions.push(session.clone());
+        }
+        Ok(())
+    }
+
+    fn get_listening_sessions(&mut self) -> DbResult<Vec<ListeningSession>> {
+        let mut sessions = self.data.sessions.clone();
+        sessions.sort_by_key(|session| session.started_at);
+        Ok(sessions)
+    }
+
+    fn get_listening_stats(&mut self) -> DbResult<ListeningStats> {
+        let data = &self.data;
+        let mut feeds: Vec<(f64, FeedListeningStats)> = Vec::new();
+        for feed in &data.feeds {
+            let sessions: Vec<&ListeningSession> = (data.sessions.iter())
+                .filter(|session| {
+                    (data.episode(session.episode_id))
+                        .map_or(false, |episode| episode.feed_id == feed.id)
+                })
+                .collect();
+            if sessions.is_empty() {
+                continue;
+            }
+            let episodes: HashSet<EpisodeId> =
+                sessions.iter().map(|session| session.episode_id).collect();
+            let listened = session_seconds(&sessions);
+            feeds.push((
+                listened,
+                FeedListeningStats {
+                    feed_id: feed.id,
+                    title: feed.display_title(),
+                    listened: Duration::from_secs_f64(listened),
+                    episodes: episodes.len(),
+                    sessions: sessions.len(),
+                },
+            ));
+        }
+        feeds.sort_by(|(a_listened, a), (b_listened, b)| {
+            (b_listened.partial_cmp(a_listened).unwrap_or(Ordering::Equal))
+                .then_with(|| a.feed_id.as_i64().cmp(&b.feed_id.as_i64()))
+        });
+
+        let mut weeks: Vec<WeekListeningStats> = Vec::new();
+        let mut week_starts: Vec<_> = (data.sessions.iter())
+            .map(|session| {
+                let date = session.started_at.naive_utc().date();
+                date - chrono::Duration::days(date.weekday().num_days_from_monday() as i64)
+            })
+            .collect();
+        week_starts.sort_unstable();
+        week_starts.dedup();
+        for week_start in week_starts {
+            let sessions: Vec<&ListeningSession> = (data.sessions.iter())
+                .filter(|session| {
+                    let date = session.started_at.naive_utc().date();
+                    let days = date.weekday().num_days_from_monday() as i64;
+                    date - chrono::Duration::days(days) == week_start
+                })
+                .collect();
+            weeks.push(WeekListeningStats {
+                week_start,
+                listened: Duration::from_secs_f64(session_seconds(&sessions)),
+            });
+        }
+
+        let listened: HashSet<EpisodeId> =
+            data.sessions.iter().map(|session| session.episode_id).collect();
+        let started: Vec<&EpisodeRecord> = (data.episodes.iter())
+            .filter(|episode| listened.contains(&episode.id))
+            .collect();
+        let finished_episodes = (started.iter())
+            .filter(|episode| episode.status == EpisodeStatus::Finished)
+            .count();
+
+        Ok(ListeningStats {
+            feeds: feeds.into_iter().map(|(_, stats)| stats).collect(),
+            weeks,
+            started_episodes: started.len(),
+            finished_episodes,
+        })
+    }
+
+    fn get_episode(&mut self, episode_id: EpisodeId) -> DbResult<Option<Episode>> {
+        Ok(self.data.episode(episode_id).map(|episode| Episode {
+            id: episode.id,
+            feed_id: episode.feed_id,
+            episode_number: episode.episode_number,
+            season_number: episode.season_number,
+            title: episode.title.clone(),
+            description: episode.description.clone(),
+            link: episode.link.clone(),
+            status: episode.status.clone(),
+            duration: episode.duration,
+            publication_date: episode.publication_date,
+            media_url: episode.media_url.clone(),
+        }))
+    }
+
+    fn get_episode_playback_data(
+        &mut self,
+        episode_id: EpisodeId,
+    ) -> DbResult<Option<EpisodePlaybackData>> {
+        let episode = match self.data.episode(episode_id) {
+            Some(episode) => episode,
+            None => return Ok(None),
+        };
+        let feed = match self.data.feed(episode.feed_id) {
+            Some(feed) => feed,
+            None => return Ok(None),
+        };
+        Ok(Some(EpisodePlaybackData {
+            id: episode.id,
+            media_url: episode.media_url.clone(),
+            position: episode.status.db_view().1,
+            duration: episode.duration,
+            episode_title: episode.title.clone(),
+            feed_id: feed.id,
+            feed_title: feed.title.clone(),
+            playback_rate: feed.playback_rate,
+            skip_intro: feed.skip_intro,
+            skip_outro: feed.skip_outro,
+        }))
+    }
+
+    fn get_episodes_list_metadata(
+        &mut self,
+        query: EpisodesQuery,
+    ) -> DbResult<EpisodesListMetadata> {
+        let episodes = self.data.query_episodes(&query);
+        let reversed_order = match (query.feed_id, episodes.is_empty()) {
+            (Some(feed_id), false) => self.data.feed(feed_id).map_or(false, |feed| feed.reversed),
+            _ => false,
+        };
+        Ok(EpisodesListMetadata {
+            items_count: episodes.len(),
+            max_season_number: episodes.iter().filter_map(|episode| episode.season_number).max(),
+            max_episode_number: (episodes.iter())
+                .filter_map(|episode| episode.episode_number)
+                .max(),
+            max_duration: episodes.iter().filter_map(|episode| episode.duration).max(),
+            has_publication_date: (episodes.iter())
+                .any(|episode| episode.publication_date.is_some()),
+            reversed_order,
+        })
+    }
+
+    fn get_episode_summaries(
+        &mut self,
+        query: EpisodesQuery,
+        range: Range<usize>,
+    ) -> DbResult<Vec<EpisodeSummary>> {
+        let mut episodes = self.data.query_episodes(&query);
+        episodes.sort_by(|a, b| publication_order(a, b));
+        if !query.reversed_order {
+            episodes.reverse();
+        }
+        Ok((episodes.into_iter())
+            .skip(range.start)
+            .take(range.end.saturating_sub(range.start))
+            .map(|episode| {
+                let feed_title = match query.include_feed_title {
+                    true => self.data.feed(episode.feed_id).and_then(|feed| feed.title.clone()),
+                    false => None,
+                };
+                episode.summary(feed_title)
+            })
+            .collect())
+    }
+
+    fn count_episodes(&mut self, query: EpisodesQuery) -> DbResult<usize> {
+        Ok(self.data.query_ids(&query).len())
+    }
+
+    fn get_next_episode(
+        &mut self,
+        query: EpisodesQuery,
+        episode_id: EpisodeId,
+    ) -> DbResult<Option<EpisodeId>> {
+        let query = query.with_hidden(false);
+        let current = match self.data.episode(episode_id) {
+            Some(current) => current,
+            None => return Ok(None),
+        };
+        let mut candidates: Vec<&EpisodeRecord> = (self.data.query_episodes(&query).into_iter())
+            .filter(|episode| episode.status != EpisodeStatus::Finished)
+            .filter(|episode| match query.reversed_order {
+                true => publication_order(episode, current) == Ordering::Greater,
+                false => publication_order(episode, current) == Ordering::Less,
+            })
+            .collect();
+        candidates.sort_by(|a, b| publication_order(a, b));
+        let next = match query.reversed_order {
+            true => candidates.first(),
+            false => candidates.last(),
+        };
+        Ok(next.map(|episode| episode.id))
+    }
+
+    fn create_feed_pending(&mut self, data: &NewFeedMetadata) -> DbResult<Option<FeedId>> {
+        match self.data.find_feed_by_source(&data.source, None) {
+            Some((feed_id, true)) => {
+                self.restore_feed(feed_id)?;
+                return Ok(Some(feed_id));
+            }
+            Some((_, false)) => return Ok(None),
+            None => {}
+        }
+
+        let id = FeedId(self.data.next_id());
+        self.data.feeds.push(FeedRecord {
+            id,
+            title: data.title.clone(),
+            title_override: None,
+            description: None,
+            link: data.link.clone(),
+            author: None,
+            copyright: None,
+            source: data.source.clone(),
+            status: FeedStatus::Pending,
+            enabled: true,
+            reversed: false,
+            group_id: None,
+            deleted_at: None,
+            playback_rate: None,
+            skip_intro: Duration::ZERO,
+            skip_outro: Duration::ZERO,
+            tags: BTreeSet::new(),
+        });
+        Ok(Some(id))
+    }
+
+    fn find_duplicate_feed(&mut self, feed_id: FeedId) -> DbResult<Option<String>> {
+        let feeds: Vec<&FeedRecord> = (self.data.feeds.iter())
+            .filter(|feed| feed.deleted_at.is_none())
+            .collect();
+        let (title, link) = match feeds.iter().find(|feed| feed.id == feed_id) {
+            Some(feed) if feed.title.as_deref().map_or(false, |t| !t.trim().is_empty()) => {
+                let title = feed.title.as_deref().unwrap_or_default();
+                let link = (feed.link.as_deref())
+                    .filter(|link| !link.trim().is_empty())
+                    .map(feed_url::comparison_key);
+                (title.trim().to_lowercase(), link)
+            }
+            _ => return Ok(None),
+        };
+        let duplicate = feeds.into_iter().find(|feed| {
+            let same_title = (feed.title.as_deref())
+                .map_or(false, |other_title| other_title.trim().to_lowercase() == title);
+            let same_link = match (&link, feed.link.as_deref()) {
+                (Some(link), Some(other_link)) if !other_link.trim().is_empty() => {
+                    *link == feed_url::comparison_key(other_link)
+                }
+                _ => true,
+            };
+            feed.id != feed_id && same_title && same_link
+        });
+        Ok(duplicate.map(FeedRecord::display_title))
+    }
+
+    fn set_feed_source(&mut self, feed_id: FeedId, source: &str) -> DbResult<bool> {
+        if let Some((_, false)) = self.data.find_feed_by_source(source, Some(feed_id)) {
+            return Ok(false);
+        }
+        if let Some(feed) = self.data.feed_mut(feed_id) {
+            feed.source = source.to_string();
+        }
+        Ok(true)
+    }
+
+    fn delete_feed(&mut self, id: FeedId) -> DbResult<()> {
+        if let Some(feed) = self.data.feed_mut(id) {
+            feed.deleted_at = Some(Utc::now());
+        }
+        Ok(())
+    }
+
+    fn merge_feed(&mut self, feed_id: FeedId, target_id: FeedId) -> DbResult<usize> {
+        let data = &mut self.data;
+        let sources: Vec<EpisodeRecord> = (data.episodes.iter())
+            .filter(|episode| episode.feed_id == feed_id)
+            .cloned()
+            .collect();
+        let mut matched = HashSet::new();
+        for source in sources {
+            let mut candidates: Vec<&EpisodeRecord> = (data.episodes.iter())
+                .filter(|episode| episode.feed_id == target_id)
+                .filter(|episode| {
+                    episode.guid == source.guid
+                        || episode.media_url == source.media_url
+                        || episode.same_title_and_date(
+                            source.title.as_deref(),
+                            source.publication_date,
+                        )
+                })
+                .collect();
+            candidates.sort_by_key(|episode| {
+                (
+                    episode.guid != source.guid,
+                    episode.media_url != source.media_url,
+                    episode.id.as_i64(),
+                )
+            });
+            let target = candidates
+                .into_iter()
+                .map(|episode| episode.id)
+                .find(|id| !matched.contains(id));
+            let target = match target {
+                Some(target) => target,
+                None => continue,
+            };
+            matched.insert(target);
+
+            let episode = (data.episodes.iter_mut()).find(|episode| episode.id == target);
+            if let Some(episode) = episode {
+                if restores_status(&episode.status, &source.status) {
+                    episode.status = source.status.clone();
+                }
+                episode.hidden |= source.hidden;
+                episode.tags.extend(source.tags.iter().cloned());
+            }
+
+            for bookmark in &mut data.bookmarks {
+                if bookmark.episode_id == source.id {
+                    bookmark.episode_id = target;
+                }
+            }
+            let target_sessions: HashSet<DateTime<Utc>> = (data.sessions.iter())
+                .filter(|session| session.episode_id == target)
+                .map(|session| session.started_at)
+                .collect();
+            for session in &mut data.sessions {
+                if session.episode_id == source.id
+                    && !target_sessions.contains(&session.started_at)
+                {
+                    session.episode_id = target;
+                }
+            }
+            if !data.queue.contains(&target) {
+                for episode_id in &mut data.queue {
+                    if *episode_id == source.id {
+                        *episode_id = target;
+                    }
+                }
+            }
+        }
+
+        let (group_id, tags) = match data.feed(feed_id) {
+            Some(feed) => (feed.group_id, feed.tags.clone()),
+            None => (None, BTreeSet::new()),
+        };
+        if let Some(target) = data.feed_mut(target_id) {
+            target.group_id = target.group_id.or(group_id);
+            target.tags.extend(tags);
+        }
+        if let Some(feed) = data.feed_mut(feed_id) {
+            feed.deleted_at = Some(Utc::now());
+        }
+        Ok(matched.len())
+    }
+
+    fn restore_feed(&mut self, id: FeedId) -> DbResult<()> {
+        if let Some(feed) = self.data.feed_mut(id) {
+            feed.deleted_at = None;
+        }
+        Ok(())
+    }
+
+    fn get_trashed_feeds(&mut self) -> DbResult<Vec<TrashedFeed>> {
+        let mut feeds: Vec<TrashedFeed> = (self.data.feeds.iter())
+            .filter_map(|feed| {
+                Some(TrashedFeed {
+                    id: feed.id,
+                    title: feed.display_title(),
+                    deleted_at: feed.deleted_at?,
+                })
+            })
+            .collect();
+        feeds.sort_by_key(|feed| std::cmp::Reverse(feed.deleted_at));
+        Ok(feeds)
+    }
+
+    fn purge_trash(&mut self, deleted_before: Option<DateTime<Utc>>) -> DbResult<usize> {
+        Ok(self.data.delete_feeds(|feed| match (feed.deleted_at, deleted_before) {
+            (Some(deleted_at), Some(deleted_before)) => deleted_at < deleted_before,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }))
+    }
+
+    fn set_feed_status(&mut self, feed_id: FeedId, status: FeedStatus) -> DbResult<()> {
+        if let Some(feed) = self.data.feed_mut(feed_id) {
+            feed.status = status;
+        }
+        Ok(())
+    }
+
+    fn set_feed_enabled(&mut self, feed_id: FeedId, enabled: bool) -> DbResult<()> {
+        if let Some(feed) = self.data.feed_mut(feed_id) {
+            feed.enabled = enabled;
+        }
+        Ok(())
+    }
+
+    fn set_feed_playback_rate(&mut self, feed_id: FeedId, rate: Option<f64>) -> DbResult<()> {
+        if let Some(feed) = self.data.feed_mut(feed_id) {
+            feed.playback_rate = rate;
+        }
+        Ok(())
+    }
+
+    fn set_feed_skip_intro(&mut self, feed_id: FeedId, duration: Duration) -> DbResult<()> {
+        if let Some(feed) = self.data.feed_mut(feed_id) {
+            feed.skip_intro = duration;
+        }
+        Ok(())
+    }
+
+    fn set_feed_skip_outro(&mut self, feed_id: FeedId, duration: Duration) -> DbResult<()> {
+        if let Some(feed) = self.data.feed_mut(feed_id) {
+            feed.skip_outro = duration;
+        }
+        Ok(())
+    }
+
+    fn reverse_feed_order(&mut self, feed_id: FeedId) -> DbResult<()> {
+        if let Some(feed) = self.data.feed_mut(feed_id) {
+            feed.reversed = !feed.reversed;
+        }
+        Ok(())
+    }
+
+    fn set_episode_status(
+        &mut self,
+        query: EpisodesQuery,
+        status: EpisodeStatus,
+    ) -> DbResult<HashSet<FeedId>> {
+        let ids: HashSet<EpisodeId> = self.data.query_ids(&query).into_iter().collect();
+        let mut feed_ids = HashSet::new();
+        for episode in &mut self.data.episodes {
+            if ids.contains(&episode.id) {
+                episode.status = status.clone();
+                feed_ids.insert(episode.feed_id);
+            }
+        }
+        Ok(feed_ids)
+    }
+
+    fn set_episode_hidden(&mut self, query: EpisodesQuery, hidden: bool) -> DbResult<()> {
+        let ids: HashSet<EpisodeId> = self.data.query_ids(&query).into_iter().collect();
+        for episode in &mut self.data.episodes {
+            if ids.contains(&episode.id) {
+                episode.hidden = hidden;
+            }
+        }
+        Ok(())
+    }
+
+    fn get_episode_states(&mut self, query: EpisodesQuery) -> DbResult<Vec<EpisodeState>> {
+        Ok((self.data.query_episodes(&query).into_iter())
+            .map(|episode| EpisodeState {
+                id: episode.id,
+                status: episode.status.clone(),
+                hidden: episode.hidden,
+            })
+            .collect())
+    }
+
+    fn restore_episode_states(&mut self, states: &[EpisodeState]) -> DbResult<HashSet<FeedId>> {
+        let mut feed_ids = HashSet::new();
+        for state in states {
+            let episode = (self.data.episodes.iter_mut()).find(|episode| episode.id == state.id);
+            if let Some(episode) = episode {
+                episode.status = state.status.clone();
+                episode.hidden = state.hidden;
+                feed_ids.insert(episode.feed_id);
+            }
+        }
+        Ok(feed_ids)
+    }
+
+    fn writer<'a>(&'a mut self, feed_id: FeedId) -> DbResult<Box<dyn EpisodeWriter + 'a>> {
+        let data = self.data.clone();
+        Ok(Box::new(MemoryEpisodeWriter {
+            provider: self,
+            data,
+            feed_id,
+            written: HashSet::new(),
+        }))
+    }
+}
+
+/// Writes the changes to a copy of the data, which replaces the provider's data when the writer
+/// is closed, so that the changes are discarded if the writer is dropped.
+pub struct MemoryEpisodeWriter<'a> {
+    provider: &'a mut MemoryDataProvider,
+    data: Data,
+    feed_id: FeedId,
+    written: HashSet<EpisodeId>,
+}
+
+impl<'a> MemoryEpisodeWriter<'a> {
+    fn find_episode(&mut self, guid: &str) -> Option<&mut EpisodeRecord> {
+        let feed_id = self.feed_id;
+        (self.data.episodes.iter_mut())
+            .find(|episode| episode.feed_id == feed_id && episode.guid == guid)
+    }
+}
+
+impl<'a> EpisodeWriter for MemoryEpisodeWriter<'a> {
+    fn set_feed_metadata(&mut self, metadata: &FeedMetadata) -> DbResult<()> {
+        if let Some(feed) = self.data.feed_mut(self.feed_id) {
+            feed.title = Some(metadata.title.to_string());
+            feed.description = Some(metadata.description.to_string());
+            feed.link = Some(metadata.link.to_string());
+            feed.author = metadata.author.map(str::to_string);
+            feed.copyright = metadata.copyright.map(str::to_string);
+            feed.status = FeedStatus::Loaded;
+        }
+        Ok(())
+    }
+
+    fn set_episode_metadata(&mut self, metadata: &EpisodeMetadata) -> DbResult<EpisodeId> {
+        if self.data.feed(self.feed_id).is_none() {
+            return Err(QueryError::NotFound);
+        }
+        let episode_id = match self.find_episode(metadata.guid) {
+            Some(episode) => {
+                episode.title = metadata.title.map(str::to_string);
+                episode.description = metadata.description.map(str::to_string);
+                episode.link = metadata.link.map(str::to_string);
+                episode.duration = metadata.duration;
+                episode.publication_date = metadata.publication_date;
+                episode.episode_number = metadata.episode_number;
+                episode.season_number = metadata.season_number;
+                episode.media_url = metadata.media_url.to_string();
+                episode.id
+            }
+            None => {
+                let id = EpisodeId(self.data.next_id());
+                self.data.episodes.push(EpisodeRecord {
+                    id,
+                    feed_id: self.feed_id,
+                    guid: metadata.guid.to_string(),
+                    title: metadata.title.map(str::to_string),
+                    description: metadata.description.map(str::to_string),
+                    link: metadata.link.map(str::to_string),
+                    duration: metadata.duration,
+                    publication_date: metadata.publication_date,
+                    episode_number: metadata.episode_number,
+                    season_number: metadata.season_number,
+                    media_url: metadata.media_url.to_string(),
+                    status: EpisodeStatus::New,
+                    hidden: false,
+                    tags: BTreeSet::new(),
+                });
+                id
+            }
+        };
+        self.written.insert(episode_id);
+        Ok(episode_id)
+    }
+
+    fn relink_episode(&mut self, metadata: &EpisodeMetadata) -> DbResult<()> {
+        if self.find_episode(metadata.guid).is_some() {
+            return Ok(());
+        }
+
+        let feed_id = self.feed_id;
+        let mut candidates: Vec<&mut EpisodeRecord> = (self.data.episodes.iter_mut())
+            .filter(|episode| episode.feed_id == feed_id)
+            .filter(|episode| {
+                episode.media_url == metadata.media_url
+                    || episode.same_title_and_date(metadata.title, metadata.publication_date)
+            })
+            .collect();
+        candidates
+            .sort_by_key(|episode| (episode.media_url != metadata.media_url, episode.id.as_i64()));
+        let written = &self.written;
+        let episode = (candidates.into_iter()).find(|episode| !written.contains(&episode.id));
+        if let Some(episode) = episode {
+            episode.guid = metadata.guid.to_string();
+        }
+        Ok(())
+    }
+
+    fn delete_episode(&mut self, guid: &str) -> DbResult<()> {
+        let feed_id = self.feed_id;
+        (self.data).delete_episodes(|episode| episode.feed_id == feed_id && episode.guid == guid);
+        Ok(())
+    }
+
+    fn close(self: Box<Self>) -> DbResult<()> {
+        let MemoryEpisodeWriter { provider, data, .. } = *self;
+        provider.data = data;
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+//! Reads the database exported from AntennaPod with "Export database" in its settings.
+//!
+//! Episodes without media files are skipped, as are local folders added as feeds and the feeds
+//! that were only previewed without subscribing. Custom feed titles are imported as title
+//! overrides.
+
+use super::{duration_millis, has_column, timestamp_millis, ClientEpisode, ClientFeed, Error};
+use crate::model::EpisodeStatus;
+use rusqlite::{Connection, OpenFlags};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The values of the `read` column of the `FeedItems` table.
+const STATE_NEW: i64 = -1;
+const STATE_PLAYED: i64 = 1;
+
+/// The value of the `state` column of the `Feeds` table for the feeds that are not subscribed to.
+/// The column is missing in the databases of older versions, which only store subscriptions.
+const FEED_STATE_NOT_SUBSCRIBED: i64 = 1;
+
+fn episode_status(state: i64, position: i64) -> EpisodeStatus {
+    match (state, duration_millis(position)) {
+        (STATE_PLAYED, _) => EpisodeStatus::Finished,
+        (_, Some(position)) => EpisodeStatus::Started(position),
+        (STATE_NEW, None) => EpisodeStatus::New,
+        (_, None) => EpisodeStatus::NotStarted,
+    }
+}
+
+pub fn read_feeds(path: &Path) -> Result<Vec<ClientFeed>, Error> {
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+    let filter = if has_column(&connection, "Feeds", "state")? {
+        format!("WHERE state IS NOT {}", FEED_STATE_NOT_SUBSCRIBED)
+    } else {
+        String::new()
+    };
+    let mut statement = connection.prepare(&format!(
+        "SELECT id, download_url, title, custom_title, link FROM Feeds {} ORDER BY id",
+        filter
+    ))?;
+    let rows = statement.query_map([], |row| {
+        let feed = ClientFeed {
+            title: row.get(2)?,
+            title_override: row.get(3)?,
+            link: row.get(4)?,
+            ..ClientFeed::new(row.get(1)?)
+        };
+        Ok((row.get::<_, i64>(0)?, feed))
+    })?;
+    let mut ids = HashMap::new();
+    let mut feeds = Vec::new();
+    for row in rows {
+        let (id, feed) = row?;
+        ids.insert(id, feeds.len());
+        feeds.push(feed);
+    }
+
+    let mut statement = connection.prepare(
+        "SELECT FeedItems.feed, FeedItems.item_identifier, FeedMedia.download_url,
+                FeedItems.title, FeedItems.description, FeedItems.link, FeedItems.pubDate,
+                FeedMedia.duration, FeedMedia.position, FeedItems.read
+        FROM FeedItems JOIN FeedMedia ON FeedMedia.feeditem = FeedItems.id
+        WHERE FeedMedia.download_url IS NOT NULL
+        ORDER BY FeedItems.feed, FeedItems.pubDate, FeedItems.id",
+    )?;
+    let rows = statement.query_map([], |row| {
+        let media_url: String = row.get(2)?;
+        let guid: Option<String> = row.get(1)?;
+        let episode = ClientEpisode {
+            guid: guid.unwrap_or_else(|| media_url.clone()),
+            title: row.get(3)?,
+            description: row.get(4)?,
+            link: row.get(5)?,
+            media_url,
+            duration: row.get::<_, Option<i64>>(7)?.and_then(duration_millis),
+            publication_date: row.get::<_, Option<i64>>(6)?.and_then(timestamp_millis),
+            status: episode_status(row.get(9)?, row.get::<_, Option<i64>>(8)?.unwrap_or(0)),
+        };
+        Ok((row.get::<_, i64>(0)?, episode))
+    })?;
+    for row in rows {
+        let (feed_id, episode) = row?;
+        if let Some(index) = ids.get(&feed_id) {
+            feeds[*index].episodes.push(episode);
+        }
+    }
+    Ok(feeds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_feeds;
+    use crate::model::EpisodeStatus;
+    use rusqlite::Connection;
+    use std::time::Duration;
+
+    #[test]
+    fn reads_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("AntennaPodBackup.db");
+        let connection = Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE Feeds (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT, custom_title TEXT,
+                    download_url TEXT, link TEXT, state INTEGER DEFAULT 0
+                );
+                CREATE TABLE FeedItems (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT, pubDate INTEGER,
+                    read INTEGER, link TEXT, description TEXT, feed INTEGER,
+                    item_identifier TEXT
+                );
+                CREATE TABLE FeedMedia (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT, duration INTEGER, download_url TEXT,
+                    position INTEGER, feeditem INTEGER
+                );
+                INSERT INTO Feeds (id, title, custom_title, download_url, link, state) VALUES
+                    (1, 'Podcast', 'My podcast', 'https://example.com/feed.xml', NULL, 0),
+                    (2, 'Recordings', NULL, 'antennapod_local:content://recordings', NULL, 0),
+                    (3, 'Previewed', NULL, 'https://example.com/preview.xml', NULL, 1);
+                INSERT INTO FeedItems (id, title, pubDate, read, feed, item_identifier) VALUES
+                    (1, 'Played', 1600000000000, 1, 1, 'ep1'),
+                    (2, 'Started', 1600100000000, 0, 1, 'ep2'),
+                    (3, 'Seen', 1600200000000, 0, 1, NULL),
+                    (4, 'New', 1600300000000, -1, 1, 'ep4'),
+                    (5, 'Text only', 1600400000000, -1, 1, 'ep5'),
+                    (6, 'Preview', 1600500000000, -1, 3, 'ep6');
+                INSERT INTO FeedMedia (duration, download_url, position, feeditem) VALUES
+                    (600000, 'https://example.com/1.mp3', 0, 1),
+                    (600000, 'https://example.com/2.mp3', 90500, 2),
+                    (0, 'https://example.com/3.mp3', 0, 3),
+                    (600000, 'https://example.com/4.mp3', 0, 4),
+                    (600000, 'https://example.com/6.mp3', 0, 6);",
+            )
+            .unwrap();
+        drop(connection);
+
+        let feeds = read_feeds(&path).unwrap();
+        assert_eq!(feeds.len(), 2);
+        assert_eq!(feeds[0].title.as_deref(), Some("Podcast"));
+        assert_eq!(feeds[0].title_override.as_deref(), Some("My podcast"));
+        assert_eq!(feeds[1].source, "antennapod_local:content://recordings");
+
+        let episodes: Vec<_> = (feeds[0].episodes.iter())
+            .map(|episode| (episode.guid.as_str(), episode.status.clone()))
+            .collect();
+        assert_eq!(
+            episodes,
+            vec![
+                ("ep1", EpisodeStatus::Finished),
+                ("ep2", EpisodeStatus::Started(Duration::from_millis(90500))),
+                ("https://example.com/3.mp3", EpisodeStatus::NotStarted),
+                ("ep4", EpisodeStatus::New),
+            ]
+        );
+        assert_eq!(feeds[0].episodes[0].duration, Some(Duration::from_secs(600)));
+        assert_eq!(feeds[0].episodes[2].duration, None);
+    }
+
+    #[test]
+    fn reads_database_without_feed_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("AntennaPodBackup.db");
+        let connection = Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE Feeds (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT, custom_title TEXT,
+                    download_url TEXT, link TEXT
+                );
+                CREATE TABLE FeedItems (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT, pubDate INTEGER,
+                    read INTEGER, link TEXT, description TEXT, feed INTEGER,
+                    item_identifier TEXT
+                );
+                CREATE TABLE FeedMedia (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT, duration INTEGER, download_url TEXT,
+                    position INTEGER, feeditem INTEGER
+                );
+                INSERT INTO Feeds (id, title, custom_title, download_url, link) VALUES
+                    (1, 'Podcast', NULL, 'https://example.com/feed.xml', NULL);",
+            )
+            .unwrap();
+        drop(connection);
+
+        let feeds = read_feeds(&path).unwrap();
+        assert_eq!(feeds.len(), 1);
+        assert_eq!(feeds[0].source, "https://example.com/feed.xml");
+    }
+}
@@ -0,0 +1,175 @@
+//! Reads the database of gPodder 3, usually located at `~/gPodder/Database`.
+//!
+//! gPodder marks an episode as new until it is played or marked as old. Episodes that are not new
+//! are imported as started if they have a playback position before the end, as finished if they
+//! were played to the end or have a playback date, and as seen otherwise, since marking an
+//! episode as old doesn't mean it was listened to. Paused subscriptions are imported as disabled
+//! feeds.
+
+use super::{has_column, ClientEpisode, ClientFeed, Error};
+use crate::model::EpisodeStatus;
+use chrono::{TimeZone, Utc};
+use rusqlite::{Connection, OpenFlags};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+fn episode_status(
+    is_new: bool,
+    position: i64,
+    total_time: i64,
+    last_playback: i64,
+) -> EpisodeStatus {
+    if position > 0 && (total_time <= 0 || position < total_time) {
+        EpisodeStatus::Started(Duration::from_secs(position as u64))
+    } else if is_new {
+        EpisodeStatus::New
+    } else if position > 0 || last_playback > 0 {
+        EpisodeStatus::Finished
+    } else {
+        EpisodeStatus::NotStarted
+    }
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|value| !value.is_empty())
+}
+
+pub fn read_feeds(path: &Path) -> Result<Vec<ClientFeed>, Error> {
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+    let mut statement = connection
+        .prepare("SELECT id, url, title, link, pause_subscription FROM podcast ORDER BY id")?;
+    let rows = statement.query_map([], |row| {
+        let feed = ClientFeed {
+            title: non_empty(row.get(2)?),
+            link: non_empty(row.get(3)?),
+            enabled: !row.get::<_, bool>(4)?,
+            ..ClientFeed::new(row.get(1)?)
+        };
+        Ok((row.get::<_, i64>(0)?, feed))
+    })?;
+    let mut ids = HashMap::new();
+    let mut feeds = Vec::new();
+    for row in rows {
+        let (id, feed) = row?;
+        ids.insert(id, feeds.len());
+        feeds.push(feed);
+    }
+
+    // Older databases don't record when an episode was played.
+    let last_playback = if has_column(&connection, "episode", "last_playback")? {
+        "last_playback"
+    } else {
+        "0"
+    };
+    let mut statement = connection.prepare(&format!(
+        "SELECT podcast_id, guid, url, title, description, link, published, total_time,
+                current_position, is_new, {}
+        FROM episode ORDER BY podcast_id, published, id",
+        last_playback
+    ))?;
+    let rows = statement.query_map([], |row| {
+        let media_url: String = row.get(2)?;
+        let guid = non_empty(row.get(1)?).unwrap_or_else(|| media_url.clone());
+        let published: i64 = row.get(6)?;
+        let total_time: i64 = row.get(7)?;
+        let episode = ClientEpisode {
+            guid,
+            title: non_empty(row.get(3)?),
+            description: non_empty(row.get(4)?),
+            link: non_empty(row.get(5)?),
+            media_url,
+            duration: Some(Duration::from_secs(total_time.max(0) as u64))
+                .filter(|duration| !duration.is_zero()),
+            publication_date: Some(published)
+                .filter(|published| *published > 0)
+                .and_then(|published| Utc.timestamp_opt(published, 0).single()),
+            status: episode_status(row.get(9)?, row.get(8)?, total_time, row.get(10)?),
+        };
+        Ok((row.get::<_, i64>(0)?, episode))
+    })?;
+    for row in rows {
+        let (podcast_id, episode) = row?;
+        if let Some(index) = ids.get(&podcast_id) {
+            feeds[*index].episodes.push(episode);
+        }
+    }
+    Ok(feeds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_feeds;
+    use crate::model::EpisodeStatus;
+    use chrono::{TimeZone, Utc};
+    use rusqlite::Connection;
+    use std::time::Duration;
+
+    #[test]
+    fn reads_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Database");
+        let connection = Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE podcast (
+                    id INTEGER PRIMARY KEY NOT NULL, title TEXT NOT NULL DEFAULT '',
+                    url TEXT NOT NULL DEFAULT '', link TEXT NOT NULL DEFAULT '',
+                    pause_subscription INTEGER NOT NULL DEFAULT 0
+                );
+                CREATE TABLE episode (
+                    id INTEGER PRIMARY KEY NOT NULL, podcast_id INTEGER NOT NULL,
+                    title TEXT NOT NULL DEFAULT '', description TEXT NOT NULL DEFAULT '',
+                    url TEXT NOT NULL, published INTEGER NOT NULL DEFAULT 0, guid TEXT NOT NULL,
+                    link TEXT NOT NULL DEFAULT '', is_new INTEGER NOT NULL DEFAULT 0,
+                    total_time INTEGER NOT NULL DEFAULT 0,
+                    current_position INTEGER NOT NULL DEFAULT 0,
+                    last_playback INTEGER NOT NULL DEFAULT 0
+                );
+                INSERT INTO podcast (id, title, url, link, pause_subscription) VALUES
+                    (1, 'Podcast', 'https://example.com/feed.xml', 'https://example.com', 0),
+                    (2, '', 'https://example.com/paused.xml', '', 1);
+                INSERT INTO episode (podcast_id, title, url, published, guid, is_new, total_time,
+                                     current_position, last_playback) VALUES
+                    (1, 'Second', 'https://example.com/2.mp3', 1600100000, 'ep2', 1, 600, 0, 0),
+                    (1, 'First', 'https://example.com/1.mp3', 1600000000, 'ep1', 0, 600, 600, 0),
+                    (1, 'Third', 'https://example.com/3.mp3', 1600200000, '', 0, 0, 90, 0),
+                    (1, 'Fourth', 'https://example.com/4.mp3', 1600300000, 'ep4', 0, 600, 0, 0),
+                    (1, 'Fifth', 'https://example.com/5.mp3', 1600400000, 'ep5', 0, 600, 0,
+                     1600500000);",
+            )
+            .unwrap();
+        drop(connection);
+
+        let feeds = read_feeds(&path).unwrap();
+        assert_eq!(feeds.len(), 2);
+        assert_eq!(feeds[0].source, "https://example.com/feed.xml");
+        assert_eq!(feeds[0].title.as_deref(), Some("Podcast"));
+        assert!(feeds[0].enabled);
+        assert_eq!(feeds[1].title, None);
+        assert!(!feeds[1].enabled);
+        assert!(feeds[1].episodes.is_empty());
+
+        let episodes: Vec<_> = (feeds[0].episodes.iter())
+            .map(|episode| (episode.guid.as_str(), episode.status.clone()))
+            .collect();
+        assert_eq!(
+            episodes,
+            vec![
+                ("ep1", EpisodeStatus::Finished),
+                ("ep2", EpisodeStatus::New),
+                (
+                    "https://example.com/3.mp3",
+                    EpisodeStatus::Started(Duration::from_secs(90))
+                ),
+                ("ep4", EpisodeStatus::NotStarted),
+                ("ep5", EpisodeStatus::Finished),
+            ]
+        );
+        let first = &feeds[0].episodes[0];
+        assert_eq!(first.title.as_deref(), Some("First"));
+        assert_eq!(first.duration, Some(Duration::from_secs(600)));
+        assert_eq!(first.publication_date, Some(Utc.timestamp(1600000000, 0)));
+    }
+}
@@ -0,0 +1,411 @@
+//! Importers of subscriptions and listening history from other podcast clients.
+//!
+//! Each importer reads the data of another client into a list of [`ClientFeed`]s, which are then
+//! written to the library by [`import_feeds`]. Feeds that are already in the library are not
+//! added again, but the listening history of their episodes is still merged into the library
+//! following the same rules as restoring a backup: a status only replaces the status of an
+//! existing episode if it has more progress.
+
+pub mod antennapod;
+pub mod gpodder;
+pub mod newsboat;
+
+use crate::backup::restores_status;
+use crate::datasource::DataProvider;
+use crate::feed_url;
+use crate::metadata::EpisodeMetadata;
+use crate::model::{EpisodeId, EpisodeSnapshot, EpisodeStatus, FeedId, TagTarget};
+use crate::opml::{ImportEntry, ImportReport, InvalidOutline, InvalidReason};
+use crate::{EpisodesQuery, NewFeedMetadata, QueryError};
+use chrono::{DateTime, TimeZone, Utc};
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::io;
+use std::time::Duration;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Db(#[from] QueryError),
+
+    #[error("Cannot read the database of the other client, {0}")]
+    Source(#[from] rusqlite::Error),
+}
+
+/// A subscription read from another client.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientFeed {
+    pub source: String,
+    pub title: Option<String>,
+    pub title_override: Option<String>,
+    pub link: Option<String>,
+    pub enabled: bool,
+    pub tags: Vec<String>,
+    pub episodes: Vec<ClientEpisode>,
+}
+
+impl ClientFeed {
+    pub(crate) fn new(source: String) -> Self {
+        ClientFeed {
+            source,
+            title: None,
+            title_override: None,
+            link: None,
+            enabled: true,
+            tags: Vec::new(),
+            episodes: Vec::new(),
+        }
+    }
+}
+
+/// An episode known to another client along with its playback state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientEpisode {
+    pub guid: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub link: Option<String>,
+    pub media_url: String,
+    pub duration: Option<Duration>,
+    pub publication_date: Option<DateTime<Utc>>,
+    pub status: EpisodeStatus,
+}
+
+impl ClientEpisode {
+    fn metadata(&self) -> EpisodeMetadata<'_> {
+        EpisodeMetadata {
+            title: self.title.as_deref(),
+            description: self.description.as_deref(),
+            link: self.link.as_deref(),
+            guid: &self.guid,
+            duration: self.duration,
+            publication_date: self.publication_date,
+            episode_number: None,
+            season_number: None,
+            media_url: &self.media_url,
+            block: false,
+        }
+    }
+}
+
+/// Converts a UNIX timestamp in milliseconds, treating non-positive values as missing dates.
+pub(crate) fn timestamp_millis(millis: i64) -> Option<DateTime<Utc>> {
+    if millis <= 0 {
+        return None;
+    }
+    let nanos = (millis % 1000) as u32 * 1_000_000;
+    Utc.timestamp_opt(millis / 1000, nanos).single()
+}
+
+/// Converts a non-negative number of milliseconds, treating non-positive values as missing.
+pub(crate) fn duration_millis(millis: i64) -> Option<Duration> {
+    if millis <= 0 {
+        return None;
+    }
+    Some(Duration::from_millis(millis as u64))
+}
+
+pub fn import_feeds<D: DataProvider>(
+    feeds: Vec<ClientFeed>,
+    data: &mut D,
+) -> Result<ImportReport, Error> {
+    import(feeds, data, false)
+}
+
+/// Reports what `import_feeds` would do without changing the library.
+pub fn check_feeds_import<D: DataProvider>(
+    feeds: Vec<ClientFeed>,
+    data: &mut D,
+) -> Result<ImportReport, Error> {
+    import(feeds, data, true)
+}
+
+fn import<D: DataProvider>(
+    feeds: Vec<ClientFeed>,
+    data: &mut D,
+    dry_run: bool,
+) -> Result<ImportReport, Error> {
+    let mut known_feeds: HashMap<String, Option<FeedId>> = (data.get_feed_snapshots()?)
+        .into_iter()
+        .map(|feed| (feed_url::comparison_key(&feed.source), Some(feed.id)))
+        .collect();
+    let mut report = ImportReport::default();
+    for feed in feeds {
+        if !feed_url::is_valid(&feed.source) {
+            report.invalid.push(InvalidOutline {
+                title: feed.title_override.or(feed.title),
+                source: Some(feed.source),
+                reason: InvalidReason::InvalidUrl,
+            });
+            continue;
+        }
+
+        let metadata = NewFeedMetadata::new(feed.source.clone())
+            .with_title(feed.title.clone())
+            .with_link(feed.link.clone());
+        let mut entry = ImportEntry {
+            feed_id: None,
+            source: metadata.source.clone(),
+            title: feed.title_override.clone().or_else(|| feed.title.clone()),
+        };
+        let key = feed_url::comparison_key(&metadata.source);
+        if let Some(feed_id) = known_feeds.get(&key) {
+            if let (Some(feed_id), false) = (feed_id, dry_run) {
+                write_episodes(data, *feed_id, &feed.episodes)?;
+            }
+            report.duplicates.push(entry);
+            continue;
+        }
+        if dry_run {
+            known_feeds.insert(key, None);
+            report.added.push(entry);
+            continue;
+        }
+
+        let feed_id = match data.create_feed_pending(&metadata)? {
+            Some(feed_id) => feed_id,
+            None => {
+                report.duplicates.push(entry);
+                continue;
+            }
+        };
+        known_feeds.insert(key, Some(feed_id));
+        if feed.title_override.is_some() {
+            data.rename_feed(feed_id, feed.title_override.clone())?;
+        }
+        if !feed.enabled {
+            data.set_feed_enabled(feed_id, false)?;
+        }
+        for tag in &feed.tags {
+            data.add_tag(TagTarget::Feed(feed_id), tag)?;
+        }
+        write_episodes(data, feed_id, &feed.episodes)?;
+        entry.feed_id = Some(feed_id);
+        report.added.push(entry);
+    }
+    Ok(report)
+}
+
+/// Finds the existing episode the client's episode corresponds to. Episodes are matched by GUID,
+/// then by the media URL, and then by the title and the publication date, as clients may store
+/// GUIDs differently or fall back to the media URL when the feed has none.
+fn find_existing<'a>(
+    existing: &'a [EpisodeSnapshot],
+    episode: &ClientEpisode,
+) -> Option<&'a EpisodeSnapshot> {
+    (existing.iter().find(|current| current.guid == episode.guid))
+        .or_else(|| (existing.iter()).find(|current| current.media_url == episode.media_url))
+        .or_else(|| {
+            let title = episode.title.as_ref()?;
+            let date = episode.publication_date?;
+            (existing.iter()).find(|current| {
+                current.title.as_ref() == Some(title) && current.publication_date == Some(date)
+            })
+        })
+}
+
+fn write_episodes<D: DataProvider>(
+    data: &mut D,
+    feed_id: FeedId,
+    episodes: &[ClientEpisode],
+) -> Result<(), Error> {
+    let existing = data.get_episode_snapshots(feed_id)?;
+    let matched: Vec<Option<(EpisodeId, EpisodeStatus)>> = (episodes.iter())
+        .map(|episode| {
+            find_existing(&existing, episode).map(|current| (current.id, current.status.clone()))
+        })
+        .collect();
+
+    let mut created = Vec::new();
+    let mut writer = data.writer(feed_id)?;
+    for (episode, current) in episodes.iter().zip(&matched) {
+        if current.is_none() {
+            let episode_id = writer.set_episode_metadata(&episode.metadata())?;
+            created.push((episode_id, episode.status.clone()));
+        }
+    }
+    writer.close()?;
+
+    for (episode_id, status) in created {
+        if status != EpisodeStatus::New {
+            data.set_episode_status(EpisodesQuery::default().id(episode_id), status)?;
+        }
+    }
+    for (episode, current) in episodes.iter().zip(matched) {
+        if let Some((episode_id, status)) = current {
+            if restores_status(&status, &episode.status) {
+                let query = EpisodesQuery::default().id(episode_id);
+                data.set_episode_status(query, episode.status.clone())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether the table of the other client's database has the column, for the columns that were
+/// added in later versions of the client.
+pub(crate) fn has_column(
+    connection: &Connection,
+    table: &str,
+    column: &str,
+) -> Result<bool, rusqlite::Error> {
+    connection.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+        [table, column],
+        |row| row.get(0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{import_feeds, ClientEpisode, ClientFeed};
+    use crate::datasource::DataProvider;
+    use crate::model::EpisodeStatus;
+    use crate::{EpisodesQuery, NewFeedMetadata, SqliteDataProvider};
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    fn episode(guid: &str, status: EpisodeStatus) -> ClientEpisode {
+        ClientEpisode {
+            guid: guid.to_string(),
+            title: Some(guid.to_uppercase()),
+            description: None,
+            link: None,
+            media_url: format!("http://example.com/{}.mp3", guid),
+            duration: Some(Duration::from_secs(600)),
+            publication_date: None,
+            status,
+        }
+    }
+
+    fn statuses(data: &mut SqliteDataProvider) -> Vec<Vec<(String, EpisodeStatus)>> {
+        let feeds = data.get_feed_snapshots().unwrap();
+        (feeds.into_iter())
+            .map(|feed| {
+                let mut episodes: Vec<_> = (data.get_episode_snapshots(feed.id).unwrap())
+                    .into_iter()
+                    .map(|episode| (episode.guid, episode.status))
+                    .collect();
+                episodes.sort_by(|a, b| a.0.cmp(&b.0));
+                episodes
+            })
+            .collect()
+    }
+
+    #[test]
+    fn imports_feeds_with_history() {
+        let mut data = SqliteDataProvider::connect(":memory:").unwrap();
+        let existing_id = data
+            .create_feed_pending(&NewFeedMetadata::new(
+                "https://example.com/existing.xml".to_string(),
+            ))
+            .unwrap()
+            .unwrap();
+        let mut writer = data.writer(existing_id).unwrap();
+        let played = episode("played", EpisodeStatus::New);
+        let finished = episode("finished", EpisodeStatus::New);
+        writer.set_episode_metadata(&played.metadata()).unwrap();
+        let finished_id = writer.set_episode_metadata(&finished.metadata()).unwrap();
+        writer.close().unwrap();
+        let query = EpisodesQuery::default().id(finished_id);
+        data.set_episode_status(query, EpisodeStatus::Finished).unwrap();
+
+        let feeds = vec![
+            ClientFeed {
+                title_override: Some("Custom".to_string()),
+                enabled: false,
+                tags: vec!["news".to_string()],
+                episodes: vec![
+                    episode("a", EpisodeStatus::Finished),
+                    episode("b", EpisodeStatus::Started(Duration::from_secs(30))),
+                    episode("c", EpisodeStatus::New),
+                ],
+                ..ClientFeed::new("https://example.com/new.xml".to_string())
+            },
+            ClientFeed {
+                episodes: vec![
+                    episode("played", EpisodeStatus::Finished),
+                    episode("finished", EpisodeStatus::Started(Duration::from_secs(5))),
+                    episode("missing", EpisodeStatus::NotStarted),
+                ],
+                ..ClientFeed::new("http://example.com/existing.xml/".to_string())
+            },
+            ClientFeed::new("file:///home/user/podcast".to_string()),
+        ];
+        let report = import_feeds(feeds, &mut data).unwrap();
+        assert_eq!(report.added.len(), 1);
+        assert_eq!(report.duplicates.len(), 1);
+        assert_eq!(report.invalid.len(), 1);
+
+        assert_eq!(
+            statuses(&mut data),
+            vec![
+                vec![
+                    ("finished".to_string(), EpisodeStatus::Finished),
+                    ("missing".to_string(), EpisodeStatus::NotStarted),
+                    ("played".to_string(), EpisodeStatus::Finished),
+                ],
+                vec![
+                    ("a".to_string(), EpisodeStatus::Finished),
+                    ("b".to_string(), EpisodeStatus::Started(Duration::from_secs(30))),
+                    ("c".to_string(), EpisodeStatus::New),
+                ],
+            ]
+        );
+        let feed = (data.get_feed_snapshots().unwrap().into_iter())
+            .find(|feed| Some(feed.id) == report.added[0].feed_id)
+            .unwrap();
+        assert_eq!(feed.title_override.as_deref(), Some("Custom"));
+        assert!(!feed.enabled);
+        assert_eq!(feed.tags, vec!["news".to_string()]);
+    }
+
+    #[test]
+    fn matches_episodes_without_guid() {
+        let mut data = SqliteDataProvider::connect(":memory:").unwrap();
+        let feed_id = data
+            .create_feed_pending(&NewFeedMetadata::new(
+                "https://example.com/feed.xml".to_string(),
+            ))
+            .unwrap()
+            .unwrap();
+        let date = Utc.ymd(2022, 1, 1).and_hms(12, 0, 0);
+        let mut writer = data.writer(feed_id).unwrap();
+        writer.set_episode_metadata(&episode("one", EpisodeStatus::New).metadata()).unwrap();
+        let two = ClientEpisode {
+            publication_date: Some(date),
+            ..episode("two", EpisodeStatus::New)
+        };
+        writer.set_episode_metadata(&two.metadata()).unwrap();
+        writer.close().unwrap();
+
+        let feeds = vec![ClientFeed {
+            episodes: vec![
+                ClientEpisode {
+                    guid: "other-one".to_string(),
+                    ..episode("one", EpisodeStatus::Finished)
+                },
+                ClientEpisode {
+                    guid: "other-two".to_string(),
+                    media_url: "http://cdn.example.com/two.mp3".to_string(),
+                    publication_date: Some(date),
+                    ..episode("two", EpisodeStatus::Started(Duration::from_secs(30)))
+                },
+            ],
+            ..ClientFeed::new("https://example.com/feed.xml".to_string())
+        }];
+        import_feeds(feeds, &mut data).unwrap();
+
+        assert_eq!(
+            statuses(&mut data),
+            vec![vec![
+                ("one".to_string(), EpisodeStatus::Finished),
+                ("two".to_string(), EpisodeStatus::Started(Duration::from_secs(30))),
+            ]]
+        );
+    }
+}
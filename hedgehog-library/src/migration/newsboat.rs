@@ -0,0 +1,100 @@
+//! Reads the `urls` file of newsboat and podboat.
+//!
+//! Each line of the file contains a feed URL followed by its tags. Tags may be quoted, a tag
+//! starting with a tilde sets the title of the feed, and a tag starting with an exclamation mark
+//! hides the feed in newsboat, so it is ignored. Newsboat doesn't keep the playback state of
+//! episodes, so only subscriptions are imported.
+
+use super::{ClientFeed, Error};
+use std::io;
+
+/// Splits a line into words separated by whitespace, where double quotes group several words
+/// into one and a backslash escapes the next character within quotes.
+fn split_words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.peek().map_or(false, |ch| ch.is_whitespace()) {
+            chars.next();
+        }
+        let mut word = String::new();
+        match chars.peek() {
+            None => return words,
+            Some('"') => {
+                chars.next();
+                while let Some(ch) = chars.next() {
+                    match ch {
+                        '"' => break,
+                        '\\' => word.extend(chars.next()),
+                        ch => word.push(ch),
+                    }
+                }
+            }
+            Some(_) => {
+                while let Some(ch) = chars.next_if(|ch| !ch.is_whitespace()) {
+                    word.push(ch);
+                }
+            }
+        }
+        words.push(word);
+    }
+}
+
+pub fn read_feeds<R: io::BufRead>(reader: R) -> Result<Vec<ClientFeed>, Error> {
+    let mut feeds = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut words = split_words(line).into_iter();
+        let mut feed = match words.next() {
+            Some(source) => ClientFeed::new(source),
+            None => continue,
+        };
+        for word in words {
+            if let Some(title) = word.strip_prefix('~') {
+                feed.title_override = Some(title.to_string());
+            } else if !word.starts_with('!') && !word.is_empty() {
+                feed.tags.push(word);
+            }
+        }
+        feeds.push(feed);
+    }
+    Ok(feeds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_feeds;
+    use crate::migration::ClientFeed;
+    use std::io::Cursor;
+
+    #[test]
+    fn reads_urls_file() {
+        let urls = concat!(
+            "# podcasts\n",
+            "https://example.com/feed.xml podcast \"tech news\"\n",
+            "\n",
+            "  https://example.com/other.xml \"~The \\\"other\\\" show\" ! \"!hidden\"\n",
+            "\"query:Unread:unread = \\\"yes\\\"\"\n",
+        );
+        let feeds = read_feeds(Cursor::new(urls)).unwrap();
+        assert_eq!(
+            feeds,
+            vec![
+                ClientFeed {
+                    tags: vec!["podcast".to_string(), "tech news".to_string()],
+                    ..ClientFeed::new("https://example.com/feed.xml".to_string())
+                },
+                ClientFeed {
+                    title_override: Some("The \"other\" show".to_string()),
+                    ..ClientFeed::new("https://example.com/other.xml".to_string())
+                },
+                ClientFeed::new("query:Unread:unread = \"yes\"".to_string()),
+            ]
+        );
+    }
+}
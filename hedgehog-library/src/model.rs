@@ -1,13 +1,15 @@
-use crate::{metadata::FeedMetadata, NewFeedMetadata};
+use crate::{metadata::FeedMetadata, EpisodesQuery, NewFeedMetadata};
 use actix::MessageResponse;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use rusqlite::types::{FromSql, ToSql};
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::time::Duration;
 
 macro_rules! entity_id {
     ($name:ident) => {
-        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(transparent)]
         pub struct $name(pub i64);
 
         impl $name {
@@ -35,6 +37,8 @@ macro_rules! entity_id {
 entity_id!(FeedId);
 entity_id!(EpisodeId);
 entity_id!(GroupId);
+entity_id!(ViewId);
+entity_id!(BookmarkId);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FeedError {
@@ -118,6 +122,10 @@ pub trait Identifiable {
 pub struct GroupSummary {
     pub id: GroupId,
     pub name: String,
+    pub parent_id: Option<GroupId>,
+    pub depth: usize,
+    pub collapsed: bool,
+    pub new_count: usize,
 }
 
 impl Identifiable for GroupSummary {
@@ -128,6 +136,137 @@ impl Identifiable for GroupSummary {
     }
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub struct ViewSummary {
+    pub id: ViewId,
+    pub name: String,
+    pub query: EpisodesQuery,
+}
+
+impl Identifiable for ViewSummary {
+    type Id = ViewId;
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrashedFeed {
+    pub id: FeedId,
+    pub title: String,
+    pub deleted_at: DateTime<Utc>,
+}
+
+impl Identifiable for TrashedFeed {
+    type Id = FeedId;
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    pub id: BookmarkId,
+    pub episode_id: EpisodeId,
+    pub episode_title: Option<String>,
+    pub feed_title: Option<String>,
+    pub position: Duration,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Identifiable for Bookmark {
+    type Id = BookmarkId;
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TagTarget {
+    Feed(FeedId),
+    Episode(EpisodeId),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListeningSession {
+    pub episode_id: EpisodeId,
+    pub start_position: Duration,
+    pub end_position: Duration,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub rate: f64,
+}
+
+impl ListeningSession {
+    pub fn new(episode_id: EpisodeId, position: Duration, rate: f64) -> Self {
+        let now = Utc::now();
+        ListeningSession {
+            episode_id,
+            start_position: position,
+            end_position: position,
+            started_at: now,
+            ended_at: now,
+            rate,
+        }
+    }
+
+    pub fn listened(&self) -> Duration {
+        (self.ended_at - self.started_at).to_std().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedListeningStats {
+    pub feed_id: FeedId,
+    pub title: String,
+    pub listened: Duration,
+    pub episodes: usize,
+    pub sessions: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WeekListeningStats {
+    pub week_start: NaiveDate,
+    pub listened: Duration,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, MessageResponse)]
+pub struct ListeningStats {
+    pub feeds: Vec<FeedListeningStats>,
+    pub weeks: Vec<WeekListeningStats>,
+    pub started_episodes: usize,
+    pub finished_episodes: usize,
+}
+
+impl ListeningStats {
+    pub fn total_listened(&self) -> Duration {
+        self.feeds.iter().map(|feed| feed.listened).sum()
+    }
+
+    pub fn completion_rate(&self) -> Option<f64> {
+        if self.started_episodes == 0 {
+            None
+        } else {
+            Some(self.finished_episodes as f64 / self.started_episodes as f64)
+        }
+    }
+
+    pub fn most_played(&self, count: usize) -> Vec<&FeedListeningStats> {
+        let mut feeds: Vec<_> = self.feeds.iter().collect();
+        feeds.sort_by(|a, b| {
+            b.episodes
+                .cmp(&a.episodes)
+                .then_with(|| b.listened.cmp(&a.listened))
+        });
+        feeds.truncate(count);
+        feeds
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct FeedSummary {
     pub id: FeedId,
@@ -176,8 +315,48 @@ impl Identifiable for FeedSummary {
 
 pub struct FeedOMPLEntry {
     pub title: Option<String>,
+    pub title_override: Option<String>,
     pub feed_source: String,
     pub link: Option<String>,
+    pub group_id: Option<GroupId>,
+    pub enabled: bool,
+    pub reversed: bool,
+}
+
+/// A feed along with all of its settings, as it is written to a library backup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedSnapshot {
+    pub id: FeedId,
+    pub source: String,
+    pub title: Option<String>,
+    pub title_override: Option<String>,
+    pub link: Option<String>,
+    pub group_id: Option<GroupId>,
+    pub enabled: bool,
+    pub reversed: bool,
+    pub playback_rate: Option<f64>,
+    pub skip_intro: Duration,
+    pub skip_outro: Duration,
+    pub tags: Vec<String>,
+}
+
+/// An episode along with its state and the metadata needed to recreate it, as it is written to a
+/// library backup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpisodeSnapshot {
+    pub id: EpisodeId,
+    pub guid: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub link: Option<String>,
+    pub duration: Option<Duration>,
+    pub publication_date: Option<DateTime<Utc>>,
+    pub episode_number: Option<i64>,
+    pub season_number: Option<i64>,
+    pub media_url: String,
+    pub status: EpisodeStatus,
+    pub hidden: bool,
+    pub tags: Vec<String>,
 }
 
 pub struct Feed {
@@ -190,6 +369,7 @@ pub struct Feed {
     pub copyright: Option<String>,
     pub source: String,
     pub status: FeedStatus,
+    pub group_id: Option<GroupId>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, cmdparse::Parsable)]
@@ -226,7 +406,16 @@ impl EpisodeStatus {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, cmdparse::Parsable, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpisodeState {
+    pub id: EpisodeId,
+    pub status: EpisodeStatus,
+    pub hidden: bool,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, cmdparse::Parsable, Hash, Serialize, Deserialize,
+)]
 pub enum EpisodeSummaryStatus {
     New,
     #[cmd(rename = "seen")]
@@ -315,6 +504,9 @@ pub struct EpisodePlaybackData {
     pub episode_title: Option<String>,
     pub feed_id: FeedId,
     pub feed_title: Option<String>,
+    pub playback_rate: Option<f64>,
+    pub skip_intro: Duration,
+    pub skip_outro: Duration,
 }
 
 #[derive(Debug, Default, Clone, MessageResponse)]
@@ -328,14 +520,15 @@ pub struct EpisodesListMetadata {
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
-pub enum FeedView<F, G> {
+pub enum FeedView<F, G, V> {
     All,
     New,
+    View(V),
     Feed(F),
     Group(G),
 }
 
-impl<F, G> FeedView<F, G> {
+impl<F, G, V> FeedView<F, G, V> {
     pub fn as_feed(&self) -> Option<&F> {
         match self {
             FeedView::Feed(feed) => Some(feed),
@@ -364,32 +557,49 @@ impl<F, G> FeedView<F, G> {
         }
     }
 
-    pub fn map_feed<R>(self, f: impl FnOnce(F) -> R) -> FeedView<R, G> {
+    pub fn as_view(&self) -> Option<&V> {
+        match self {
+            FeedView::View(view) => Some(view),
+            _ => None,
+        }
+    }
+
+    pub fn as_view_mut(&mut self) -> Option<&mut V> {
+        match self {
+            FeedView::View(view) => Some(view),
+            _ => None,
+        }
+    }
+
+    pub fn map_feed<R>(self, f: impl FnOnce(F) -> R) -> FeedView<R, G, V> {
         match self {
             FeedView::All => FeedView::All,
             FeedView::New => FeedView::New,
+            FeedView::View(view) => FeedView::View(view),
             FeedView::Feed(feed) => FeedView::Feed(f(feed)),
             FeedView::Group(group) => FeedView::Group(group),
         }
     }
 
-    pub fn as_ref(&self) -> FeedView<&F, &G> {
+    pub fn as_ref(&self) -> FeedView<&F, &G, &V> {
         match self {
             FeedView::All => FeedView::All,
             FeedView::New => FeedView::New,
+            FeedView::View(view) => FeedView::View(view),
             FeedView::Feed(feed) => FeedView::Feed(feed),
             FeedView::Group(group) => FeedView::Group(group),
         }
     }
 }
 
-impl<F: Identifiable, G: Identifiable> Identifiable for FeedView<F, G> {
-    type Id = FeedView<F::Id, G::Id>;
+impl<F: Identifiable, G: Identifiable, V: Identifiable> Identifiable for FeedView<F, G, V> {
+    type Id = FeedView<F::Id, G::Id, V::Id>;
 
     fn id(&self) -> Self::Id {
         match self {
             FeedView::All => FeedView::All,
             FeedView::New => FeedView::New,
+            FeedView::View(view) => FeedView::View(view.id()),
             FeedView::Feed(feed) => FeedView::Feed(feed.id()),
             FeedView::Group(group) => FeedView::Group(group.id()),
         }
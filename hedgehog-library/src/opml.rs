@@ -1,8 +1,15 @@
+use crate::feed_url;
+use crate::model::{FeedId, FeedOMPLEntry, GroupId, GroupSummary};
 use crate::NewFeedMetadata;
 use crate::{datasource::DataProvider, QueryError};
 use quick_xml::escape::escape;
 use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::io;
+use std::time::Duration;
+
+/// The namespace of the outline attributes that only Hedgehog understands.
+pub const HEDGEHOG_NAMESPACE: &str = "https://github.com/poletaevvlad/Hedgehog";
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -17,14 +24,35 @@ pub enum Error {
 
     #[error(transparent)]
     Utf8(#[from] std::str::Utf8Error),
+
+    #[error("Networking error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Request failed: {0}")]
+    FailedStatusCode(reqwest::StatusCode),
+}
+
+/// Downloads an OPML file published at the URL.
+pub async fn download_opml(url: &str) -> Result<Vec<u8>, Error> {
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("Hedgehog ", env!("CARGO_PKG_VERSION")))
+        .build()?;
+    let request = client.get(url).timeout(Duration::from_secs(300));
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(Error::FailedStatusCode(response.status()));
+    }
+    Ok(response.bytes().await?.to_vec())
 }
 
 pub fn build_opml<W: io::Write, D: DataProvider>(write: W, data: &mut D) -> Result<(), Error> {
     let mut writer = quick_xml::Writer::new_with_indent(write, b' ', 2);
     writer.write_event(Event::Decl(BytesDecl::new(b"1.0", Some(b"utf-8"), None)))?;
     writer.write_event(Event::Start(
-        BytesStart::borrowed_name(b"opml")
-            .with_attributes([(b"version" as &[u8], b"1.0" as &[u8])]),
+        BytesStart::borrowed_name(b"opml").with_attributes([
+            (b"version" as &[u8], b"1.0" as &[u8]),
+            (b"xmlns:hedgehog", HEDGEHOG_NAMESPACE.as_bytes()),
+        ]),
     ))?;
 
     writer.write_event(Event::Start(BytesStart::borrowed_name(b"head")))?;
@@ -34,26 +62,16 @@ pub fn build_opml<W: io::Write, D: DataProvider>(write: W, data: &mut D) -> Resu
     writer.write_event(Event::End(BytesEnd::borrowed(b"head")))?;
 
     let items = data.get_feed_opml_entries()?;
+    let groups = data.get_group_summaries()?;
     if items.is_empty() {
         writer.write_event(Event::Empty(BytesStart::borrowed_name(b"body")))?;
     } else {
-        writer.write_event(Event::Start(BytesStart::borrowed_name(b"body")))?;
+        let mut feeds: HashMap<Option<GroupId>, Vec<FeedOMPLEntry>> = HashMap::new();
         for item in items {
-            let xml_url = escape(item.feed_source.as_bytes());
-            let title = item.title.as_ref().map(|title| escape(title.as_bytes()));
-            let link = item.link.as_ref().map(|link| escape(link.as_bytes()));
-
-            let mut attrs: Vec<(&[u8], &[u8])> = vec![(b"type", b"rss"), (b"xmlUrl", &xml_url)];
-            if let Some(link) = link.as_ref() {
-                attrs.push((b"htmlUrl", link));
-            }
-            if let Some(title) = title.as_ref() {
-                attrs.push((b"title", title));
-                attrs.push((b"text", title));
-            }
-            let element = BytesStart::borrowed_name(b"outline").with_attributes(attrs);
-            writer.write_event(Event::Empty(element))?;
+            feeds.entry(item.group_id).or_default().push(item);
         }
+        writer.write_event(Event::Start(BytesStart::borrowed_name(b"body")))?;
+        write_outlines(&mut writer, None, &groups, &mut feeds)?;
         writer.write_event(Event::End(BytesEnd::borrowed(b"body")))?;
     }
 
@@ -61,10 +79,71 @@ pub fn build_opml<W: io::Write, D: DataProvider>(write: W, data: &mut D) -> Resu
     Ok(())
 }
 
+fn write_outlines<W: io::Write>(
+    writer: &mut quick_xml::Writer<W>,
+    parent_id: Option<GroupId>,
+    groups: &[GroupSummary],
+    feeds: &mut HashMap<Option<GroupId>, Vec<FeedOMPLEntry>>,
+) -> Result<(), Error> {
+    for item in feeds.remove(&parent_id).unwrap_or_default() {
+        let xml_url = escape(item.feed_source.as_bytes());
+        let title = item.title.as_ref().map(|title| escape(title.as_bytes()));
+        let text = (item.title_override.as_ref().or_else(|| item.title.as_ref()))
+            .map(|text| escape(text.as_bytes()));
+        let link = item.link.as_ref().map(|link| escape(link.as_bytes()));
+
+        let mut attrs: Vec<(&[u8], &[u8])> = vec![(b"type", b"rss"), (b"xmlUrl", &xml_url)];
+        if let Some(link) = link.as_ref() {
+            attrs.push((b"htmlUrl", link));
+        }
+        if let Some(title) = title.as_ref() {
+            attrs.push((b"title", title));
+        }
+        if let Some(text) = text.as_ref() {
+            attrs.push((b"text", text));
+        }
+        if !item.enabled {
+            attrs.push((b"hedgehog:enabled", b"false"));
+        }
+        if item.reversed {
+            attrs.push((b"hedgehog:reversed", b"true"));
+        }
+        let element = BytesStart::borrowed_name(b"outline").with_attributes(attrs);
+        writer.write_event(Event::Empty(element))?;
+    }
+
+    for group in groups.iter().filter(|group| group.parent_id == parent_id) {
+        if !has_feeds(group.id, groups, feeds) {
+            continue;
+        }
+        let name = escape(group.name.as_bytes());
+        let attrs: [(&[u8], &[u8]); 2] = [(b"title", &name), (b"text", &name)];
+        let element = BytesStart::borrowed_name(b"outline").with_attributes(attrs);
+        writer.write_event(Event::Start(element))?;
+        write_outlines(writer, Some(group.id), groups, feeds)?;
+        writer.write_event(Event::End(BytesEnd::borrowed(b"outline")))?;
+    }
+    Ok(())
+}
+
+/// Whether the group or any of its subgroups has feeds. Empty groups are not exported, as an
+/// outline without children is not a group for the programs reading OPML.
+fn has_feeds(
+    group_id: GroupId,
+    groups: &[GroupSummary],
+    feeds: &HashMap<Option<GroupId>, Vec<FeedOMPLEntry>>,
+) -> bool {
+    feeds.contains_key(&Some(group_id))
+        || (groups.iter())
+            .filter(|child| child.parent_id == Some(group_id))
+            .any(|child| has_feeds(child.id, groups, feeds))
+}
+
 pub fn parse_opml<R: io::BufRead>(reader: R) -> Result<OpmlEntries<R>, Error> {
     let mut reader = quick_xml::Reader::from_reader(reader);
     let mut buf = Vec::new();
     let mut depth: u64 = 0;
+    let mut prefix = None;
     loop {
         let event = reader.read_event(&mut buf)?;
 
@@ -79,12 +158,18 @@ pub fn parse_opml<R: io::BufRead>(reader: R) -> Result<OpmlEntries<R>, Error> {
             _ => continue,
         };
 
-        if depth == 0 && bytes_start.name() != b"opml" {
-            return Err(Error::NotOpmlFile);
+        if depth == 0 {
+            if bytes_start.name() != b"opml" {
+                return Err(Error::NotOpmlFile);
+            }
+            prefix = namespace_prefix(&bytes_start)?;
         } else if depth == 1 && bytes_start.name() == b"body" {
             return Ok(OpmlEntries {
                 reader: if is_empty { None } else { Some(reader) },
+                prefix,
                 depth: 0,
+                groups: Vec::new(),
+                queued: VecDeque::new(),
                 buf,
             });
         }
@@ -95,51 +180,297 @@ pub fn parse_opml<R: io::BufRead>(reader: R) -> Result<OpmlEntries<R>, Error> {
     }
 }
 
-pub fn import_opml<R: io::BufRead, D: DataProvider>(reader: R, data: &mut D) -> Result<(), Error> {
-    let entries = parse_opml(reader)?;
-    for entry in entries {
-        data.create_feed_pending(&entry?)?;
+/// Returns the prefix that the `<opml>` element binds to `HEDGEHOG_NAMESPACE`, if any.
+fn namespace_prefix(element: &BytesStart) -> Result<Option<Vec<u8>>, Error> {
+    for attr in element.attributes() {
+        let attr = attr?;
+        if let Some(prefix) = attr.key.strip_prefix(b"xmlns:" as &[u8]) {
+            if &*attr.unescaped_value()? == HEDGEHOG_NAMESPACE.as_bytes() {
+                return Ok(Some(prefix.to_vec()));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// A feed found in the OPML file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportEntry {
+    /// The identifier of the created feed. It is not known for duplicates and for dry runs.
+    pub feed_id: Option<FeedId>,
+    pub source: String,
+    pub title: Option<String>,
+}
+
+/// What importing an OPML file did or, for a dry run, would do.
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportReport {
+    pub added: Vec<ImportEntry>,
+    /// Feeds that are already in the library or appear in the file more than once.
+    pub duplicates: Vec<ImportEntry>,
+    pub invalid: Vec<InvalidOutline>,
+}
+
+pub fn import_opml<R: io::BufRead, D: DataProvider>(
+    reader: R,
+    data: &mut D,
+) -> Result<ImportReport, Error> {
+    import(reader, data, false)
+}
+
+/// Reports what `import_opml` would do without changing the library.
+pub fn check_opml_import<R: io::BufRead, D: DataProvider>(
+    reader: R,
+    data: &mut D,
+) -> Result<ImportReport, Error> {
+    import(reader, data, true)
+}
+
+fn import<R: io::BufRead, D: DataProvider>(
+    reader: R,
+    data: &mut D,
+    dry_run: bool,
+) -> Result<ImportReport, Error> {
+    let mut groups: HashMap<String, GroupId> = (data.get_group_summaries()?.into_iter())
+        .map(|group| (group.name, group.id))
+        .collect();
+    let mut known_feeds: HashSet<String> = (data.get_feed_opml_entries()?.into_iter())
+        .map(|feed| feed_url::comparison_key(&feed.feed_source))
+        .collect();
+    let mut report = ImportReport::default();
+    for entry in parse_opml(reader)? {
+        let feed = match entry? {
+            OpmlEntry::Group(path) => {
+                if !dry_run {
+                    find_or_create_group(data, &mut groups, &path)?;
+                }
+                continue;
+            }
+            OpmlEntry::Invalid(outline) => {
+                report.invalid.push(outline);
+                continue;
+            }
+            OpmlEntry::Feed(feed) => feed,
+        };
+
+        let mut entry = ImportEntry {
+            feed_id: None,
+            source: feed.metadata.source.clone(),
+            title: (feed.title_override.as_ref())
+                .or_else(|| feed.metadata.title.as_ref())
+                .cloned(),
+        };
+        if !known_feeds.insert(feed_url::comparison_key(&entry.source)) {
+            report.duplicates.push(entry);
+            continue;
+        }
+        if !dry_run {
+            match data.create_feed_pending(&feed.metadata)? {
+                Some(feed_id) => {
+                    apply_feed_settings(data, &mut groups, feed_id, feed)?;
+                    entry.feed_id = Some(feed_id);
+                }
+                None => {
+                    report.duplicates.push(entry);
+                    continue;
+                }
+            }
+        }
+        report.added.push(entry);
+    }
+    Ok(report)
+}
+
+fn apply_feed_settings<D: DataProvider>(
+    data: &mut D,
+    groups: &mut HashMap<String, GroupId>,
+    feed_id: FeedId,
+    feed: OpmlFeed,
+) -> Result<(), Error> {
+    let group_id = find_or_create_group(data, groups, &feed.group)?;
+    if group_id.is_some() {
+        data.set_feed_for_group(group_id, feed_id)?;
+    }
+    if feed.title_override.is_some() {
+        data.rename_feed(feed_id, feed.title_override)?;
+    }
+    if !feed.enabled {
+        data.set_feed_enabled(feed_id, false)?;
+    }
+    if feed.reversed {
+        data.reverse_feed_order(feed_id)?;
     }
     Ok(())
 }
 
+/// Returns the group at the end of the path of group names, creating the missing groups along the
+/// way. As group names are unique, an existing group is reused wherever it is in the tree.
+fn find_or_create_group<D: DataProvider>(
+    data: &mut D,
+    groups: &mut HashMap<String, GroupId>,
+    path: &[String],
+) -> Result<Option<GroupId>, Error> {
+    let mut parent_id = None;
+    for name in path {
+        let group_id = match groups.get(name) {
+            Some(group_id) => *group_id,
+            None => match data.create_group(name)? {
+                Some(group_id) => {
+                    if parent_id.is_some() {
+                        data.set_group_parent(group_id, parent_id)?;
+                    }
+                    groups.insert(name.clone(), group_id);
+                    group_id
+                }
+                None => return Ok(parent_id),
+            },
+        };
+        parent_id = Some(group_id);
+    }
+    Ok(parent_id)
+}
+
+/// An outline read from the OPML file.
+#[derive(Debug, PartialEq)]
+pub enum OpmlEntry {
+    /// An outline without a feed URL, represented by the names of the groups from the top level
+    /// down to this one.
+    Group(Vec<String>),
+    Feed(OpmlFeed),
+    /// An outline that looks like a feed but cannot be imported.
+    Invalid(InvalidOutline),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum InvalidReason {
+    #[error("the outline has no feed URL")]
+    MissingUrl,
+
+    #[error("the outline is not an RSS feed")]
+    NotRss,
+
+    #[error("the feed URL is not an HTTP URL")]
+    InvalidUrl,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidOutline {
+    pub title: Option<String>,
+    pub source: Option<String>,
+    pub reason: InvalidReason,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct OpmlFeed {
+    pub metadata: NewFeedMetadata,
+    /// The names of the groups the outline is nested in, from the top level down.
+    pub group: Vec<String>,
+    pub title_override: Option<String>,
+    pub enabled: bool,
+    pub reversed: bool,
+}
+
+impl OpmlFeed {
+    pub fn new(metadata: NewFeedMetadata) -> Self {
+        OpmlFeed {
+            metadata,
+            group: Vec::new(),
+            title_override: None,
+            enabled: true,
+            reversed: false,
+        }
+    }
+}
+
+/// An outline that may be a group. It is reported once its first child outline is read, so that
+/// empty outlines don't create groups.
+struct OpenGroup {
+    depth: u64,
+    name: String,
+    reported: bool,
+}
+
+/// Reads the outlines of the OPML body. Groups are created for the outlines without a feed URL
+/// that have no `type` or the `folder` type and contain other outlines. Hedgehog's attributes
+/// are recognized by the prefix bound to `HEDGEHOG_NAMESPACE` on the `<opml>` element.
 pub struct OpmlEntries<R: io::BufRead> {
     reader: Option<quick_xml::Reader<R>>,
+    prefix: Option<Vec<u8>>,
     depth: u64,
+    groups: Vec<OpenGroup>,
+    queued: VecDeque<OpmlEntry>,
     buf: Vec<u8>,
 }
 
+impl<R: io::BufRead> OpmlEntries<R> {
+    fn group_path(&self) -> Vec<String> {
+        self.groups.iter().map(|group| group.name.clone()).collect()
+    }
+
+    /// Queues the groups enclosing the current outline that haven't been reported yet.
+    fn queue_groups(&mut self) {
+        for index in 0..self.groups.len() {
+            if !self.groups[index].reported {
+                self.groups[index].reported = true;
+                let path = (self.groups[..=index].iter())
+                    .map(|group| group.name.clone())
+                    .collect();
+                self.queued.push_back(OpmlEntry::Group(path));
+            }
+        }
+    }
+}
+
+/// Returns the local name of the attribute if it has the given namespace prefix.
+fn local_name<'a>(prefix: Option<&[u8]>, key: &'a [u8]) -> Option<&'a [u8]> {
+    key.strip_prefix(prefix?)?.strip_prefix(b":" as &[u8])
+}
+
+fn attribute_string(value: &[u8]) -> Result<String, Error> {
+    Ok(std::str::from_utf8(value)?.to_string())
+}
+
 impl<R: io::BufRead> Iterator for OpmlEntries<R> {
-    type Item = Result<NewFeedMetadata, Error>;
+    type Item = Result<OpmlEntry, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(entry) = self.queued.pop_front() {
+            return Some(Ok(entry));
+        }
         loop {
             let event = match self.reader {
                 Some(ref mut reader) => reader.read_event(&mut self.buf),
                 None => return None,
             };
-            let bytes_start = match event {
+            let (bytes_start, is_empty) = match event {
                 Ok(Event::Start(start)) => {
                     self.depth += 1;
-                    start
+                    (start, false)
                 }
                 Ok(Event::End(_)) => {
                     if self.depth == 0 {
                         return None;
                     }
                     self.depth -= 1;
+                    while matches!(self.groups.last(), Some(group) if group.depth > self.depth) {
+                        self.groups.pop();
+                    }
                     continue;
                 }
-                Ok(Event::Empty(start)) => start,
+                Ok(Event::Empty(start)) => (start, true),
                 Ok(_) => continue,
                 Err(err) => return Some(Err(err.into())),
             };
 
             if bytes_start.name() == b"outline" {
                 let mut title = None;
+                let mut text = None;
                 let mut html_feed = None;
                 let mut xml_feed = None;
                 let mut is_rss = false;
+                let mut is_folder = true;
+                let mut enabled = true;
+                let mut reversed = false;
                 for attr in bytes_start.attributes() {
                     let attr = match attr {
                         Ok(attr) => attr,
@@ -149,37 +480,77 @@ impl<R: io::BufRead> Iterator for OpmlEntries<R> {
                         Ok(value) => value,
                         Err(err) => return Some(Err(err.into())),
                     };
-                    match attr.key {
-                        b"type" => is_rss = (&*value) == (b"rss" as &[u8]),
-                        b"xmlUrl" => {
-                            xml_feed = match std::str::from_utf8(&value) {
-                                Ok(xml_feed) => Some(xml_feed.to_string()),
-                                Err(error) => return Some(Err(error.into())),
-                            }
-                        }
-                        b"htmlUrl" => {
-                            html_feed = match std::str::from_utf8(&value) {
-                                Ok(html_feed) => Some(html_feed.to_string()),
-                                Err(error) => return Some(Err(error.into())),
-                            }
+                    let target = match attr.key {
+                        b"type" => {
+                            is_rss = (&*value) == (b"rss" as &[u8]);
+                            is_folder = (&*value) == (b"folder" as &[u8]);
+                            continue;
                         }
-                        b"title" | b"text" => {
-                            title = match std::str::from_utf8(&value) {
-                                Ok(title) => Some(title.to_string()),
-                                Err(error) => return Some(Err(error.into())),
+                        b"xmlUrl" => &mut xml_feed,
+                        b"htmlUrl" => &mut html_feed,
+                        b"title" => &mut title,
+                        b"text" => &mut text,
+                        key => {
+                            match local_name(self.prefix.as_deref(), key) {
+                                Some(b"enabled") => enabled = (&*value) != (b"false" as &[u8]),
+                                Some(b"reversed") => reversed = (&*value) == (b"true" as &[u8]),
+                                _ => {}
                             }
+                            continue;
                         }
-                        _ => {}
+                    };
+                    match attribute_string(&value) {
+                        Ok(value) => *target = Some(value),
+                        Err(error) => return Some(Err(error)),
                     }
                 }
 
-                if !is_rss {
-                    continue;
-                }
-                if let Some(xml_feed) = xml_feed {
-                    return Some(Ok(NewFeedMetadata::new(xml_feed)
+                // The outline is a child of the enclosing groups, so they can be reported now.
+                self.queue_groups();
+
+                let reason = match &xml_feed {
+                    Some(_) if !is_rss => Some(InvalidReason::NotRss),
+                    Some(xml_feed) if !feed_url::is_valid(xml_feed) => {
+                        Some(InvalidReason::InvalidUrl)
+                    }
+                    Some(_) => None,
+                    None if is_rss => Some(InvalidReason::MissingUrl),
+                    None => None,
+                };
+                if let Some(reason) = reason {
+                    self.queued.push_back(OpmlEntry::Invalid(InvalidOutline {
+                        title: text.or(title),
+                        source: xml_feed,
+                        reason,
+                    }));
+                } else if let Some(xml_feed) = xml_feed {
+                    // Hedgehog writes the feed's own title as "title" and the title shown in the
+                    // list as "text", other programs usually write the same value to both.
+                    let (title, title_override) = match (title, text) {
+                        (Some(title), Some(text)) if title != text => (Some(title), Some(text)),
+                        (title, text) => (title.or(text), None),
+                    };
+                    let metadata = NewFeedMetadata::new(xml_feed)
                         .with_title(title)
-                        .with_link(html_feed)));
+                        .with_link(html_feed);
+                    let group = self.group_path();
+                    self.queued.push_back(OpmlEntry::Feed(OpmlFeed {
+                        metadata,
+                        group,
+                        title_override,
+                        enabled,
+                        reversed,
+                    }));
+                } else if let Some(name) = text.or(title).filter(|_| is_folder && !is_empty) {
+                    self.groups.push(OpenGroup {
+                        depth: self.depth,
+                        name,
+                        reported: false,
+                    });
+                }
+
+                if let Some(entry) = self.queued.pop_front() {
+                    return Some(Ok(entry));
                 }
             }
         }
@@ -241,13 +612,32 @@ impl<W: io::Write> io::Write for LineEndingTransformer<W> {
 
 #[cfg(test)]
 mod tests {
-    use super::{build_opml, WindowsLineEndingTransformer};
+    use super::{
+        build_opml, check_opml_import, download_opml, import_opml, ImportEntry, InvalidOutline,
+        InvalidReason, OpmlEntry, OpmlFeed, WindowsLineEndingTransformer,
+    };
     use crate::datasource::{DataProvider, NewFeedMetadata};
     use crate::metadata::FeedMetadata;
     use crate::opml::{parse_opml, LineEndingTransformer};
-    use crate::SqliteDataProvider;
+    use crate::{MemoryDataProvider, SqliteDataProvider};
+    use httpmock::prelude::*;
     use std::io::{Cursor, Write};
 
+    fn feed_entry(metadata: NewFeedMetadata) -> OpmlEntry {
+        OpmlEntry::Feed(OpmlFeed::new(metadata))
+    }
+
+    fn round_trip<D: DataProvider>(mut data_provider: D, opml: &str) -> String {
+        import_opml(Cursor::new(opml), &mut data_provider).unwrap();
+        let mut buffer = Vec::<u8>::new();
+        build_opml(
+            LineEndingTransformer::new(Cursor::new(&mut buffer)),
+            &mut data_provider,
+        )
+        .unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
     #[test]
     fn test_build_opml_empty() {
         let mut data_provider = SqliteDataProvider::connect(":memory:").unwrap();
@@ -323,6 +713,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_build_opml_with_groups() {
+        let mut data_provider = SqliteDataProvider::connect(":memory:").unwrap();
+        let tech_id = data_provider.create_group("Tech").unwrap().unwrap();
+        let rust_id = data_provider.create_group("Rust").unwrap().unwrap();
+        data_provider.create_group("News").unwrap().unwrap();
+        assert!(data_provider.set_group_parent(rust_id, Some(tech_id)).unwrap());
+
+        let groups = [None, Some(tech_id), Some(rust_id)];
+        for (index, group_id) in groups.iter().copied().enumerate() {
+            let feed_id = data_provider
+                .create_feed_pending(&NewFeedMetadata::new(format!(
+                    "https://example.com/source_{}",
+                    index + 1
+                )))
+                .unwrap()
+                .unwrap();
+            data_provider.set_feed_for_group(group_id, feed_id).unwrap();
+        }
+
+        let mut buffer = Vec::<u8>::new();
+        build_opml(
+            LineEndingTransformer::new(Cursor::new(&mut buffer)),
+            &mut data_provider,
+        )
+        .unwrap();
+
+        let xml = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            &xml,
+            include_str!("./test_data/opml/with-groups.opml").trim_end()
+        );
+    }
+
     #[test]
     fn parse_opml_empty() {
         let reader = Cursor::new(include_str!("./test_data/opml/empty.opml"));
@@ -336,27 +760,129 @@ mod tests {
         let mut parser = parse_opml(reader).unwrap();
         assert_eq!(
             parser.next().unwrap().unwrap(),
-            NewFeedMetadata::new("https://example.com/source_not_fetched".to_string())
+            feed_entry(NewFeedMetadata::new(
+                "https://example.com/source_not_fetched".to_string()
+            ))
         );
         assert_eq!(
             parser.next().unwrap().unwrap(),
-            NewFeedMetadata::new("https://example.com/source_2".to_string())
-                .with_title("Feed title".to_string())
-                .with_link("http://example.com/podcast2.html".to_string())
+            feed_entry(
+                NewFeedMetadata::new("https://example.com/source_2".to_string())
+                    .with_title("Feed title".to_string())
+                    .with_link("http://example.com/podcast2.html".to_string())
+            )
         );
         assert_eq!(
             parser.next().unwrap().unwrap(),
-            NewFeedMetadata::new("https://example.com/source_3".to_string())
-                .with_title("\"Second\" <fetched> podcast".to_string())
-                .with_link("http://example.com/podcast3.html".to_string())
+            feed_entry(
+                NewFeedMetadata::new("https://example.com/source_3".to_string())
+                    .with_title("\"Second\" <fetched> podcast".to_string())
+                    .with_link("http://example.com/podcast3.html".to_string())
+            )
         );
     }
 
+    #[test]
+    fn parse_opml_with_groups_and_settings() {
+        let reader = Cursor::new(include_str!("./test_data/opml/round-trip.opml"));
+        let entries: Vec<OpmlEntry> = parse_opml(reader).unwrap().map(Result::unwrap).collect();
+        let group = |path: &[&str]| OpmlEntry::Group(path.iter().map(|s| s.to_string()).collect());
+        assert_eq!(
+            entries,
+            vec![
+                OpmlEntry::Feed(OpmlFeed {
+                    title_override: Some("My podcast".to_string()),
+                    reversed: true,
+                    ..OpmlFeed::new(
+                        NewFeedMetadata::new("https://example.com/source_1".to_string())
+                            .with_title("Feed title".to_string())
+                            .with_link("http://example.com/podcast1.html".to_string())
+                    )
+                }),
+                group(&["Tech"]),
+                OpmlEntry::Feed(OpmlFeed {
+                    group: vec!["Tech".to_string()],
+                    enabled: false,
+                    ..OpmlFeed::new(NewFeedMetadata::new(
+                        "https://example.com/source_2".to_string()
+                    ))
+                }),
+                group(&["Tech", "Rust"]),
+                OpmlEntry::Feed(OpmlFeed {
+                    group: vec!["Tech".to_string(), "Rust".to_string()],
+                    ..OpmlFeed::new(
+                        NewFeedMetadata::new("https://example.com/source_3".to_string())
+                            .with_title("Rust & friends".to_string())
+                    )
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_opml_with_folders() {
+        let reader = Cursor::new(include_str!("./test_data/opml/with-folders.opml"));
+        let entries: Vec<OpmlEntry> = parse_opml(reader).unwrap().map(Result::unwrap).collect();
+        assert_eq!(
+            entries,
+            vec![
+                OpmlEntry::Group(vec!["Folder".to_string()]),
+                OpmlEntry::Feed(OpmlFeed {
+                    group: vec!["Folder".to_string()],
+                    reversed: true,
+                    ..OpmlFeed::new(NewFeedMetadata::new(
+                        "https://example.com/source_1".to_string()
+                    ))
+                }),
+                feed_entry(NewFeedMetadata::new(
+                    "https://example.com/source_2".to_string()
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trips_groups_and_settings() {
+        let opml = include_str!("./test_data/opml/round-trip.opml");
+        let sqlite = SqliteDataProvider::connect(":memory:").unwrap();
+        assert_eq!(round_trip(sqlite, opml), opml.trim_end());
+        assert_eq!(round_trip(MemoryDataProvider::new(), opml), opml.trim_end());
+
+        let opml = include_str!("./test_data/opml/with-groups.opml");
+        let sqlite = SqliteDataProvider::connect(":memory:").unwrap();
+        assert_eq!(round_trip(sqlite, opml), opml.trim_end());
+    }
+
+    #[test]
+    fn import_reuses_existing_groups() {
+        let mut data_provider = SqliteDataProvider::connect(":memory:").unwrap();
+        let rust_id = data_provider.create_group("Rust").unwrap().unwrap();
+        let reader = Cursor::new(include_str!("./test_data/opml/with-groups.opml"));
+        import_opml(reader, &mut data_provider).unwrap();
+
+        let groups = data_provider.get_group_summaries().unwrap();
+        let groups: Vec<_> = (groups.iter())
+            .map(|group| (group.name.as_str(), group.depth))
+            .collect();
+        assert_eq!(groups, vec![("Rust", 0), ("Tech", 0)]);
+        let feeds = data_provider.get_feed_opml_entries().unwrap();
+        let rust_feeds: Vec<_> = (feeds.iter())
+            .filter(|feed| feed.group_id == Some(rust_id))
+            .map(|feed| feed.feed_source.as_str())
+            .collect();
+        assert_eq!(rust_feeds, vec!["https://example.com/source_3"]);
+    }
+
     #[test]
     fn parse_ompl_with_invalid_nodes() {
         let reader = Cursor::new(include_str!("./test_data/opml/with-invalid-nodes.opml"));
         let parser = parse_opml(reader).unwrap();
-        let resource: Vec<String> = parser.map(|entry| entry.unwrap().source).collect();
+        let resource: Vec<String> = (parser.map(Result::unwrap))
+            .filter_map(|entry| match entry {
+                OpmlEntry::Feed(feed) => Some(feed.metadata.source),
+                _ => None,
+            })
+            .collect();
         assert_eq!(
             resource,
             vec![
@@ -367,6 +893,86 @@ mod tests {
         );
     }
 
+    #[test]
+    fn reports_import_conflicts() {
+        let mut data_provider = SqliteDataProvider::connect(":memory:").unwrap();
+        let existing_id = data_provider
+            .create_feed_pending(&NewFeedMetadata::new(
+                "http://example.com/source_2/".to_string(),
+            ))
+            .unwrap()
+            .unwrap();
+        let opml = include_str!("./test_data/opml/with-invalid-nodes.opml");
+        let entry = |source: &str, title: Option<&str>| ImportEntry {
+            feed_id: None,
+            source: source.to_string(),
+            title: title.map(str::to_string),
+        };
+
+        let report = check_opml_import(Cursor::new(opml), &mut data_provider).unwrap();
+        assert_eq!(
+            report.added,
+            vec![
+                entry("https://example.com/source_1", None),
+                entry("https://example.com/source_3", Some("Feed title")),
+            ]
+        );
+        assert_eq!(
+            report.duplicates,
+            vec![entry("https://example.com/source_2", None)]
+        );
+        assert_eq!(
+            report.invalid,
+            vec![
+                InvalidOutline {
+                    title: None,
+                    source: Some("https://example.com/source_4".to_string()),
+                    reason: InvalidReason::NotRss,
+                },
+                InvalidOutline {
+                    title: None,
+                    source: None,
+                    reason: InvalidReason::MissingUrl,
+                },
+            ]
+        );
+        assert_eq!(data_provider.get_feed_opml_entries().unwrap().len(), 1);
+
+        let imported = import_opml(Cursor::new(opml), &mut data_provider).unwrap();
+        assert_eq!(imported.duplicates, report.duplicates);
+        assert_eq!(imported.invalid, report.invalid);
+        let feed_ids: Vec<_> = imported.added.iter().map(|entry| entry.feed_id).collect();
+        assert_eq!(feed_ids.len(), 2);
+        assert!(feed_ids
+            .iter()
+            .all(|feed_id| feed_id.is_some() && *feed_id != Some(existing_id)));
+        assert_eq!(data_provider.get_feed_opml_entries().unwrap().len(), 3);
+    }
+
+    #[actix::test]
+    async fn downloads_opml() {
+        let mock_server = MockServer::start();
+        let mock = mock_server.mock(|when, then| {
+            when.method(GET).path("/subscriptions.opml");
+            then.status(200)
+                .header("content-type", "text/x-opml")
+                .body(include_str!("./test_data/opml/with-feeds.opml"));
+        });
+        let missing = mock_server.mock(|when, then| {
+            when.method(GET).path("/missing.opml");
+            then.status(404);
+        });
+
+        let opml = download_opml(&mock_server.url("/subscriptions.opml"))
+            .await
+            .unwrap();
+        assert_eq!(opml, include_bytes!("./test_data/opml/with-feeds.opml").to_vec());
+        let result = download_opml(&mock_server.url("/missing.opml")).await;
+        assert!(matches!(result, Err(super::Error::FailedStatusCode(_))));
+        mock.assert();
+        missing.assert();
+    }
+
     #[test]
     fn windows_transform_line_endings() {
         let mut buffer = Vec::<u8>::new();
@@ -0,0 +1,66 @@
+use crate::datasource::{DataProvider, DbResult, QueryError};
+use crate::sqlite::{ConnectionError, SqliteDataProvider};
+use actix::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Serves read-only queries through a separate connection to the database, so that slow queries
+/// don't block updates and other requests handled by the library.
+pub struct DatabaseReader {
+    /// `None` if the reader was restarted and its connection could not be opened again.
+    data_provider: Option<SqliteDataProvider>,
+}
+
+impl DatabaseReader {
+    /// Starts a pool of readers, each of which runs in its own thread and uses its own connection
+    /// to the database at `path`. All connections are opened before the readers are started, so
+    /// that an error is returned if any of them cannot be established.
+    pub fn start_pool<P: AsRef<Path>>(
+        path: P,
+        threads: usize,
+    ) -> Result<Addr<DatabaseReader>, ConnectionError> {
+        let path: PathBuf = path.as_ref().to_owned();
+        let connections = (0..threads)
+            .map(|_| SqliteDataProvider::connect_read_only(&path))
+            .collect::<Result<Vec<_>, _>>()?;
+        let connections = Arc::new(Mutex::new(connections));
+        Ok(SyncArbiter::start(threads, move || {
+            let connection = (connections.lock().ok()).and_then(|mut pool| pool.pop());
+            DatabaseReader {
+                data_provider: connection
+                    .or_else(|| SqliteDataProvider::connect_read_only(&path).ok()),
+            }
+        }))
+    }
+}
+
+impl Actor for DatabaseReader {
+    type Context = SyncContext<Self>;
+}
+
+type ReadQuery<R> = Box<dyn FnOnce(&mut dyn DataProvider) -> DbResult<R> + Send>;
+
+pub(crate) struct ReadRequest<R>(ReadQuery<R>);
+
+impl<R> ReadRequest<R> {
+    pub(crate) fn new(
+        query: impl FnOnce(&mut dyn DataProvider) -> DbResult<R> + Send + 'static,
+    ) -> Self {
+        ReadRequest(Box::new(query))
+    }
+}
+
+impl<R: Send + 'static> Message for ReadRequest<R> {
+    type Result = DbResult<R>;
+}
+
+impl<R: Send + 'static> Handler<ReadRequest<R>> for DatabaseReader {
+    type Result = MessageResult<ReadRequest<R>>;
+
+    fn handle(&mut self, msg: ReadRequest<R>, _ctx: &mut Self::Context) -> Self::Result {
+        MessageResult(match self.data_provider {
+            Some(ref mut data_provider) => (msg.0)(data_provider),
+            None => Err(QueryError::ReaderUnavailable(MailboxError::Closed)),
+        })
+    }
+}
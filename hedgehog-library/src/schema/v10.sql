@@ -0,0 +1 @@
+ALTER TABLE groups ADD COLUMN collapsed INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE feeds ADD COLUMN source_key TEXT;
+CREATE INDEX feeds_source_key ON feeds(source_key);
@@ -0,0 +1,77 @@
+-- Counts the changes made to the database, except for the playback position updates, so that
+-- other instances can tell whether they need to reload their data.
+CREATE TABLE revision (
+    "value" INTEGER NOT NULL
+);
+INSERT INTO revision ("value") VALUES (0);
+
+CREATE TRIGGER episodes_insert_revision AFTER INSERT ON episodes
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER episodes_delete_revision AFTER DELETE ON episodes
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER episodes_update_revision AFTER UPDATE ON episodes
+WHEN NEW.status IS NOT OLD.status OR NEW.position IS OLD.position
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+
+CREATE TRIGGER feeds_insert_revision AFTER INSERT ON feeds
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER feeds_update_revision AFTER UPDATE ON feeds
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER feeds_delete_revision AFTER DELETE ON feeds
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+
+CREATE TRIGGER groups_insert_revision AFTER INSERT ON groups
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER groups_update_revision AFTER UPDATE ON groups
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER groups_delete_revision AFTER DELETE ON groups
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+
+CREATE TRIGGER views_insert_revision AFTER INSERT ON views
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER views_update_revision AFTER UPDATE ON views
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER views_delete_revision AFTER DELETE ON views
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+
+CREATE TRIGGER queue_insert_revision AFTER INSERT ON queue
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER queue_update_revision AFTER UPDATE ON queue
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER queue_delete_revision AFTER DELETE ON queue
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+
+CREATE TRIGGER bookmarks_insert_revision AFTER INSERT ON bookmarks
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER bookmarks_update_revision AFTER UPDATE ON bookmarks
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER bookmarks_delete_revision AFTER DELETE ON bookmarks
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+
+CREATE TRIGGER listening_sessions_insert_revision AFTER INSERT ON listening_sessions
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER listening_sessions_update_revision AFTER UPDATE ON listening_sessions
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER listening_sessions_delete_revision AFTER DELETE ON listening_sessions
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+
+CREATE TRIGGER tags_insert_revision AFTER INSERT ON tags
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER tags_update_revision AFTER UPDATE ON tags
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER tags_delete_revision AFTER DELETE ON tags
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+
+CREATE TRIGGER feed_tags_insert_revision AFTER INSERT ON feed_tags
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER feed_tags_update_revision AFTER UPDATE ON feed_tags
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER feed_tags_delete_revision AFTER DELETE ON feed_tags
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+
+CREATE TRIGGER episode_tags_insert_revision AFTER INSERT ON episode_tags
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER episode_tags_update_revision AFTER UPDATE ON episode_tags
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
+CREATE TRIGGER episode_tags_delete_revision AFTER DELETE ON episode_tags
+BEGIN UPDATE revision SET "value" = "value" + 1; END;
@@ -0,0 +1,5 @@
+CREATE TABLE views (
+    "id" INTEGER NOT NULL PRIMARY KEY,
+    "name" TEXT NOT NULL UNIQUE,
+    "query" TEXT NOT NULL
+);
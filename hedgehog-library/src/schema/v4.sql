@@ -0,0 +1,4 @@
+CREATE TABLE queue (
+    "episode_id" INTEGER NOT NULL PRIMARY KEY REFERENCES episodes("id") ON DELETE CASCADE,
+    "ordering" INTEGER NOT NULL
+);
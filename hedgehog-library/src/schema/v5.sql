@@ -0,0 +1,7 @@
+CREATE TABLE bookmarks (
+    "id" INTEGER NOT NULL PRIMARY KEY,
+    "episode_id" INTEGER NOT NULL REFERENCES episodes("id") ON DELETE CASCADE,
+    "position" INTEGER NOT NULL,
+    "note" TEXT,
+    "created_at" TEXT NOT NULL
+);
@@ -0,0 +1,11 @@
+CREATE TABLE listening_sessions (
+    "id" INTEGER NOT NULL PRIMARY KEY,
+    "episode_id" INTEGER NOT NULL REFERENCES episodes("id") ON DELETE CASCADE,
+    "start_position" INTEGER NOT NULL,
+    "end_position" INTEGER NOT NULL,
+    "started_at" TEXT NOT NULL,
+    "ended_at" TEXT NOT NULL,
+    "rate" REAL NOT NULL DEFAULT 1.0
+);
+
+CREATE UNIQUE INDEX listening_sessions_episode_id_started_at_index ON listening_sessions ("episode_id", "started_at");
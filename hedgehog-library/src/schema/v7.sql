@@ -0,0 +1,3 @@
+ALTER TABLE feeds ADD COLUMN playback_rate REAL;
+ALTER TABLE feeds ADD COLUMN skip_intro INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE feeds ADD COLUMN skip_outro INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,16 @@
+CREATE TABLE tags (
+    "id" INTEGER NOT NULL PRIMARY KEY,
+    "name" TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE feed_tags (
+    "feed_id" INTEGER NOT NULL REFERENCES feeds("id") ON DELETE CASCADE,
+    "tag_id" INTEGER NOT NULL REFERENCES tags("id") ON DELETE CASCADE,
+    PRIMARY KEY ("feed_id", "tag_id")
+);
+
+CREATE TABLE episode_tags (
+    "episode_id" INTEGER NOT NULL REFERENCES episodes("id") ON DELETE CASCADE,
+    "tag_id" INTEGER NOT NULL REFERENCES tags("id") ON DELETE CASCADE,
+    PRIMARY KEY ("episode_id", "tag_id")
+);
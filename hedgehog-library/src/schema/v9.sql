@@ -0,0 +1,2 @@
+ALTER TABLE groups ADD COLUMN parent_id INTEGER REFERENCES groups("id") ON DELETE SET NULL;
+CREATE INDEX groups_parent_id_index ON groups ("parent_id");
@@ -1,19 +1,25 @@
 use crate::actor::UpdateQuery;
+use crate::backup::restores_status;
 use crate::datasource::{
     DataProvider, DbResult, EpisodeWriter, EpisodesQuery, NewFeedMetadata, QueryError,
 };
+use crate::feed_url;
 use crate::metadata::{EpisodeMetadata, FeedMetadata};
 use crate::model::{
-    Episode, EpisodeId, EpisodePlaybackData, EpisodeStatus, EpisodeSummary, EpisodeSummaryStatus,
-    EpisodesListMetadata, Feed, FeedId, FeedOMPLEntry, FeedStatus, FeedSummary, GroupId,
-    GroupSummary,
+    Bookmark, BookmarkId, Episode, EpisodeId, EpisodePlaybackData, EpisodeSnapshot, EpisodeState,
+    EpisodeStatus, EpisodeSummary, EpisodeSummaryStatus, EpisodesListMetadata, Feed, FeedId,
+    FeedListeningStats, FeedOMPLEntry, FeedSnapshot, FeedStatus, FeedSummary, GroupId,
+    GroupSummary, ListeningSession, ListeningStats, TagTarget, TrashedFeed, ViewId, ViewSummary,
+    WeekListeningStats,
 };
-use rusqlite::{named_params, Connection};
+use chrono::{DateTime, Utc};
+use rusqlite::backup::Backup;
+use rusqlite::{named_params, Connection, DatabaseName, ErrorCode, OpenFlags};
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
 use std::ops::Range;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use thiserror::Error;
 
@@ -25,20 +31,48 @@ pub enum ConnectionError {
     #[error("Database was updated in a newer version of hedgehog (db version: {version}, current: {current})")]
     VersionUnknown { version: u32, current: u32 },
 
+    #[error("Database backup cannot be written")]
+    BackupFailed(#[from] std::io::Error),
+
+    #[error("The file is not a backup of the episodes database")]
+    NotABackup,
+
     #[error(transparent)]
     Other(#[from] Box<dyn std::error::Error>),
 }
 
+/// The number of the most recent database backups that are kept.
+const BACKUPS_KEPT: usize = 5;
+
+/// Walks the groups tree starting from the top-level groups. `path` sorts groups so that every
+/// group is followed by its subgroups, with siblings ordered by their `ordering`.
+const GROUP_TREE_CTE: &str = "WITH RECURSIVE group_tree(id, depth, path) AS (
+        SELECT id, 0, printf('%08d', ordering) FROM groups WHERE parent_id IS NULL
+        UNION ALL
+        SELECT groups.id, group_tree.depth + 1, group_tree.path || printf('/%08d', ordering)
+        FROM groups JOIN group_tree ON groups.parent_id = group_tree.id
+    )";
+
+/// Selects the id of the `:group_id` group along with the ids of all its subgroups.
+const GROUP_DESCENDANTS_SQL: &str = "WITH RECURSIVE descendants(id) AS (
+        SELECT :group_id
+        UNION ALL
+        SELECT groups.id FROM groups JOIN descendants ON groups.parent_id = descendants.id
+    )
+    SELECT id FROM descendants";
+
 #[derive(Debug)]
 pub struct SqliteDataProvider {
     connection: Connection,
+    data_version: u32,
+    revision: i64,
 }
 
 impl SqliteDataProvider {
-    const CURRENT_VERSION: u32 = 2;
+    const CURRENT_VERSION: u32 = 13;
 
     pub fn connect<P: AsRef<Path>>(path: P) -> Result<Self, ConnectionError> {
-        let connection = Connection::open(path)?;
+        let mut connection = Connection::open(&path)?;
         let version = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
         if version > Self::CURRENT_VERSION {
             return Err(ConnectionError::VersionUnknown {
@@ -48,15 +82,199 @@ impl SqliteDataProvider {
         }
 
         connection.execute("PRAGMA foreign_keys = ON", named_params! {})?;
+        connection.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+        connection.busy_timeout(Duration::from_secs(5))?;
+        if version > 0 && version < Self::CURRENT_VERSION {
+            Self::write_backup(&connection, path.as_ref())?;
+            Self::rotate_backups(path.as_ref(), None)?;
+        }
+
+        let transaction = connection.transaction()?;
         if version < 1 {
-            connection.execute_batch(include_str!("schema/init.sql"))?;
+            transaction.execute_batch(include_str!("schema/init.sql"))?;
         }
         if version < 2 {
-            connection.execute_batch(include_str!("schema/v2.sql"))?;
+            transaction.execute_batch(include_str!("schema/v2.sql"))?;
+        }
+        if version < 3 {
+            transaction.execute_batch(include_str!("schema/v3.sql"))?;
+        }
+        if version < 4 {
+            transaction.execute_batch(include_str!("schema/v4.sql"))?;
+        }
+        if version < 5 {
+            transaction.execute_batch(include_str!("schema/v5.sql"))?;
+        }
+        if version < 6 {
+            transaction.execute_batch(include_str!("schema/v6.sql"))?;
+        }
+        if version < 7 {
+            transaction.execute_batch(include_str!("schema/v7.sql"))?;
+        }
+        if version < 8 {
+            transaction.execute_batch(include_str!("schema/v8.sql"))?;
+        }
+        if version < 9 {
+            transaction.execute_batch(include_str!("schema/v9.sql"))?;
+        }
+        if version < 10 {
+            transaction.execute_batch(include_str!("schema/v10.sql"))?;
+        }
+        if version < 11 {
+            transaction.execute_batch(include_str!("schema/v11.sql"))?;
+        }
+        if version < 12 {
+            transaction.execute_batch(include_str!("schema/v12.sql"))?;
+            Self::fill_source_keys(&transaction)?;
+        }
+        if version < 13 {
+            transaction.execute_batch(include_str!("schema/v13.sql"))?;
         }
 
-        connection.pragma_update(None, "user_version", Self::CURRENT_VERSION)?;
-        Ok(SqliteDataProvider { connection })
+        if version < Self::CURRENT_VERSION {
+            transaction.pragma_update(None, "user_version", Self::CURRENT_VERSION)?;
+        }
+        transaction.commit()?;
+
+        let data_version = connection.pragma_query_value(None, "data_version", |row| row.get(0))?;
+        let revision = Self::query_revision(&connection)?;
+        Ok(SqliteDataProvider {
+            connection,
+            data_version,
+            revision,
+        })
+    }
+
+    fn query_revision(connection: &Connection) -> rusqlite::Result<i64> {
+        connection.query_row("SELECT value FROM revision", [], |row| row.get(0))
+    }
+
+    /// Opens a read-only connection to the database that has already been initialized by
+    /// `connect`.
+    pub fn connect_read_only<P: AsRef<Path>>(path: P) -> Result<Self, ConnectionError> {
+        let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+        let connection = Connection::open_with_flags(path, flags)?;
+        let version = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version != Self::CURRENT_VERSION {
+            return Err(ConnectionError::VersionUnknown {
+                version,
+                current: Self::CURRENT_VERSION,
+            });
+        }
+        connection.busy_timeout(Duration::from_secs(5))?;
+
+        let data_version = connection.pragma_query_value(None, "data_version", |row| row.get(0))?;
+        let revision = Self::query_revision(&connection)?;
+        Ok(SqliteDataProvider {
+            connection,
+            data_version,
+            revision,
+        })
+    }
+
+    /// Replaces the contents of the database at `path` with the backup. The current contents are
+    /// backed up first. A damaged database is the most likely reason to restore it, so the
+    /// backup is restored even if the current contents cannot be backed up, in which case the
+    /// error is returned.
+    pub fn restore_backup<P: AsRef<Path>, B: AsRef<Path>>(
+        path: P,
+        backup_path: B,
+    ) -> Result<Option<ConnectionError>, ConnectionError> {
+        let backup_path = backup_path.as_ref();
+        let backup = Connection::open_with_flags(backup_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let version: u32 = backup.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version == 0 {
+            return Err(ConnectionError::NotABackup);
+        }
+        if version > Self::CURRENT_VERSION {
+            return Err(ConnectionError::VersionUnknown {
+                version,
+                current: Self::CURRENT_VERSION,
+            });
+        }
+
+        let path = path.as_ref();
+        let mut connection = Connection::open(path)?;
+        let backup_error = Self::write_backup(&connection, path).err();
+        let restored = Backup::new(&backup, &mut connection)
+            .and_then(|restore| restore.run_to_completion(100, Duration::ZERO, None));
+        match restored {
+            Ok(()) => {}
+            Err(rusqlite::Error::SqliteFailure(error, _))
+                if error.code == ErrorCode::NotADatabase =>
+            {
+                drop(connection);
+                Self::move_damaged(path)?;
+                backup.backup(DatabaseName::Main, path, None)?;
+            }
+            Err(error) => return Err(error.into()),
+        }
+        Self::rotate_backups(path, Some(backup_path))?;
+        Ok(backup_error)
+    }
+
+    /// Moves the file that SQLite cannot read as a database to the backups directory, together
+    /// with its write-ahead log so that it is not applied to the restored database.
+    fn move_damaged(path: &Path) -> Result<(), ConnectionError> {
+        let (directory, prefix) = Self::backups_location(path);
+        std::fs::create_dir_all(&directory)?;
+        let timestamp = Utc::now().format("%Y%m%d-%H%M%S%.3f");
+        let damaged_path = directory.join(format!("{}{}.damaged", prefix, timestamp));
+        std::fs::rename(path, &damaged_path)?;
+        for suffix in ["-wal", "-shm"] {
+            let mut journal_path = path.as_os_str().to_owned();
+            journal_path.push(suffix);
+            let mut damaged_journal_path = damaged_path.as_os_str().to_owned();
+            damaged_journal_path.push(suffix);
+            match std::fs::rename(&journal_path, &damaged_journal_path) {
+                Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(error.into())
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the directory in which the backups of the database are stored and the prefix of
+    /// their file names.
+    fn backups_location(path: &Path) -> (PathBuf, String) {
+        let directory = path.parent().unwrap_or_else(|| Path::new(".")).join("backups");
+        let name = (path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        (directory, format!("{}-", name))
+    }
+
+    fn write_backup(connection: &Connection, path: &Path) -> Result<(), ConnectionError> {
+        let (directory, prefix) = Self::backups_location(path);
+        std::fs::create_dir_all(&directory)?;
+        let timestamp = Utc::now().format("%Y%m%d-%H%M%S%.3f");
+        let backup_path = directory.join(format!("{}{}.bak", prefix, timestamp));
+        connection.backup(DatabaseName::Main, &backup_path, None)?;
+        Ok(())
+    }
+
+    /// Removes the oldest backups except for the `kept` one, which is the backup being restored.
+    fn rotate_backups(path: &Path, kept: Option<&Path>) -> Result<(), ConnectionError> {
+        let (directory, prefix) = Self::backups_location(path);
+        let kept = kept.and_then(|kept| kept.canonicalize().ok());
+        let mut backups = Vec::new();
+        for entry in std::fs::read_dir(&directory)? {
+            let entry = entry?;
+            let is_backup = (entry.file_name().to_str())
+                .map_or(false, |name| name.starts_with(&prefix) && name.ends_with(".bak"));
+            let is_kept = kept.is_some() && entry.path().canonicalize().ok() == kept;
+            if is_backup && !is_kept {
+                backups.push(entry.path());
+            }
+        }
+        backups.sort();
+        let excess = backups.len().saturating_sub(BACKUPS_KEPT);
+        for backup in &backups[..excess] {
+            std::fs::remove_file(backup)?;
+        }
+        Ok(())
     }
 
     fn fix_group_oredering(&mut self) -> DbResult<()> {
@@ -68,13 +286,92 @@ impl SqliteDataProvider {
         statement.execute([])?;
         Ok(())
     }
+
+    /// Renumbers the queue so that orderings are consecutive. The new orderings are computed
+    /// before any row is updated, as a correlated subquery would see the rows that were already
+    /// renumbered.
+    fn fix_queue_ordering(&mut self) -> DbResult<()> {
+        let transaction = self.connection.transaction()?;
+        {
+            let mut select =
+                transaction.prepare("SELECT episode_id FROM queue ORDER BY ordering, episode_id")?;
+            let episode_ids: Vec<EpisodeId> =
+                collect_results(select.query_map([], |row| row.get(0))?)?;
+            let mut update = transaction
+                .prepare("UPDATE queue SET ordering = :ordering WHERE episode_id = :episode_id")?;
+            for (index, episode_id) in episode_ids.into_iter().enumerate() {
+                update.execute(named_params! {":ordering": index + 1, ":episode_id": episode_id})?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Computes `source_key`, the comparison key of the feed's source, for the feeds added before
+    /// the column existed.
+    fn fill_source_keys(connection: &Connection) -> Result<(), rusqlite::Error> {
+        let mut select = connection.prepare("SELECT id, source FROM feeds")?;
+        let feeds: Vec<(FeedId, String)> =
+            collect_results(select.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?)?;
+        let mut update =
+            connection.prepare("UPDATE feeds SET source_key = :source_key WHERE id = :id")?;
+        for (feed_id, source) in feeds {
+            let source_key = feed_url::comparison_key(&source);
+            update.execute(named_params! {":source_key": source_key, ":id": feed_id})?;
+        }
+        Ok(())
+    }
+
+    /// Finds a feed other than `except_id` whose source is equivalent to the given one. Feeds in
+    /// the trash are only returned if there is no matching feed in the library.
+    fn find_feed_by_source(
+        &mut self,
+        source: &str,
+        except_id: Option<FeedId>,
+    ) -> DbResult<Option<(FeedId, bool)>> {
+        let mut statement = self.connection.prepare(
+            "SELECT id, deleted_at IS NOT NULL FROM feeds
+            WHERE source_key = :source_key AND (:except_id IS NULL OR id != :except_id)
+            ORDER BY deleted_at IS NOT NULL, id
+            LIMIT 1",
+        )?;
+        let result = statement.query_row(
+            named_params! {
+                ":source_key": feed_url::comparison_key(source),
+                ":except_id": except_id,
+            },
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        );
+        match result {
+            Ok(feed) => Ok(Some(feed)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
 }
 
 impl DataProvider for SqliteDataProvider {
+    fn check_external_changes(&mut self) -> DbResult<bool> {
+        let data_version = self
+            .connection
+            .pragma_query_value(None, "data_version", |row| row.get(0))?;
+        if data_version == self.data_version {
+            return Ok(false);
+        }
+        self.data_version = data_version;
+
+        // The revision is not incremented by the playback position updates, which are written
+        // every second while another instance is playing and don't affect any lists
+        let revision = Self::query_revision(&self.connection)?;
+        let changed = revision != self.revision;
+        self.revision = revision;
+        Ok(changed)
+    }
+
     fn get_feed(&mut self, id: FeedId) -> DbResult<Option<crate::model::Feed>> {
         let mut statement = self.connection.prepare(
             "SELECT id, COALESCE(title_override, title), title_override IS NOT NULL, description, 
-                    link, author, copyright, source, status, error_code 
+                    link, author, copyright, source, status, error_code, group_id
             FROM feeds
             WHERE id = ?1
         ",
@@ -90,6 +387,7 @@ impl DataProvider for SqliteDataProvider {
                 copyright: row.get(6)?,
                 source: row.get(7)?,
                 status: FeedStatus::from_db(row.get(8)?, row.get(9)?),
+                group_id: row.get(10)?,
             })
         });
         match result {
@@ -100,16 +398,20 @@ impl DataProvider for SqliteDataProvider {
     }
 
     fn get_feed_summaries(&mut self) -> DbResult<Vec<FeedSummary>> {
-        let mut select = self.connection.prepare(
-            "SELECT feeds.id, COALESCE(feeds.title_override, feeds.title, feeds.source), 
+        let sql = format!(
+            "{}
+            SELECT feeds.id, COALESCE(feeds.title_override, feeds.title, feeds.source), 
                     feeds.title IS NOT NULL, feeds.status, feeds.error_code, COUNT(episodes.id),
                     feeds.group_id
             FROM feeds 
             LEFT JOIN episodes ON feeds.id = episodes.feed_id AND episodes.status = 0
-            LEFT JOIN groups ON feeds.group_id = groups.id
+            LEFT JOIN group_tree ON feeds.group_id = group_tree.id
+            WHERE feeds.deleted_at IS NULL
             GROUP BY feeds.id
-            ORDER BY groups.ordering, COALESCE(feeds.title_override, feeds.title), feeds.source",
-        )?;
+            ORDER BY group_tree.path, COALESCE(feeds.title_override, feeds.title), feeds.source",
+            GROUP_TREE_CTE
+        );
+        let mut select = self.connection.prepare(&sql)?;
         let rows = select.query_map([], |row| {
             Ok(FeedSummary {
                 id: row.get(0)?,
@@ -124,14 +426,104 @@ impl DataProvider for SqliteDataProvider {
     }
 
     fn get_feed_opml_entries(&mut self) -> DbResult<Vec<crate::model::FeedOMPLEntry>> {
-        let mut select = self
-            .connection
-            .prepare("SELECT title, source, link FROM feeds")?;
+        let mut select = self.connection.prepare(
+            "SELECT title, title_override, source, link, group_id, enabled, reversed
+            FROM feeds WHERE deleted_at IS NULL",
+        )?;
         let rows = select.query_map([], |row| {
             Ok(FeedOMPLEntry {
                 title: row.get(0)?,
-                feed_source: row.get(1)?,
-                link: row.get(2)?,
+                title_override: row.get(1)?,
+                feed_source: row.get(2)?,
+                link: row.get(3)?,
+                group_id: row.get(4)?,
+                enabled: row.get(5)?,
+                reversed: row.get(6)?,
+            })
+        })?;
+        Ok(collect_results(rows)?)
+    }
+
+    fn get_feed_snapshots(&mut self) -> DbResult<Vec<FeedSnapshot>> {
+        let mut tags: HashMap<FeedId, Vec<String>> = HashMap::new();
+        let mut statement = self.connection.prepare(
+            "SELECT feed_tags.feed_id, tags.name FROM feed_tags
+            JOIN tags ON tags.id = feed_tags.tag_id
+            ORDER BY tags.name",
+        )?;
+        let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        for row in rows {
+            let (feed_id, name) = row?;
+            tags.entry(feed_id).or_default().push(name);
+        }
+
+        let mut statement = self.connection.prepare(
+            "SELECT id, source, title, title_override, link, group_id, enabled, reversed,
+                    playback_rate, skip_intro, skip_outro
+            FROM feeds
+            WHERE deleted_at IS NULL
+            ORDER BY id",
+        )?;
+        let rows = statement.query_map([], |row| {
+            let id = row.get(0)?;
+            Ok(FeedSnapshot {
+                id,
+                source: row.get(1)?,
+                title: row.get(2)?,
+                title_override: row.get(3)?,
+                link: row.get(4)?,
+                group_id: row.get(5)?,
+                enabled: row.get(6)?,
+                reversed: row.get(7)?,
+                playback_rate: row.get(8)?,
+                skip_intro: Duration::from_nanos(row.get(9)?),
+                skip_outro: Duration::from_nanos(row.get(10)?),
+                tags: tags.remove(&id).unwrap_or_default(),
+            })
+        })?;
+        Ok(collect_results(rows)?)
+    }
+
+    fn get_episode_snapshots(&mut self, feed_id: FeedId) -> DbResult<Vec<EpisodeSnapshot>> {
+        let mut tags: HashMap<EpisodeId, Vec<String>> = HashMap::new();
+        let mut statement = self.connection.prepare(
+            "SELECT episode_tags.episode_id, tags.name FROM episode_tags
+            JOIN tags ON tags.id = episode_tags.tag_id
+            JOIN episodes ON episodes.id = episode_tags.episode_id
+            WHERE episodes.feed_id = :feed_id
+            ORDER BY tags.name",
+        )?;
+        let rows = statement.query_map(named_params! {":feed_id": feed_id}, |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+        for row in rows {
+            let (episode_id, name) = row?;
+            tags.entry(episode_id).or_default().push(name);
+        }
+
+        let mut statement = self.connection.prepare(
+            "SELECT id, guid, title, description, link, duration, publication_date,
+                    episode_number, season_number, media_url, status, position, hidden
+            FROM episodes
+            WHERE feed_id = :feed_id
+            ORDER BY publication_date, id",
+        )?;
+        let rows = statement.query_map(named_params! {":feed_id": feed_id}, |row| {
+            let id = row.get(0)?;
+            Ok(EpisodeSnapshot {
+                id,
+                guid: row.get(1)?,
+                title: row.get(2)?,
+                description: row.get(3)?,
+                link: row.get(4)?,
+                duration: row.get::<_, Option<u64>>(5)?.map(Duration::from_nanos),
+                publication_date: row.get(6)?,
+                episode_number: row.get(7)?,
+                season_number: row.get(8)?,
+                media_url: row.get(9)?,
+                status: EpisodeStatus::from_db(row.get(10)?, Duration::from_nanos(row.get(11)?)),
+                hidden: row.get(12)?,
+                tags: tags.remove(&id).unwrap_or_default(),
             })
         })?;
         Ok(collect_results(rows)?)
@@ -150,14 +542,15 @@ impl DataProvider for SqliteDataProvider {
             UpdateQuery::All => {
                 let mut statement = self
                     .connection
-                    .prepare("SELECT id, source FROM feeds WHERE enabled")?;
+                    .prepare("SELECT id, source FROM feeds WHERE enabled AND deleted_at IS NULL")?;
                 let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
                 Ok(collect_results(rows)?)
             }
             UpdateQuery::Pending => {
-                let mut statement = self
-                    .connection
-                    .prepare("SELECT id, source FROM feeds WHERE enabled AND status = :status")?;
+                let mut statement = self.connection.prepare(
+                    "SELECT id, source FROM feeds
+                    WHERE enabled AND status = :status AND deleted_at IS NULL",
+                )?;
                 let rows = statement.query_map(
                     named_params! {":status": FeedStatus::Pending.db_view().0},
                     |row| Ok((row.get(0)?, row.get(1)?)),
@@ -197,7 +590,7 @@ impl DataProvider for SqliteDataProvider {
         Ok(results)
     }
 
-    fn rename_feed(&mut self, feed_id: FeedId, name: String) -> DbResult<()> {
+    fn rename_feed(&mut self, feed_id: FeedId, name: Option<String>) -> DbResult<()> {
         let mut statement = self
             .connection
             .prepare("UPDATE feeds SET title_override = :name WHERE id = :feed_id")?;
@@ -223,13 +616,28 @@ impl DataProvider for SqliteDataProvider {
     }
 
     fn get_group_summaries(&mut self) -> DbResult<Vec<GroupSummary>> {
-        let mut statement = self
-            .connection
-            .prepare("SELECT id, name FROM groups ORDER BY ordering")?;
+        let sql = format!(
+            "{}
+            SELECT groups.id, groups.name, groups.parent_id, group_tree.depth, groups.collapsed,
+                (SELECT COUNT(episodes.id) FROM episodes
+                JOIN feeds ON feeds.id = episodes.feed_id
+                JOIN group_tree AS feed_group ON feed_group.id = feeds.group_id
+                WHERE episodes.status = 0 AND feeds.deleted_at IS NULL
+                    AND (feed_group.path = group_tree.path
+                    OR feed_group.path LIKE group_tree.path || '/%'))
+            FROM groups JOIN group_tree ON groups.id = group_tree.id
+            ORDER BY group_tree.path",
+            GROUP_TREE_CTE
+        );
+        let mut statement = self.connection.prepare(&sql)?;
         let items = statement.query_map([], |row| {
             Ok(GroupSummary {
                 id: row.get(0)?,
                 name: row.get(1)?,
+                parent_id: row.get(2)?,
+                depth: row.get(3)?,
+                collapsed: row.get(4)?,
+                new_count: row.get(5)?,
             })
         })?;
         Ok(collect_results(items)?)
@@ -253,10 +661,22 @@ impl DataProvider for SqliteDataProvider {
 
     fn delete_group(&mut self, group_id: GroupId) -> DbResult<()> {
         {
-            let mut statement = self
-                .connection
-                .prepare("DELETE FROM groups WHERE id = :group_id")?;
-            statement.execute(named_params! { ":group_id": group_id })?;
+            let transaction = self.connection.transaction()?;
+            transaction.execute(
+                "UPDATE groups SET parent_id = (SELECT parent_id FROM groups WHERE id = :group_id)
+                WHERE parent_id = :group_id",
+                named_params! { ":group_id": group_id },
+            )?;
+            transaction.execute(
+                "UPDATE feeds SET group_id = (SELECT parent_id FROM groups WHERE id = :group_id)
+                WHERE group_id = :group_id",
+                named_params! { ":group_id": group_id },
+            )?;
+            transaction.execute(
+                "DELETE FROM groups WHERE id = :group_id",
+                named_params! { ":group_id": group_id },
+            )?;
+            transaction.commit()?;
         }
         self.fix_group_oredering()?;
         Ok(())
@@ -293,6 +713,370 @@ impl DataProvider for SqliteDataProvider {
         Ok(())
     }
 
+    fn set_group_parent(
+        &mut self,
+        group_id: GroupId,
+        parent_id: Option<GroupId>,
+    ) -> DbResult<bool> {
+        if let Some(parent_id) = parent_id {
+            let mut statement = self.connection.prepare(GROUP_DESCENDANTS_SQL)?;
+            let descendants: Vec<GroupId> = collect_results(
+                statement.query_map(named_params! {":group_id": group_id}, |row| row.get(0))?,
+            )?;
+            if descendants.contains(&parent_id) {
+                return Ok(false);
+            }
+        }
+
+        let mut statement = self
+            .connection
+            .prepare("UPDATE groups SET parent_id = :parent_id WHERE id = :group_id")?;
+        statement.execute(named_params! {":parent_id": parent_id, ":group_id": group_id})?;
+        Ok(true)
+    }
+
+    fn set_group_collapsed(&mut self, group_id: Option<GroupId>, collapsed: bool) -> DbResult<()> {
+        let mut statement = self.connection.prepare(
+            "UPDATE groups SET collapsed = :collapsed WHERE :group_id IS NULL OR id = :group_id",
+        )?;
+        statement.execute(named_params! {":collapsed": collapsed, ":group_id": group_id})?;
+        Ok(())
+    }
+
+    fn create_view(&mut self, name: &str, query: &EpisodesQuery) -> DbResult<Option<ViewId>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT 1 FROM views WHERE name = :name")?;
+        let already_exist = statement
+            .query(named_params! {":name": name})?
+            .next()?
+            .is_some();
+        if already_exist {
+            return Ok(None);
+        }
+
+        let query = serde_json::to_string(query)
+            .map_err(|error| rusqlite::Error::ToSqlConversionFailure(Box::new(error)))?;
+        let mut statement = self
+            .connection
+            .prepare("INSERT INTO views (name, query) VALUES (:name, :query)")?;
+        let result = statement.insert(named_params! {":name": name, ":query": query})?;
+        Ok(Some(ViewId(result)))
+    }
+
+    fn get_view_summaries(&mut self) -> DbResult<Vec<ViewSummary>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT id, name, query FROM views ORDER BY name")?;
+        let items = statement.query_map([], |row| {
+            let query: String = row.get(2)?;
+            Ok(ViewSummary {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                query: serde_json::from_str(&query).map_err(|error| {
+                    rusqlite::Error::FromSqlConversionFailure(
+                        2,
+                        rusqlite::types::Type::Text,
+                        Box::new(error),
+                    )
+                })?,
+            })
+        })?;
+        Ok(collect_results(items)?)
+    }
+
+    fn rename_view(&mut self, view_id: ViewId, name: String) -> DbResult<()> {
+        let mut statement = self
+            .connection
+            .prepare("UPDATE views SET name = :name WHERE id = :view_id")?;
+        statement.execute(named_params! {":name": name, ":view_id": view_id})?;
+        Ok(())
+    }
+
+    fn delete_view(&mut self, view_id: ViewId) -> DbResult<()> {
+        let mut statement = self
+            .connection
+            .prepare("DELETE FROM views WHERE id = :view_id")?;
+        statement.execute(named_params! {":view_id": view_id})?;
+        Ok(())
+    }
+
+    fn get_queue(&mut self) -> DbResult<Vec<EpisodeSummary>> {
+        let mut statement = self.connection.prepare(
+            "SELECT ep.id, ep.feed_id, ep.episode_number, ep.season_number, ep.title, ep.status, ep.duration, ep.publication_date, ep.hidden, feeds.title
+            FROM queue
+            JOIN episodes AS ep ON ep.id = queue.episode_id
+            JOIN feeds ON feeds.id = ep.feed_id
+            WHERE feeds.deleted_at IS NULL
+            ORDER BY queue.ordering",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok(EpisodeSummary {
+                id: row.get(0)?,
+                feed_id: row.get(1)?,
+                episode_number: row.get(2)?,
+                season_number: row.get(3)?,
+                title: row.get(4)?,
+                status: EpisodeSummaryStatus::from_db(row.get(5)?),
+                duration: row.get::<_, Option<u64>>(6)?.map(Duration::from_nanos),
+                publication_date: row.get(7)?,
+                is_hidden: row.get(8)?,
+                feed_title: row.get(9)?,
+            })
+        })?;
+        Ok(collect_results(rows)?)
+    }
+
+    fn add_to_queue(&mut self, episode_id: EpisodeId) -> DbResult<()> {
+        let mut statement = self.connection.prepare("INSERT OR IGNORE INTO queue (episode_id, ordering) VALUES (:episode_id, COALESCE((SELECT MAX(ordering) + 1 FROM queue), 1))")?;
+        statement.execute(named_params! {":episode_id": episode_id})?;
+        Ok(())
+    }
+
+    fn remove_from_queue(&mut self, episode_id: EpisodeId) -> DbResult<()> {
+        self.connection.execute(
+            "DELETE FROM queue WHERE episode_id = :episode_id",
+            named_params! {":episode_id": episode_id},
+        )?;
+        self.fix_queue_ordering()?;
+        Ok(())
+    }
+
+    fn set_queue_position(&mut self, episode_id: EpisodeId, position: usize) -> DbResult<()> {
+        let current_position: usize = self
+            .connection
+            .prepare("SELECT ordering FROM queue WHERE episode_id = :episode_id")?
+            .query_row(named_params! {":episode_id": episode_id}, |row| row.get(0))?;
+
+        let sql = match current_position.cmp(&position) {
+            Ordering::Less => {
+                "UPDATE queue SET ordering = CASE
+                    WHEN ordering < :current_position OR ordering > :position THEN ordering
+                    WHEN ordering = :current_position THEN :position
+                    ELSE ordering - 1
+                END"
+            }
+            Ordering::Greater => {
+                "UPDATE queue SET ordering = CASE
+                    WHEN ordering < :position OR ordering > :current_position THEN ordering
+                    WHEN ordering = :current_position THEN :position
+                    ELSE ordering + 1
+                END"
+            }
+            Ordering::Equal => return Ok(()),
+        };
+
+        self.connection.prepare(sql)?.execute(
+            named_params! {":position": position, ":current_position": current_position},
+        )?;
+        self.fix_queue_ordering()?;
+        Ok(())
+    }
+
+    fn clear_queue(&mut self) -> DbResult<()> {
+        self.connection.execute("DELETE FROM queue", [])?;
+        Ok(())
+    }
+
+    fn create_bookmark(
+        &mut self,
+        episode_id: EpisodeId,
+        position: Duration,
+        note: Option<&str>,
+    ) -> DbResult<BookmarkId> {
+        let mut statement = self.connection.prepare(
+            "INSERT INTO bookmarks (episode_id, position, note, created_at)
+            VALUES (:episode_id, :position, :note, :created_at)",
+        )?;
+        let result = statement.insert(named_params! {
+            ":episode_id": episode_id,
+            ":position": position.as_nanos() as u64,
+            ":note": note,
+            ":created_at": Utc::now(),
+        })?;
+        Ok(BookmarkId(result))
+    }
+
+    fn get_bookmarks(&mut self) -> DbResult<Vec<Bookmark>> {
+        let mut statement = self.connection.prepare(
+            "SELECT bookmarks.id, bookmarks.episode_id, episodes.title, COALESCE(feeds.title_override, feeds.title),
+                    bookmarks.position, bookmarks.note, bookmarks.created_at
+            FROM bookmarks
+            JOIN episodes ON episodes.id = bookmarks.episode_id
+            JOIN feeds ON feeds.id = episodes.feed_id
+            WHERE feeds.deleted_at IS NULL
+            ORDER BY feeds.id, episodes.publication_date, episodes.id, bookmarks.position",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok(Bookmark {
+                id: row.get(0)?,
+                episode_id: row.get(1)?,
+                episode_title: row.get(2)?,
+                feed_title: row.get(3)?,
+                position: Duration::from_nanos(row.get(4)?),
+                note: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        })?;
+        Ok(collect_results(rows)?)
+    }
+
+    fn delete_bookmark(&mut self, bookmark_id: BookmarkId) -> DbResult<()> {
+        let mut statement = self
+            .connection
+            .prepare("DELETE FROM bookmarks WHERE id = :bookmark_id")?;
+        statement.execute(named_params! {":bookmark_id": bookmark_id})?;
+        Ok(())
+    }
+
+    fn get_tags(&mut self) -> DbResult<Vec<String>> {
+        let mut statement = self.connection.prepare(
+            "SELECT name FROM tags
+            WHERE id IN (SELECT tag_id FROM feed_tags) OR id IN (SELECT tag_id FROM episode_tags)
+            ORDER BY name",
+        )?;
+        let rows = statement.query_map([], |row| row.get(0))?;
+        Ok(collect_results(rows)?)
+    }
+
+    fn add_tag(&mut self, target: TagTarget, name: &str) -> DbResult<()> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "INSERT OR IGNORE INTO tags (name) VALUES (:name)",
+            named_params! {":name": name},
+        )?;
+        let (sql, id) = match target {
+            TagTarget::Feed(feed_id) => (
+                "INSERT OR IGNORE INTO feed_tags (feed_id, tag_id)
+                SELECT :id, id FROM tags WHERE name = :name",
+                feed_id.as_i64(),
+            ),
+            TagTarget::Episode(episode_id) => (
+                "INSERT OR IGNORE INTO episode_tags (episode_id, tag_id)
+                SELECT :id, id FROM tags WHERE name = :name",
+                episode_id.as_i64(),
+            ),
+        };
+        transaction.execute(sql, named_params! {":id": id, ":name": name})?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn remove_tag(&mut self, target: TagTarget, name: &str) -> DbResult<()> {
+        let transaction = self.connection.transaction()?;
+        let (sql, id) = match target {
+            TagTarget::Feed(feed_id) => (
+                "DELETE FROM feed_tags
+                WHERE feed_id = :id AND tag_id IN (SELECT id FROM tags WHERE name = :name)",
+                feed_id.as_i64(),
+            ),
+            TagTarget::Episode(episode_id) => (
+                "DELETE FROM episode_tags
+                WHERE episode_id = :id AND tag_id IN (SELECT id FROM tags WHERE name = :name)",
+                episode_id.as_i64(),
+            ),
+        };
+        transaction.execute(sql, named_params! {":id": id, ":name": name})?;
+        transaction.execute(
+            "DELETE FROM tags
+            WHERE id NOT IN (SELECT tag_id FROM feed_tags)
+                AND id NOT IN (SELECT tag_id FROM episode_tags)",
+            [],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn save_listening_session(&mut self, session: &ListeningSession) -> DbResult<()> {
+        let mut statement = self.connection.prepare(
+            "INSERT OR IGNORE INTO listening_sessions
+                (episode_id, start_position, end_position, started_at, ended_at, rate)
+            VALUES (:episode_id, :start_position, :end_position, :started_at, :ended_at, :rate)",
+        )?;
+        statement.execute(named_params! {
+            ":episode_id": session.episode_id,
+            ":start_position": session.start_position.as_nanos() as u64,
+            ":end_position": session.end_position.as_nanos() as u64,
+            ":started_at": session.started_at,
+            ":ended_at": session.ended_at,
+            ":rate": session.rate,
+        })?;
+        Ok(())
+    }
+
+    fn get_listening_sessions(&mut self) -> DbResult<Vec<ListeningSession>> {
+        let mut statement = self.connection.prepare(
+            "SELECT episode_id, start_position, end_position, started_at, ended_at, rate
+            FROM listening_sessions
+            ORDER BY started_at",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok(ListeningSession {
+                episode_id: row.get(0)?,
+                start_position: Duration::from_nanos(row.get(1)?),
+                end_position: Duration::from_nanos(row.get(2)?),
+                started_at: row.get(3)?,
+                ended_at: row.get(4)?,
+                rate: row.get(5)?,
+            })
+        })?;
+        Ok(collect_results(rows)?)
+    }
+
+    fn get_listening_stats(&mut self) -> DbResult<ListeningStats> {
+        let mut statement = self.connection.prepare(
+            "SELECT feeds.id, COALESCE(feeds.title_override, feeds.title, feeds.source),
+                    ROUND(TOTAL(julianday(sessions.ended_at) - julianday(sessions.started_at)) * 86400),
+                    COUNT(DISTINCT sessions.episode_id), COUNT(*)
+            FROM listening_sessions AS sessions
+            JOIN episodes ON episodes.id = sessions.episode_id
+            JOIN feeds ON feeds.id = episodes.feed_id
+            GROUP BY feeds.id
+            ORDER BY 3 DESC, feeds.id",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok(FeedListeningStats {
+                feed_id: row.get(0)?,
+                title: row.get(1)?,
+                listened: Duration::from_secs_f64(row.get::<_, f64>(2)?.max(0.0)),
+                episodes: row.get(3)?,
+                sessions: row.get(4)?,
+            })
+        })?;
+        let feeds = collect_results(rows)?;
+
+        let mut statement = self.connection.prepare(
+            "SELECT date(started_at, 'weekday 0', '-6 days') AS week_start,
+                    ROUND(TOTAL(julianday(ended_at) - julianday(started_at)) * 86400)
+            FROM listening_sessions
+            GROUP BY week_start
+            ORDER BY week_start",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok(WeekListeningStats {
+                week_start: row.get(0)?,
+                listened: Duration::from_secs_f64(row.get::<_, f64>(1)?.max(0.0)),
+            })
+        })?;
+        let weeks = collect_results(rows)?;
+
+        let mut statement = self.connection.prepare(
+            "SELECT COUNT(*), TOTAL(status = :finished) FROM episodes
+            WHERE id IN (SELECT episode_id FROM listening_sessions)",
+        )?;
+        let (started_episodes, finished_episodes) = statement.query_row(
+            named_params! {":finished": EpisodeSummaryStatus::Finished.db_view()},
+            |row| Ok((row.get(0)?, row.get::<_, f64>(1)? as usize)),
+        )?;
+
+        Ok(ListeningStats {
+            feeds,
+            weeks,
+            started_episodes,
+            finished_episodes,
+        })
+    }
+
     fn get_episode(&mut self, episode_id: EpisodeId) -> DbResult<Option<Episode>> {
         let mut statement =
             self.connection.prepare("SELECT feed_id, episode_number, season_number, title, description, link, status, position, duration, publication_date, media_url FROM episodes WHERE id = :id")?;
@@ -325,7 +1109,8 @@ impl DataProvider for SqliteDataProvider {
         let mut statement = self
             .connection
             .prepare(
-                "SELECT episodes.media_url, episodes.position, episodes.duration, episodes.title, feeds.id, feeds.title
+                "SELECT episodes.media_url, episodes.position, episodes.duration, episodes.title, feeds.id, feeds.title,
+                        feeds.playback_rate, feeds.skip_intro, feeds.skip_outro
                 FROM episodes JOIN feeds ON feeds.id = episodes.feed_id
                 WHERE episodes.id = :id LIMIT 1")?;
         let result = statement.query_row(named_params! {":id": episode_id}, |row| {
@@ -337,6 +1122,9 @@ impl DataProvider for SqliteDataProvider {
                 episode_title: row.get(3)?,
                 feed_id: row.get(4)?,
                 feed_title: row.get(5)?,
+                playback_rate: row.get(6)?,
+                skip_intro: Duration::from_nanos(row.get(7)?),
+                skip_outro: Duration::from_nanos(row.get(8)?),
             })
         });
         match result {
@@ -355,7 +1143,6 @@ impl DataProvider for SqliteDataProvider {
                     SUM(CASE WHEN ep.publication_date IS NOT NULL THEN 1 ELSE 0 END), feeds.reversed
             FROM episodes AS ep
             JOIN feeds ON ep.feed_id = feeds.id
-            LEFT JOIN groups ON feeds.group_id = groups.id
             "
             .to_string();
         query.build_where_clause(&mut sql);
@@ -388,18 +1175,14 @@ impl DataProvider for SqliteDataProvider {
         range: Range<usize>,
     ) -> DbResult<Vec<EpisodeSummary>> {
         let feed_title_required = request.include_feed_title;
-        let has_group_filter = request.group_id.is_some();
         let mut sql = "SELECT ep.id, ep.feed_id, ep.episode_number, ep.season_number, ep.title, ep.status, ep.duration, ep.publication_date, ep.hidden".to_string();
         if feed_title_required {
             sql.push_str(", feeds.title");
         }
         sql.push_str(" FROM episodes AS ep");
-        if feed_title_required || has_group_filter {
+        if feed_title_required {
             sql.push_str(" JOIN feeds ON feeds.id == ep.feed_id");
         }
-        if has_group_filter {
-            sql.push_str(" LEFT JOIN groups on groups.id = feeds.group_id");
-        }
         request.build_where_clause(&mut sql);
         sql.push_str(" ORDER BY ep.publication_date ");
         sql.push_str(match request.reversed_order {
@@ -446,37 +1229,287 @@ impl DataProvider for SqliteDataProvider {
         Ok(count)
     }
 
+    fn get_next_episode(
+        &mut self,
+        query: EpisodesQuery,
+        episode_id: EpisodeId,
+    ) -> DbResult<Option<EpisodeId>> {
+        // Episodes without a publication date are ordered before all others, the same way
+        // SQLite sorts NULL values in the episodes list.
+        let query = query.with_hidden(false);
+        let position = match query.reversed_order {
+            true => concat!(
+                "(ep.publication_date IS NOT NULL, COALESCE(ep.publication_date, ''), ep.id) > ",
+                "(SELECT publication_date IS NOT NULL, COALESCE(publication_date, ''), id ",
+                "FROM episodes WHERE id = :current_id)",
+            ),
+            false => concat!(
+                "(ep.publication_date IS NOT NULL, COALESCE(ep.publication_date, ''), ep.id) < ",
+                "(SELECT publication_date IS NOT NULL, COALESCE(publication_date, ''), id ",
+                "FROM episodes WHERE id = :current_id)",
+            ),
+        };
+        let mut sql = "SELECT ep.id FROM episodes AS ep".to_string();
+        query.build_where_clause_with(&mut sql, &["ep.status != :finished", position]);
+        sql.push_str(match query.reversed_order {
+            true => concat!(
+                " ORDER BY ep.publication_date IS NOT NULL ASC, ep.publication_date ASC,",
+                " ep.id ASC",
+            ),
+            false => concat!(
+                " ORDER BY ep.publication_date IS NOT NULL DESC, ep.publication_date DESC,",
+                " ep.id DESC",
+            ),
+        });
+        sql.push_str(" LIMIT 1");
+        let mut statement = self.connection.prepare(&sql)?;
+
+        let where_params = EpisodeQueryParams::from_query(query);
+        let mut params = where_params.as_sql_params();
+        let finished = EpisodeSummaryStatus::Finished.db_view();
+        params.push((":finished", &finished as &dyn rusqlite::ToSql));
+        params.push((":current_id", &episode_id as &dyn rusqlite::ToSql));
+        match statement.query_row(&*params, |row| row.get(0)) {
+            Ok(episode_id) => Ok(Some(episode_id)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
     fn create_feed_pending(&mut self, data: &NewFeedMetadata) -> DbResult<Option<FeedId>> {
-        let mut exists_statement = self
-            .connection
-            .prepare("SELECT true FROM feeds WHERE source = :source")?;
-        let exists = exists_statement
-            .query(named_params! {":source": data.source})?
-            .next()?
-            .is_some();
-        if exists {
-            return Ok(None);
+        match self.find_feed_by_source(&data.source, None)? {
+            Some((feed_id, true)) => {
+                self.restore_feed(feed_id)?;
+                return Ok(Some(feed_id));
+            }
+            Some((_, false)) => return Ok(None),
+            None => {}
         }
 
-        let mut statement = self
-            .connection
-            .prepare("INSERT INTO feeds (source, title, link) VALUES (:source, :title, :link)")?;
+        let mut statement = self.connection.prepare(
+            "INSERT INTO feeds (source, source_key, title, link)
+            VALUES (:source, :source_key, :title, :link)",
+        )?;
         statement
-            .insert(
-                named_params! {":source": data.source, ":title": data.title, ":link": data.link},
-            )
+            .insert(named_params! {
+                ":source": data.source,
+                ":source_key": feed_url::comparison_key(&data.source),
+                ":title": data.title,
+                ":link": data.link,
+            })
             .map(|id| Some(FeedId(id)))
             .map_err(Into::into)
     }
 
+    fn find_duplicate_feed(&mut self, feed_id: FeedId) -> DbResult<Option<String>> {
+        let mut statement = self.connection.prepare(
+            "SELECT id, title, link, COALESCE(title_override, title, source)
+            FROM feeds WHERE deleted_at IS NULL",
+        )?;
+        let feeds = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, FeedId>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (title, link) = match feeds.iter().find(|(id, _, _, _)| *id == feed_id) {
+            Some((_, Some(title), link, _)) if !title.trim().is_empty() => {
+                let link = (link.as_deref())
+                    .filter(|link| !link.trim().is_empty())
+                    .map(feed_url::comparison_key);
+                (title.trim().to_lowercase(), link)
+            }
+            _ => return Ok(None),
+        };
+        let duplicate = feeds.into_iter().find(|(id, other_title, other_link, _)| {
+            let same_title = (other_title.as_deref())
+                .map_or(false, |other_title| other_title.trim().to_lowercase() == title);
+            let same_link = match (&link, other_link.as_deref()) {
+                (Some(link), Some(other_link)) if !other_link.trim().is_empty() => {
+                    *link == feed_url::comparison_key(other_link)
+                }
+                _ => true,
+            };
+            *id != feed_id && same_title && same_link
+        });
+        Ok(duplicate.map(|(_, _, _, display_title)| display_title))
+    }
+
+    fn set_feed_source(&mut self, feed_id: FeedId, source: &str) -> DbResult<bool> {
+        if let Some((_, false)) = self.find_feed_by_source(source, Some(feed_id))? {
+            return Ok(false);
+        }
+
+        let mut statement = self.connection.prepare(
+            "UPDATE feeds SET source = :source, source_key = :source_key WHERE id = :feed_id",
+        )?;
+        statement.execute(named_params! {
+            ":source": source,
+            ":source_key": feed_url::comparison_key(source),
+            ":feed_id": feed_id,
+        })?;
+        Ok(true)
+    }
+
     fn delete_feed(&mut self, id: FeedId) -> DbResult<()> {
         let mut statement = self
             .connection
-            .prepare("DELETE FROM feeds WHERE id = :id")?;
+            .prepare("UPDATE feeds SET deleted_at = :deleted_at WHERE id = :id")?;
+        statement.execute(named_params! {":id": id, ":deleted_at": Utc::now()})?;
+        Ok(())
+    }
+
+    fn merge_feed(&mut self, feed_id: FeedId, target_id: FeedId) -> DbResult<usize> {
+        let transaction = self.connection.transaction()?;
+        let mut matched = HashSet::new();
+        {
+            let mut episodes_statement = transaction.prepare(
+                "SELECT id, guid, media_url, title, publication_date, status, position, hidden
+                FROM episodes WHERE feed_id = :feed_id",
+            )?;
+            let episodes = episodes_statement.query_map(named_params! {":feed_id": feed_id}, |row| {
+                Ok((
+                    row.get::<_, EpisodeId>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<DateTime<Utc>>>(4)?,
+                    EpisodeStatus::from_db(row.get(5)?, Duration::from_nanos(row.get(6)?)),
+                    row.get::<_, bool>(7)?,
+                ))
+            })?;
+            let episodes = collect_results(episodes)?;
+
+            let mut candidates_statement = transaction.prepare(
+                "SELECT id, status, position FROM episodes
+                WHERE feed_id = :feed_id AND (guid = :guid OR media_url = :media_url
+                    OR (title = :title AND publication_date = :publication_date))
+                ORDER BY guid = :guid DESC, media_url = :media_url DESC, id",
+            )?;
+            let mut status_statement = transaction.prepare(
+                "UPDATE episodes SET status = :status, position = :position WHERE id = :id",
+            )?;
+            for (source_id, guid, media_url, title, publication_date, status, hidden) in episodes {
+                let candidates = candidates_statement.query_map(
+                    named_params! {
+                        ":feed_id": target_id,
+                        ":guid": guid,
+                        ":media_url": media_url,
+                        ":title": title,
+                        ":publication_date": publication_date,
+                    },
+                    |row| {
+                        Ok((
+                            row.get::<_, EpisodeId>(0)?,
+                            EpisodeStatus::from_db(row.get(1)?, Duration::from_nanos(row.get(2)?)),
+                        ))
+                    },
+                )?;
+                let candidates = collect_results(candidates)?;
+                let target = (candidates.into_iter()).find(|(id, _)| !matched.contains(id));
+                let (target, target_status) = match target {
+                    Some(target) => target,
+                    None => continue,
+                };
+                matched.insert(target);
+
+                if restores_status(&target_status, &status) {
+                    let (status, position) = status.db_view();
+                    status_statement.execute(named_params! {
+                        ":status": status,
+                        ":position": position.as_nanos() as u64,
+                        ":id": target,
+                    })?;
+                }
+                if hidden {
+                    transaction.execute(
+                        "UPDATE episodes SET hidden = 1 WHERE id = :id",
+                        named_params! {":id": target},
+                    )?;
+                }
+                let params = named_params! {":source_id": source_id, ":target_id": target};
+                transaction.execute(
+                    "UPDATE bookmarks SET episode_id = :target_id WHERE episode_id = :source_id",
+                    params,
+                )?;
+                transaction.execute(
+                    "UPDATE OR IGNORE listening_sessions SET episode_id = :target_id
+                    WHERE episode_id = :source_id",
+                    params,
+                )?;
+                transaction.execute(
+                    "UPDATE OR IGNORE queue SET episode_id = :target_id
+                    WHERE episode_id = :source_id",
+                    params,
+                )?;
+                transaction.execute(
+                    "INSERT OR IGNORE INTO episode_tags (episode_id, tag_id)
+                    SELECT :target_id, tag_id FROM episode_tags WHERE episode_id = :source_id",
+                    params,
+                )?;
+            }
+
+            let params = named_params! {":feed_id": feed_id, ":target_id": target_id};
+            transaction.execute(
+                "UPDATE feeds
+                SET group_id = COALESCE(group_id, (SELECT group_id FROM feeds WHERE id = :feed_id))
+                WHERE id = :target_id",
+                params,
+            )?;
+            transaction.execute(
+                "INSERT OR IGNORE INTO feed_tags (feed_id, tag_id)
+                SELECT :target_id, tag_id FROM feed_tags WHERE feed_id = :feed_id",
+                params,
+            )?;
+            transaction.execute(
+                "UPDATE feeds SET deleted_at = :deleted_at WHERE id = :feed_id",
+                named_params! {":feed_id": feed_id, ":deleted_at": Utc::now()},
+            )?;
+        }
+        transaction.commit()?;
+        Ok(matched.len())
+    }
+
+    fn restore_feed(&mut self, id: FeedId) -> DbResult<()> {
+        let mut statement = self
+            .connection
+            .prepare("UPDATE feeds SET deleted_at = NULL WHERE id = :id")?;
         statement.execute(named_params! {":id": id})?;
         Ok(())
     }
 
+    fn get_trashed_feeds(&mut self) -> DbResult<Vec<TrashedFeed>> {
+        let mut statement = self.connection.prepare(
+            "SELECT id, COALESCE(title_override, title, source), deleted_at
+            FROM feeds
+            WHERE deleted_at IS NOT NULL
+            ORDER BY deleted_at DESC",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok(TrashedFeed {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                deleted_at: row.get(2)?,
+            })
+        })?;
+        Ok(collect_results(rows)?)
+    }
+
+    fn purge_trash(&mut self, deleted_before: Option<DateTime<Utc>>) -> DbResult<usize> {
+        let mut statement = self.connection.prepare(
+            "DELETE FROM feeds
+            WHERE deleted_at IS NOT NULL
+                AND (:deleted_before IS NULL OR deleted_at < :deleted_before)",
+        )?;
+        let count = statement.execute(named_params! {":deleted_before": deleted_before})?;
+        Ok(count)
+    }
+
     fn set_feed_status(&mut self, feed_id: FeedId, status: FeedStatus) -> DbResult<()> {
         let (status, error) = status.db_view();
         self.connection
@@ -493,6 +1526,30 @@ impl DataProvider for SqliteDataProvider {
         Ok(())
     }
 
+    fn set_feed_playback_rate(&mut self, feed_id: FeedId, rate: Option<f64>) -> DbResult<()> {
+        let mut statement = self
+            .connection
+            .prepare("UPDATE feeds SET playback_rate = :rate WHERE id = :id")?;
+        statement.execute(named_params! {":rate": rate, ":id": feed_id})?;
+        Ok(())
+    }
+
+    fn set_feed_skip_intro(&mut self, feed_id: FeedId, duration: Duration) -> DbResult<()> {
+        let mut statement = self
+            .connection
+            .prepare("UPDATE feeds SET skip_intro = :duration WHERE id = :id")?;
+        statement.execute(named_params! {":duration": duration.as_nanos() as u64, ":id": feed_id})?;
+        Ok(())
+    }
+
+    fn set_feed_skip_outro(&mut self, feed_id: FeedId, duration: Duration) -> DbResult<()> {
+        let mut statement = self
+            .connection
+            .prepare("UPDATE feeds SET skip_outro = :duration WHERE id = :id")?;
+        statement.execute(named_params! {":duration": duration.as_nanos() as u64, ":id": feed_id})?;
+        Ok(())
+    }
+
     fn reverse_feed_order(&mut self, feed_id: FeedId) -> DbResult<()> {
         let mut statement = self
             .connection
@@ -545,17 +1602,66 @@ impl DataProvider for SqliteDataProvider {
         Ok(())
     }
 
+    fn get_episode_states(&mut self, query: EpisodesQuery) -> DbResult<Vec<EpisodeState>> {
+        let mut sql =
+            "SELECT ep.id, ep.status, ep.position, ep.hidden FROM episodes AS ep ".to_string();
+        query.build_where_clause(&mut sql);
+        let where_params = EpisodeQueryParams::from_query(query);
+        let mut statement = self.connection.prepare(&sql)?;
+        let rows = statement.query_map(&*where_params.as_sql_params(), |row| {
+            Ok(EpisodeState {
+                id: row.get(0)?,
+                status: EpisodeStatus::from_db(row.get(1)?, Duration::from_nanos(row.get(2)?)),
+                hidden: row.get(3)?,
+            })
+        })?;
+        Ok(collect_results(rows)?)
+    }
+
+    fn restore_episode_states(&mut self, states: &[EpisodeState]) -> DbResult<HashSet<FeedId>> {
+        let transaction = self.connection.transaction()?;
+        let mut feed_ids = HashSet::new();
+        {
+            let mut update = transaction.prepare(
+                "UPDATE episodes SET status = :status, position = :position, hidden = :hidden
+                WHERE id = :id",
+            )?;
+            let mut select_feed =
+                transaction.prepare("SELECT feed_id FROM episodes WHERE id = ?1")?;
+            for state in states {
+                let (status, position) = state.status.db_view();
+                let updated = update.execute(named_params! {
+                    ":status": status,
+                    ":position": position.as_nanos() as u64,
+                    ":hidden": state.hidden,
+                    ":id": state.id,
+                })?;
+                if updated > 0 {
+                    feed_ids.insert(select_feed.query_row([state.id], |row| row.get(0))?);
+                }
+            }
+        }
+        transaction.commit()?;
+        Ok(feed_ids)
+    }
+
     fn writer<'a>(&'a mut self, feed_id: FeedId) -> DbResult<Box<dyn EpisodeWriter + 'a>> {
         let transaction = self.connection.transaction()?;
         Ok(Box::new(SqliteEpisodeWriter {
             feed_id,
             transaction,
+            written: HashSet::new(),
         }))
     }
 }
 
 impl EpisodesQuery {
     fn build_where_clause(&self, query: &mut String) {
+        self.build_where_clause_with(query, &[]);
+    }
+
+    /// Appends the WHERE clause matching the query combined with additional `conditions`.
+    fn build_where_clause_with(&self, query: &mut String, conditions: &[&str]) {
         let mut clauses = Vec::new();
         if self.episode_id.is_some() {
             clauses.push("ep.id = :id");
@@ -564,14 +1670,36 @@ impl EpisodesQuery {
             clauses.push("ep.feed_id = :feed_id");
         }
         if self.group_id.is_some() {
-            clauses.push("groups.id = :group_id");
+            clauses.push(concat!(
+                "ep.feed_id IN (SELECT id FROM feeds WHERE group_id IN (WITH RECURSIVE",
+                " descendants(id) AS (SELECT :group_id UNION ALL SELECT groups.id FROM groups",
+                " JOIN descendants ON groups.parent_id = descendants.id)",
+                " SELECT id FROM descendants))",
+            ));
         }
         if self.status.is_some() {
             clauses.push("ep.status = :status");
         }
+        if self.excluded_status.is_some() {
+            clauses.push("ep.status != :excluded_status");
+        }
+        if self.published_within.is_some() {
+            clauses.push("ep.publication_date >= :published_after");
+        }
+        if self.episode_id.is_none() && self.feed_id.is_none() {
+            clauses.push("ep.feed_id IN (SELECT id FROM feeds WHERE deleted_at IS NULL)");
+        }
+        if self.tag.is_some() {
+            clauses.push(concat!(
+                "(ep.id IN (SELECT episode_id FROM episode_tags JOIN tags ON tags.id = tag_id",
+                " WHERE tags.name = :tag) OR ep.feed_id IN (SELECT feed_id FROM feed_tags",
+                " JOIN tags ON tags.id = tag_id WHERE tags.name = :tag))",
+            ));
+        }
         if !self.with_hidden {
             clauses.push("NOT ep.hidden");
         }
+        clauses.extend_from_slice(conditions);
         if !clauses.is_empty() {
             query.push_str(" WHERE ");
             for (index, clause) in clauses.into_iter().enumerate() {
@@ -590,6 +1718,9 @@ struct EpisodeQueryParams {
     feed_id: Option<FeedId>,
     group_id: Option<GroupId>,
     status: Option<usize>,
+    excluded_status: Option<usize>,
+    published_after: Option<chrono::DateTime<Utc>>,
+    tag: Option<String>,
 }
 
 impl EpisodeQueryParams {
@@ -599,6 +1730,14 @@ impl EpisodeQueryParams {
             feed_id: query.feed_id,
             group_id: query.group_id,
             status: query.status.map(|status| status.db_view()),
+            excluded_status: query.excluded_status.map(|status| status.db_view()),
+            published_after: query.published_within.map(|duration| {
+                chrono::Duration::from_std(duration)
+                    .ok()
+                    .and_then(|duration| Utc::now().checked_sub_signed(duration))
+                    .unwrap_or(chrono::DateTime::<Utc>::MIN_UTC)
+            }),
+            tag: query.tag,
         }
     }
 
@@ -616,6 +1755,15 @@ impl EpisodeQueryParams {
         if let Some(status) = self.status.as_ref() {
             params.push((":status", status));
         }
+        if let Some(excluded_status) = self.excluded_status.as_ref() {
+            params.push((":excluded_status", excluded_status));
+        }
+        if let Some(published_after) = self.published_after.as_ref() {
+            params.push((":published_after", published_after));
+        }
+        if let Some(tag) = self.tag.as_ref() {
+            params.push((":tag", tag));
+        }
         params
     }
 }
@@ -632,6 +1780,7 @@ fn collect_results<T, E>(items: impl IntoIterator<Item = Result<T, E>>) -> Resul
 pub struct SqliteEpisodeWriter<'a> {
     feed_id: FeedId,
     transaction: rusqlite::Transaction<'a>,
+    written: HashSet<EpisodeId>,
 }
 
 impl<'a> EpisodeWriter for SqliteEpisodeWriter<'a> {
@@ -681,15 +1830,53 @@ impl<'a> EpisodeWriter for SqliteEpisodeWriter<'a> {
         let mut id_statement = self.transaction.prepare(
             "SELECT ep.id FROM episodes AS ep WHERE feed_id = :feed_id AND guid = :guid",
         )?;
-        id_statement
-            .query_row(
-                named_params! {
-                    ":feed_id": self.feed_id,
-                    ":guid": metadata.guid,
-                },
-                |row| row.get(0),
-            )
-            .map_err(QueryError::from)
+        let episode_id = id_statement.query_row(
+            named_params! {
+                ":feed_id": self.feed_id,
+                ":guid": metadata.guid,
+            },
+            |row| row.get(0),
+        )?;
+        self.written.insert(episode_id);
+        Ok(episode_id)
+    }
+
+    fn relink_episode(&mut self, metadata: &EpisodeMetadata) -> DbResult<()> {
+        let mut exists_statement = self
+            .transaction
+            .prepare("SELECT 1 FROM episodes WHERE feed_id = :feed_id AND guid = :guid")?;
+        let exists = exists_statement
+            .query(named_params! {":feed_id": self.feed_id, ":guid": metadata.guid})?
+            .next()?
+            .is_some();
+        if exists {
+            return Ok(());
+        }
+
+        let mut candidates_statement = self.transaction.prepare(
+            "SELECT id FROM episodes
+            WHERE feed_id = :feed_id AND (media_url = :media_url
+                OR (title = :title AND publication_date = :publication_date))
+            ORDER BY media_url = :media_url DESC, id",
+        )?;
+        let candidates = candidates_statement.query_map(
+            named_params! {
+                ":feed_id": self.feed_id,
+                ":media_url": metadata.media_url,
+                ":title": metadata.title,
+                ":publication_date": metadata.publication_date,
+            },
+            |row| row.get::<_, EpisodeId>(0),
+        )?;
+        let candidates = collect_results(candidates)?;
+        let episode_id = candidates.into_iter().find(|id| !self.written.contains(id));
+        if let Some(episode_id) = episode_id {
+            let mut statement = self
+                .transaction
+                .prepare("UPDATE episodes SET guid = :guid WHERE id = :id")?;
+            statement.execute(named_params! {":guid": metadata.guid, ":id": episode_id})?;
+        }
+        Ok(())
     }
 
     fn close(self: Box<Self>) -> DbResult<()> {
@@ -707,12 +1894,13 @@ impl<'a> EpisodeWriter for SqliteEpisodeWriter<'a> {
 
 #[cfg(test)]
 mod tests {
-    use super::{ConnectionError, SqliteDataProvider};
-    use crate::datasource::{DataProvider, NewFeedMetadata};
-    use crate::metadata::{EpisodeMetadata, FeedMetadata};
-    use crate::model::{EpisodeStatus, EpisodeSummary, EpisodeSummaryStatus, FeedStatus};
+    use super::{ConnectionError, SqliteDataProvider, BACKUPS_KEPT};
+    use crate::conformance::feed_with_episodes;
+    use crate::datasource::{DataProvider, NewFeedMetadata, QueryError};
+    use crate::model::EpisodeStatus;
     use crate::EpisodesQuery;
     use pretty_assertions::assert_eq;
+    use rusqlite::{ffi, Error};
     use std::time::Duration;
 
     #[test]
@@ -747,189 +1935,209 @@ mod tests {
             error,
             ConnectionError::VersionUnknown {
                 version: 20,
-                current: 2
+                current: 13
             }
         ));
     }
 
     #[test]
-    fn feed_update() {
+    fn fills_source_keys() {
         let mut provider = SqliteDataProvider::connect(":memory:").unwrap();
-        let id = provider
-            .create_feed_pending(&NewFeedMetadata::new(
-                "http://example.com/feed.xml".to_string(),
-            ))
-            .unwrap()
+        provider
+            .create_feed_pending(&NewFeedMetadata::new("http://example.com/feed.xml".into()))
+            .unwrap();
+        provider
+            .connection
+            .execute("UPDATE feeds SET source_key = NULL", [])
             .unwrap();
+        SqliteDataProvider::fill_source_keys(&provider.connection).unwrap();
 
-        let feed_summaries = provider.get_feed_summaries().unwrap();
-        assert_eq!(feed_summaries.len(), 1);
-        assert_eq!(feed_summaries[0].id, id);
-        assert_eq!(feed_summaries[0].title, "http://example.com/feed.xml");
-        assert_eq!(feed_summaries[0].has_title, false);
-        assert_eq!(feed_summaries[0].status, FeedStatus::Pending);
-
-        let mut writer = provider.writer(id).unwrap();
-        writer
-            .set_feed_metadata(&FeedMetadata {
-                title: "Title",
-                description: "Description",
-                link: "http://example.com",
-                author: Some("Author"),
-                copyright: Some("Copyright"),
-            })
+        let duplicate = NewFeedMetadata::new("https://example.com/feed.xml/".into());
+        assert_eq!(provider.create_feed_pending(&duplicate).unwrap(), None);
+    }
+
+    fn list_backups(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+        let mut backups: Vec<_> = std::fs::read_dir(dir.join("backups"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        backups.sort();
+        backups
+    }
+
+    #[test]
+    fn backs_up_before_migration() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("episodes");
+        SqliteDataProvider::connect(&path).unwrap();
+        assert!(!dir.path().join("backups").exists());
+
+        // Applying the latest migration to the database the second time fails
+        let previous_version = SqliteDataProvider::CURRENT_VERSION - 1;
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        connection
+            .pragma_update(None, "user_version", previous_version)
             .unwrap();
-        writer.close().unwrap();
+        drop(connection);
+        assert!(SqliteDataProvider::connect(&path).is_err());
 
-        let feed = provider.get_feed(id).unwrap().unwrap();
-        assert_eq!(feed.title.as_deref(), Some("Title"));
-        assert_eq!(feed.description.as_deref(), Some("Description"));
-        assert_eq!(feed.link.as_deref(), Some("http://example.com"));
-        assert_eq!(feed.author.as_deref(), Some("Author"));
-        assert_eq!(feed.copyright.as_deref(), Some("Copyright"));
-        assert_eq!(&feed.source, "http://example.com/feed.xml");
-        assert_eq!(feed.status, FeedStatus::Loaded);
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        let user_version: u32 = connection
+            .pragma_query_value(None, "user_version", |value| value.get(0))
+            .unwrap();
+        assert_eq!(user_version, previous_version);
+        assert_eq!(list_backups(dir.path()).len(), 1);
     }
 
     #[test]
-    fn does_not_create_duplicate() {
-        let mut provider = SqliteDataProvider::connect(":memory:").unwrap();
-        let id1 = provider
+    fn restores_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("episodes");
+        let backup_path = dir.path().join("episodes.bak");
+        let mut provider = SqliteDataProvider::connect(&path).unwrap();
+        provider
             .create_feed_pending(&NewFeedMetadata::new(
-                "http://example.com/feed.xml".to_string(),
+                "http://example.com/first.xml".to_string(),
             ))
             .unwrap();
-        let id2 = provider
+        drop(provider);
+        std::fs::copy(&path, &backup_path).unwrap();
+
+        let mut provider = SqliteDataProvider::connect(&path).unwrap();
+        provider
             .create_feed_pending(&NewFeedMetadata::new(
-                "http://example.com/feed.xml".to_string(),
+                "http://example.com/second.xml".to_string(),
             ))
             .unwrap();
-
-        assert!(id1.is_some());
-        assert!(id2.is_none());
+        drop(provider);
+
+        let backup_error = SqliteDataProvider::restore_backup(&path, &backup_path).unwrap();
+        assert!(backup_error.is_none());
+        let mut provider = SqliteDataProvider::connect(&path).unwrap();
+        let feeds = provider.get_feed_summaries().unwrap();
+        assert_eq!(feeds.len(), 1);
+        assert_eq!(feeds[0].title, "http://example.com/first.xml");
+        drop(provider);
+
+        let backups = list_backups(dir.path());
+        assert_eq!(backups.len(), 1);
+        SqliteDataProvider::restore_backup(&path, &backups[0]).unwrap();
+        let mut provider = SqliteDataProvider::connect(&path).unwrap();
+        assert_eq!(provider.get_feed_summaries().unwrap().len(), 2);
+
+        let not_backup = dir.path().join("empty.bak");
+        rusqlite::Connection::open(&not_backup).unwrap();
+        let error = SqliteDataProvider::restore_backup(&path, &not_backup).unwrap_err();
+        assert!(matches!(error, ConnectionError::NotABackup));
     }
 
     #[test]
-    fn episode_update() {
-        let mut provider = SqliteDataProvider::connect(":memory:").unwrap();
-        let feed_id = provider
+    fn restores_over_damaged_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("episodes");
+        let mut provider = SqliteDataProvider::connect(&path).unwrap();
+        provider
             .create_feed_pending(&NewFeedMetadata::new(
                 "http://example.com/feed.xml".to_string(),
             ))
-            .unwrap()
             .unwrap();
+        drop(provider);
+
+        // The restored backup is the oldest one and is kept after the rotation
+        let backups_dir = dir.path().join("backups");
+        std::fs::create_dir(&backups_dir).unwrap();
+        let backup_path = backups_dir.join("episodes-0.bak");
+        std::fs::copy(&path, &backup_path).unwrap();
+        for i in 1..=BACKUPS_KEPT {
+            std::fs::write(backups_dir.join(format!("episodes-{}.bak", i)), "").unwrap();
+        }
+        std::fs::write(&path, "not a database").unwrap();
 
-        let mut writer = provider.writer(feed_id).unwrap();
-        let episode_id = writer
-            .set_episode_metadata(&EpisodeMetadata {
-                title: Some("title"),
-                description: Some("description"),
-                link: Some("link"),
-                guid: "guid-1",
-                duration: None,
-                publication_date: None,
-                episode_number: Some(3),
-                season_number: Some(4),
-                media_url: "http://example.com/feed.xml",
-                block: false,
-            })
-            .unwrap();
-        writer.close().unwrap();
-
-        let retrieved = provider.get_episode(episode_id).unwrap().unwrap();
-        assert_eq!(retrieved.id, episode_id);
-        assert_eq!(retrieved.feed_id, feed_id);
-        assert_eq!(retrieved.episode_number, Some(3));
-        assert_eq!(retrieved.season_number, Some(4));
-        assert_eq!(retrieved.title.as_deref(), Some("title"));
-        assert_eq!(retrieved.description.as_deref(), Some("description"));
-        assert_eq!(retrieved.link.as_deref(), Some("link"));
-        assert_eq!(retrieved.status, EpisodeStatus::New);
-        assert_eq!(retrieved.duration, None);
-        assert_eq!(retrieved.publication_date, None);
-        assert_eq!(&retrieved.media_url, "http://example.com/feed.xml");
-
-        let mut writer = provider.writer(feed_id).unwrap();
-        let episode_id_1 = writer
-            .set_episode_metadata(&EpisodeMetadata {
-                title: Some("title-upd"),
-                description: Some("description-upd"),
-                link: Some("link-upd"),
-                guid: "guid-1",
-                duration: Some(Duration::from_secs(300)),
-                publication_date: None,
-                episode_number: Some(8),
-                season_number: None,
-                media_url: "http://example.com/feed2.xml",
-                block: false,
-            })
-            .unwrap();
-        assert_eq!(episode_id, episode_id_1);
-        writer.close().unwrap();
-
-        let retrieved = provider.get_episode(episode_id).unwrap().unwrap();
-        assert_eq!(retrieved.id, episode_id);
-        assert_eq!(retrieved.feed_id, feed_id);
-        assert_eq!(retrieved.episode_number, Some(8));
-        assert_eq!(retrieved.season_number, None);
-        assert_eq!(retrieved.title.as_deref(), Some("title-upd"));
-        assert_eq!(retrieved.description.as_deref(), Some("description-upd"));
-        assert_eq!(retrieved.link.as_deref(), Some("link-upd"));
-        assert_eq!(retrieved.status, EpisodeStatus::New);
-        assert_eq!(retrieved.duration, Some(Duration::from_secs(300)));
-        assert_eq!(retrieved.publication_date, None);
-        assert_eq!(&retrieved.media_url, "http://example.com/feed2.xml");
-
-        let mut writer = provider.writer(feed_id).unwrap();
-        let episode_id_2 = writer
-            .set_episode_metadata(&EpisodeMetadata {
-                title: Some("second-title"),
-                description: Some("second-description"),
-                link: None,
-                guid: "guid-2",
-                duration: None,
-                publication_date: None,
-                episode_number: None,
-                season_number: None,
-                media_url: "http://example.com/feed3.xml",
-                block: false,
-            })
-            .unwrap();
-        writer.close().unwrap();
+        let backup_error = SqliteDataProvider::restore_backup(&path, &backup_path).unwrap();
+        assert!(backup_error.is_some());
+        assert!(backup_path.exists());
+        let damaged = list_backups(dir.path()).into_iter().filter(|path| {
+            path.extension().map_or(false, |extension| extension == "damaged")
+        });
+        assert_eq!(damaged.count(), 1);
+        let mut provider = SqliteDataProvider::connect(&path).unwrap();
+        assert_eq!(provider.get_feed_summaries().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn rotates_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("episodes");
+        let backups_dir = dir.path().join("backups");
+        std::fs::create_dir(&backups_dir).unwrap();
+        for i in 0..BACKUPS_KEPT + 2 {
+            std::fs::write(backups_dir.join(format!("episodes-{}.bak", i)), "").unwrap();
+        }
+        std::fs::write(backups_dir.join("other-0.bak"), "").unwrap();
+
+        SqliteDataProvider::rotate_backups(&path, None).unwrap();
+        let backups = list_backups(dir.path());
+        assert_eq!(backups.len(), BACKUPS_KEPT + 1);
+        assert!(!backups_dir.join("episodes-0.bak").exists());
+        assert!(!backups_dir.join("episodes-1.bak").exists());
+        assert!(backups_dir.join("other-0.bak").exists());
+    }
 
-        let mut episodes = provider
-            .get_episode_summaries(EpisodesQuery::default().feed_id(feed_id), 0..100)
+    #[test]
+    fn detects_external_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("episodes");
+        let mut provider = SqliteDataProvider::connect(&path).unwrap();
+        let mut other_provider = SqliteDataProvider::connect(&path).unwrap();
+        assert!(!provider.check_external_changes().unwrap());
+
+        provider
+            .create_feed_pending(&NewFeedMetadata::new(
+                "http://example.com/feed.xml".to_string(),
+            ))
             .unwrap();
-        episodes.sort_by_key(|episode| episode.id.0);
-        assert_eq!(
-            episodes[0],
-            EpisodeSummary {
-                id: episode_id_1,
-                feed_id,
-                episode_number: Some(8),
-                season_number: None,
-                title: Some("title-upd".to_string()),
-                feed_title: None,
-                status: EpisodeSummaryStatus::New,
-                duration: Some(Duration::from_secs(300)),
-                publication_date: None,
-                is_hidden: false,
-            }
-        );
-        assert_eq!(
-            episodes[1],
-            EpisodeSummary {
-                id: episode_id_2,
-                feed_id,
-                episode_number: None,
-                season_number: None,
-                title: Some("second-title".to_string()),
-                feed_title: None,
-                status: EpisodeSummaryStatus::New,
-                duration: None,
-                publication_date: None,
-                is_hidden: false,
-            }
+        assert!(!provider.check_external_changes().unwrap());
+        assert!(other_provider.check_external_changes().unwrap());
+        assert!(!other_provider.check_external_changes().unwrap());
+        assert_eq!(other_provider.get_feed_summaries().unwrap().len(), 1);
+
+        let (_, episode_ids) = feed_with_episodes(
+            &mut provider,
+            "http://example.com/other.xml",
+            &[("episode", None)],
         );
+        let episode_id = episode_ids[0];
+        assert!(other_provider.check_external_changes().unwrap());
+        for position in [10, 20] {
+            let status = EpisodeStatus::Started(Duration::from_secs(position));
+            provider
+                .set_episode_status(EpisodesQuery::default().id(episode_id), status)
+                .unwrap();
+        }
+        assert!(other_provider.check_external_changes().unwrap());
+        provider
+            .set_episode_status(
+                EpisodesQuery::default().id(episode_id),
+                EpisodeStatus::Started(Duration::from_secs(30)),
+            )
+            .unwrap();
+        assert!(!other_provider.check_external_changes().unwrap());
+    }
+
+    #[test]
+    fn classifies_errors() {
+        let failure = |code| QueryError::from(Error::SqliteFailure(ffi::Error::new(code), None));
+        let constraint = failure(ffi::SQLITE_CONSTRAINT_UNIQUE);
+        assert!(matches!(constraint, QueryError::ConstraintViolation(_)));
+        assert!(!constraint.is_transient());
+        assert!(matches!(failure(ffi::SQLITE_CORRUPT), QueryError::Corrupted(_)));
+        assert!(matches!(failure(ffi::SQLITE_NOTADB), QueryError::Corrupted(_)));
+        assert!(failure(ffi::SQLITE_BUSY).is_transient());
+        assert!(matches!(failure(ffi::SQLITE_IOERR_WRITE), QueryError::Io(_)));
+        assert!(matches!(failure(ffi::SQLITE_MISUSE), QueryError::SqliteError(_)));
+        assert!(matches!(
+            QueryError::from(Error::QueryReturnedNoRows),
+            QueryError::NotFound
+        ));
     }
 }
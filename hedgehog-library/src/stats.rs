@@ -0,0 +1,126 @@
+use crate::datasource::DataProvider;
+use crate::model::ListeningStats;
+use crate::QueryError;
+use std::io;
+use std::time::Duration;
+
+const MOST_PLAYED_COUNT: usize = 5;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Db(#[from] QueryError),
+}
+
+pub fn build_report<W: io::Write, D: DataProvider>(write: W, data: &mut D) -> Result<(), Error> {
+    let stats = data.get_listening_stats()?;
+    write_report(write, &stats)?;
+    Ok(())
+}
+
+pub fn write_report<W: io::Write>(mut write: W, stats: &ListeningStats) -> io::Result<()> {
+    writeln!(write, "Listened: {}", HoursFormatter(stats.total_listened()))?;
+    match stats.completion_rate() {
+        Some(rate) => writeln!(
+            write,
+            "Completion rate: {:.0}% ({} of {} episodes finished)",
+            rate * 100.0,
+            stats.finished_episodes,
+            stats.started_episodes
+        )?,
+        None => writeln!(write, "Completion rate: -")?,
+    }
+
+    writeln!(write)?;
+    writeln!(write, "Most played")?;
+    for (index, feed) in stats.most_played(MOST_PLAYED_COUNT).iter().enumerate() {
+        writeln!(write, "{:>4}. {} ({} episodes)", index + 1, feed.title, feed.episodes)?;
+    }
+
+    writeln!(write)?;
+    writeln!(write, "Hours per feed")?;
+    for feed in &stats.feeds {
+        writeln!(write, "{:>8}  {}", HoursFormatter(feed.listened), feed.title)?;
+    }
+
+    writeln!(write)?;
+    writeln!(write, "Hours per week")?;
+    for week in &stats.weeks {
+        writeln!(
+            write,
+            "{:>8}  {}",
+            HoursFormatter(week.listened),
+            week.week_start.format("%Y-%m-%d")
+        )?;
+    }
+    Ok(())
+}
+
+struct HoursFormatter(Duration);
+
+impl std::fmt::Display for HoursFormatter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let hours = format!("{:.1}h", self.0.as_secs_f64() / 3600.0);
+        f.pad(&hours)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_report;
+    use crate::model::{FeedId, FeedListeningStats, ListeningStats, WeekListeningStats};
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    fn feed(id: i64, title: &str, minutes: u64, episodes: usize) -> FeedListeningStats {
+        FeedListeningStats {
+            feed_id: FeedId(id),
+            title: title.to_string(),
+            listened: Duration::from_secs(minutes * 60),
+            episodes,
+            sessions: episodes,
+        }
+    }
+
+    #[test]
+    fn writes_report() {
+        let stats = ListeningStats {
+            feeds: vec![feed(1, "Long talks", 270, 2), feed(2, "Daily news", 90, 6)],
+            weeks: vec![
+                WeekListeningStats {
+                    week_start: NaiveDate::from_ymd(2022, 1, 3),
+                    listened: Duration::from_secs(3 * 3600),
+                },
+                WeekListeningStats {
+                    week_start: NaiveDate::from_ymd(2022, 1, 10),
+                    listened: Duration::from_secs(3 * 3600),
+                },
+            ],
+            started_episodes: 8,
+            finished_episodes: 6,
+        };
+        let mut output = Vec::new();
+        write_report(&mut output, &stats).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Listened: 6.0h\n\
+            Completion rate: 75% (6 of 8 episodes finished)\n\
+            \n\
+            Most played\n   \
+            1. Daily news (6 episodes)\n   \
+            2. Long talks (2 episodes)\n\
+            \n\
+            Hours per feed\n    \
+            4.5h  Long talks\n    \
+            1.5h  Daily news\n\
+            \n\
+            Hours per week\n    \
+            3.0h  2022-01-03\n    \
+            3.0h  2022-01-10\n"
+        );
+    }
+}
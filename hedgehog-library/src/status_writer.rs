@@ -1,6 +1,7 @@
-use crate::model::{EpisodeId, EpisodeStatus};
+use crate::model::{EpisodeId, EpisodeStatus, ListeningSession};
 use crate::{EpisodesQuery, FeedUpdateRequest, Library};
 use actix::prelude::*;
+use chrono::Utc;
 use std::fs;
 use std::io::{BufRead, BufReader, ErrorKind};
 use std::path::PathBuf;
@@ -10,6 +11,7 @@ pub struct StatusWriter {
     library: Addr<Library>,
     playing_path: Option<PathBuf>,
     saved_episode_id: Option<EpisodeId>,
+    session: Option<ListeningSession>,
 }
 
 impl StatusWriter {
@@ -18,6 +20,7 @@ impl StatusWriter {
             library,
             playing_path: None,
             saved_episode_id: None,
+            session: None,
         }
     }
 
@@ -70,6 +73,22 @@ impl StatusWriter {
             }
         }
     }
+
+    fn update_session(&mut self, episode_id: EpisodeId, position: Duration) {
+        if let Some(ref mut session) = self.session {
+            if session.episode_id == episode_id {
+                session.end_position = position;
+            }
+        }
+    }
+
+    /// Takes the current session out if it is long enough to be saved. Sessions are saved only
+    /// once they end.
+    fn take_session(&mut self) -> Option<ListeningSession> {
+        let mut session = self.session.take()?;
+        session.ended_at = Utc::now();
+        Some(session).filter(|session| session.listened() >= Duration::from_secs(1))
+    }
 }
 
 impl Actor for StatusWriter {
@@ -81,6 +100,8 @@ impl Actor for StatusWriter {
 pub enum StatusWriterCommand {
     Set(EpisodesQuery, EpisodeStatus),
     StopPlayback,
+    StartSession(EpisodeId, Duration, f64),
+    EndSession,
 }
 
 impl StatusWriterCommand {
@@ -111,7 +132,7 @@ impl StatusWriterCommand {
 }
 
 impl Handler<StatusWriterCommand> for StatusWriter {
-    type Result = ();
+    type Result = ResponseFuture<()>;
 
     fn handle(&mut self, msg: StatusWriterCommand, _ctx: &mut Self::Context) -> Self::Result {
         match msg {
@@ -125,13 +146,38 @@ impl Handler<StatusWriterCommand> for StatusWriter {
                     if let Some(future_saved) = future_saved {
                         self.save_episode_id(future_saved);
                     }
+                    if let EpisodeStatus::Started(position) = status {
+                        self.update_session(episode_id, position);
+                    }
                 }
 
                 self.library
                     .do_send(FeedUpdateRequest::SetStatus(query, status));
             }
             StatusWriterCommand::StopPlayback => self.save_episode_id(None),
+            StatusWriterCommand::StartSession(episode_id, position, rate) => {
+                if let Some(session) = self.take_session() {
+                    self.library
+                        .do_send(FeedUpdateRequest::SaveListeningSession(session));
+                }
+                self.session = Some(ListeningSession::new(episode_id, position, rate));
+            }
+            // Resolves once the session is saved, so that it isn't lost when the application is
+            // stopped right after
+            StatusWriterCommand::EndSession => {
+                if let Some(session) = self.take_session() {
+                    let request = self
+                        .library
+                        .send(FeedUpdateRequest::SaveListeningSession(session));
+                    return Box::pin(async move {
+                        if let Err(error) = request.await {
+                            log::error!(target: "actix", "{}", error);
+                        }
+                    });
+                }
+            }
         }
+        Box::pin(async {})
     }
 }
 
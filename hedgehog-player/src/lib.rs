@@ -34,6 +34,8 @@ pub struct Player {
     reported_volume: Option<Option<Volume>>,
     state: Option<State>,
     required_seek: Option<Duration>,
+    required_rate: Option<f64>,
+    outro: Duration,
     should_pause: bool,
     seek_position: Option<Duration>,
 }
@@ -55,6 +57,8 @@ impl Player {
             subscribers: Vec::new(),
             state: None,
             required_seek: None,
+            required_rate: None,
+            outro: Duration::ZERO,
             should_pause: false,
             seek_position: None,
         })
@@ -67,6 +71,8 @@ impl Player {
             reported_volume: None,
             state: None,
             required_seek: None,
+            required_rate: None,
+            outro: Duration::ZERO,
             should_pause: false,
             seek_position: None,
         }
@@ -77,6 +83,16 @@ impl Player {
         self.notify_subscribers(PlayerNotification::StateChanged(self.state));
     }
 
+    fn finish(&mut self) {
+        if let Some(ref element) = self.element {
+            if let Err(error) = element.set_state(gst::State::Null) {
+                log::error!(target: "player", "{}", error);
+            }
+        }
+        self.notify_subscribers(PlayerNotification::Eos);
+        self.set_state(None);
+    }
+
     fn notify_subscribers(&mut self, notification: PlayerNotification) {
         for subscriber in &self.subscribers {
             if let Err(error) = subscriber.do_send(notification.clone()) {
@@ -276,6 +292,8 @@ pub enum PlaybackCommand {
     SeekRelative(SeekOffset),
     #[cmd(rename = "rate")]
     SetRate(f64),
+    #[cmd(ignore)]
+    SetOutro(Duration),
 }
 
 impl Handler<PlaybackCommand> for Player {
@@ -311,6 +329,8 @@ impl Handler<PlaybackCommand> for Player {
                     };
                     self.should_pause = matches!(initial_state, InitialPlaybackState::Paused);
                     self.seek_position = None;
+                    self.required_rate = None;
+                    self.outro = Duration::ZERO;
                     if let Some(metadata) = metadata {
                         self.notify_subscribers(PlayerNotification::MetadataChanged(metadata));
                     }
@@ -415,7 +435,9 @@ impl Handler<PlaybackCommand> for Player {
                     }
                 }
                 PlaybackCommand::SetRate(speed) => {
-                    if self.state.map(|state| state.is_started) == Some(true) {
+                    if self.state.map(|state| state.is_started) == Some(false) {
+                        self.required_rate = Some(speed);
+                    } else if self.state.map(|state| state.is_started) == Some(true) {
                         let element = self.element.as_ref().unwrap();
                         let current_position =
                             element.query_position::<gst::ClockTime>().or_else(|| {
@@ -437,6 +459,7 @@ impl Handler<PlaybackCommand> for Player {
                         }
                     }
                 }
+                PlaybackCommand::SetOutro(outro) => self.outro = outro,
             }
             Ok(())
         })();
@@ -579,6 +602,9 @@ impl StreamHandler<gst::Message> for Player {
                             if let Some(seek) = self.required_seek.take() {
                                 ctx.address().do_send(PlaybackCommand::Seek(seek));
                             }
+                            if let Some(rate) = self.required_rate.take() {
+                                ctx.address().do_send(PlaybackCommand::SetRate(rate));
+                            }
                             if self.should_pause {
                                 if let Some(ref element) = self.element {
                                     if let Err(error) = element.set_state(gst::State::Paused) {
@@ -625,6 +651,15 @@ impl Handler<TimerTick> for Player {
                     position,
                     seeked: false,
                 });
+
+                let duration = (self.element.as_ref())
+                    .and_then(|element| element.query_duration::<gst::ClockTime>())
+                    .map(|duration| Duration::from_nanos(duration.nseconds()));
+                if let Some(duration) = duration {
+                    if !self.outro.is_zero() && position + self.outro >= duration {
+                        self.finish();
+                    }
+                }
             }
         }
         ctx.spawn(
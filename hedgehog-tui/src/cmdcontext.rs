@@ -1,9 +1,10 @@
-use cmdparse::{tokens::Token, CompletionResult, Parsable, Parser};
-use hedgehog_library::model::{FeedSummary, FeedView, GroupSummary};
+use cmdparse::{tokens::Token, tokens::TokenStream, CompletionResult, Parsable, Parser};
+use hedgehog_library::model::{FeedSummary, FeedView, GroupSummary, ViewSummary};
 
 #[derive(Clone)]
 pub(crate) struct CommandContext<'a> {
-    pub(crate) feeds: &'a [FeedView<FeedSummary, GroupSummary>],
+    pub(crate) feeds: &'a [FeedView<FeedSummary, GroupSummary, ViewSummary>],
+    pub(crate) tags: &'a [String],
 }
 
 #[derive(Default)]
@@ -14,7 +15,7 @@ impl<'c> Parser<CommandContext<'c>> for GroupNameParser {
 
     fn parse<'a>(
         &self,
-        input: cmdparse::tokens::TokenStream<'a>,
+        input: TokenStream<'a>,
         ctx: CommandContext<'c>,
     ) -> cmdparse::ParseResult<'a, Self::Value> {
         <String as Parsable<CommandContext<'c>>>::Parser::default().parse(input, ctx)
@@ -22,35 +23,80 @@ impl<'c> Parser<CommandContext<'c>> for GroupNameParser {
 
     fn complete<'a>(
         &self,
-        input: cmdparse::tokens::TokenStream<'a>,
+        input: TokenStream<'a>,
         ctx: CommandContext<'c>,
     ) -> cmdparse::CompletionResult<'a> {
-        match input.take() {
-            Some(Ok((Token::Text(text), remaining))) if remaining.is_all_consumed() => {
-                let text = text.parse_string();
-                CompletionResult::new_final(true).add_suggestions(
-                    ctx.feeds
-                        .iter()
-                        .filter_map(FeedView::as_group)
-                        .filter_map(|group| {
-                            group.name.strip_prefix(&text as &str).and_then(|key| {
-                                match key.is_empty() {
-                                    true => None,
-                                    false => Some(key.to_string().into()),
-                                }
-                            })
-                        }),
-                )
-            }
-            Some(Ok((Token::Text(_), remaining))) => CompletionResult::new(remaining, true),
-            Some(Ok((Token::Attribute(_), _))) => CompletionResult::new(input, false),
-            Some(Err(_)) => CompletionResult::new_final(false),
-            None => CompletionResult::new_final(false).add_suggestions(
-                ctx.feeds
-                    .iter()
-                    .filter_map(FeedView::as_group)
-                    .map(|group| group.name.to_string().into()),
-            ),
+        let groups = ctx.feeds.iter().filter_map(FeedView::as_group);
+        complete_name(input, groups.map(|group| group.name.as_str()))
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct FeedNameParser;
+
+impl<'c> Parser<CommandContext<'c>> for FeedNameParser {
+    type Value = String;
+
+    fn parse<'a>(
+        &self,
+        input: TokenStream<'a>,
+        ctx: CommandContext<'c>,
+    ) -> cmdparse::ParseResult<'a, Self::Value> {
+        <String as Parsable<CommandContext<'c>>>::Parser::default().parse(input, ctx)
+    }
+
+    fn complete<'a>(
+        &self,
+        input: TokenStream<'a>,
+        ctx: CommandContext<'c>,
+    ) -> cmdparse::CompletionResult<'a> {
+        let feeds = ctx.feeds.iter().filter_map(FeedView::as_feed);
+        complete_name(input, feeds.map(|feed| feed.title.as_str()))
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct TagNameParser;
+
+impl<'c> Parser<CommandContext<'c>> for TagNameParser {
+    type Value = String;
+
+    fn parse<'a>(
+        &self,
+        input: TokenStream<'a>,
+        ctx: CommandContext<'c>,
+    ) -> cmdparse::ParseResult<'a, Self::Value> {
+        <String as Parsable<CommandContext<'c>>>::Parser::default().parse(input, ctx)
+    }
+
+    fn complete<'a>(
+        &self,
+        input: TokenStream<'a>,
+        ctx: CommandContext<'c>,
+    ) -> cmdparse::CompletionResult<'a> {
+        complete_name(input, ctx.tags.iter().map(String::as_str))
+    }
+}
+
+fn complete_name<'a, 'n>(
+    input: TokenStream<'a>,
+    names: impl Iterator<Item = &'n str>,
+) -> CompletionResult<'a> {
+    match input.take() {
+        Some(Ok((Token::Text(text), remaining))) if remaining.is_all_consumed() => {
+            let text = text.parse_string();
+            CompletionResult::new_final(true).add_suggestions(names.filter_map(|name| {
+                name.strip_prefix(&text as &str)
+                    .and_then(|key| match key.is_empty() {
+                        true => None,
+                        false => Some(key.to_string().into()),
+                    })
+            }))
         }
+        Some(Ok((Token::Text(_), remaining))) => CompletionResult::new(remaining, true),
+        Some(Ok((Token::Attribute(_), _))) => CompletionResult::new(input, false),
+        Some(Err(_)) => CompletionResult::new_final(false),
+        None => CompletionResult::new_final(false)
+            .add_suggestions(names.map(|name| name.to_string().into())),
     }
 }
@@ -1,7 +1,7 @@
 use crate::cmdcontext::CommandContext;
 use crate::keymap::Key;
 use crate::logger::Severity;
-use crate::options::OptionsUpdate;
+use crate::options::{OptionsUpdate, Seconds};
 use crate::scrolling::ScrollAction;
 use crate::theming::ThemeCommand;
 use cmdparse::Parsable;
@@ -46,9 +46,38 @@ pub(crate) enum Command {
     SetGroup(#[cmd(parser = "crate::cmdcontext::GroupNameParser")] String),
     UnsetGroup,
     PlaceGroup(usize),
+    #[cmd(ignore, alias = "collapse", alias = "expand")]
+    SetGroupCollapsed(
+        #[cmd(
+            alias_value(alias = "collapse", value = "true"),
+            alias_value(alias = "expand", value = "false")
+        )]
+        bool,
+    ),
+    ToggleGroup,
+    #[cmd(ignore, alias = "collapse-all", alias = "expand-all")]
+    SetAllGroupsCollapsed(
+        #[cmd(
+            alias_value(alias = "collapse-all", value = "true"),
+            alias_value(alias = "expand-all", value = "false")
+        )]
+        bool,
+    ),
+    View(ViewCommand),
+    Queue(QueueCommand),
+    Bookmark(Option<String>),
+    DeleteBookmark,
     #[cmd(alias = "delete-feed")]
     Delete,
+    MergeFeed(#[cmd(parser = "crate::cmdcontext::FeedNameParser")] String),
+    Restore,
+    EmptyTrash,
     Reverse,
+    FeedRate(Option<f64>),
+    SkipIntro(Seconds),
+    SkipOutro(Seconds),
+    Tag(#[cmd(parser = "crate::cmdcontext::TagNameParser")] String),
+    Untag(#[cmd(parser = "crate::cmdcontext::TagNameParser")] String),
     Rename(#[cmd(parser = "hedgehog_library::search::SearchQueryParser")] String),
     #[cmd(alias = "u")]
     Update {
@@ -56,6 +85,7 @@ pub(crate) enum Command {
         current_only: bool,
     },
     AddArchive(String),
+    SetSource(String),
     Mark {
         status: EpisodeStatus,
         #[cmd(attr(all = "true"))]
@@ -78,6 +108,8 @@ pub(crate) enum Command {
 
     RepeatCommand,
     Refresh,
+    Undo,
+    Redo,
 
     Chain(Vec<Command>),
     #[cmd(rename = "if")]
@@ -102,6 +134,32 @@ pub(crate) enum Command {
     },
 }
 
+#[derive(Debug, Clone, PartialEq, Parsable)]
+#[cmd(ctx = "CommandContext<'_>")]
+pub(crate) enum ViewCommand {
+    Add {
+        name: String,
+        #[cmd(attr(group))]
+        group: Option<String>,
+        #[cmd(attr(status))]
+        status: Option<EpisodeSummaryStatus>,
+        #[cmd(attr(unfinished = "true"))]
+        unfinished: bool,
+        #[cmd(attr(days))]
+        days: Option<u64>,
+        #[cmd(attr(tag))]
+        tag: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Parsable)]
+pub(crate) enum QueueCommand {
+    Add,
+    Remove,
+    Move(usize),
+    Clear,
+}
+
 #[derive(Debug, Clone, PartialEq, Parsable)]
 pub(crate) enum LinkType {
     Feed,
@@ -123,6 +181,10 @@ pub(crate) enum FocusedPane {
     FeedsList,
     #[cmd(rename = "episodes")]
     EpisodesList,
+    Queue,
+    Bookmarks,
+    Trash,
+    Stats,
     Search,
     #[cmd(rename = "log")]
     ErrorsLog,
@@ -133,7 +195,11 @@ pub(crate) enum SelectedItem {
     SpecialFeed,
     Feed,
     Group,
+    View,
     Episode,
+    QueuedEpisode,
+    Bookmark,
+    TrashedFeed,
     LogEntry,
     SearchResult,
     Nothing,
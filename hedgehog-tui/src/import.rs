@@ -0,0 +1,117 @@
+use actix::prelude::*;
+use hedgehog_library::model::{FeedId, FeedStatus};
+use hedgehog_library::opml::{ImportEntry, ImportReport};
+use hedgehog_library::{
+    FeedUpdateNotification, FeedUpdateRequest, FeedUpdateResult, InMemoryCache, Library,
+    SqliteDataProvider, UpdateQuery,
+};
+use std::collections::HashMap;
+use std::io;
+
+fn entry_name(entry: &ImportEntry) -> String {
+    match &entry.title {
+        Some(title) => format!("{} ({})", title, entry.source),
+        None => entry.source.clone(),
+    }
+}
+
+pub(crate) fn print_report(report: &ImportReport, dry_run: bool) {
+    if dry_run {
+        for entry in &report.added {
+            println!("new: {}", entry_name(entry));
+        }
+        for entry in &report.duplicates {
+            println!("duplicate: {}", entry_name(entry));
+        }
+        for outline in &report.invalid {
+            let name = (outline.source.as_deref())
+                .or(outline.title.as_deref())
+                .unwrap_or("untitled outline");
+            println!("invalid: {}, {}", name, outline.reason);
+        }
+    }
+    println!(
+        "{}: {}, duplicates: {}, invalid: {}",
+        if dry_run { "Feeds to add" } else { "Feeds added" },
+        report.added.len(),
+        report.duplicates.len(),
+        report.invalid.len(),
+    );
+}
+
+/// Prints the outcome of each feed's update and stops the system once all feeds are updated.
+struct UpdateProgress {
+    pending: HashMap<FeedId, String>,
+    total: usize,
+    failed: usize,
+}
+
+impl Actor for UpdateProgress {
+    type Context = Context<Self>;
+}
+
+impl Handler<FeedUpdateNotification> for UpdateProgress {
+    type Result = ();
+
+    fn handle(&mut self, msg: FeedUpdateNotification, _ctx: &mut Self::Context) -> Self::Result {
+        let (feed_id, result) = match msg {
+            FeedUpdateNotification::UpdateFinished(feed_id, result) => (feed_id, result),
+            _ => return,
+        };
+        let name = match self.pending.remove(&feed_id) {
+            Some(name) => name,
+            None => return,
+        };
+
+        let progress = format!("[{}/{}]", self.total - self.pending.len(), self.total);
+        match result {
+            FeedUpdateResult::Updated(summary) => {
+                println!("{} {}: {} new episodes", progress, summary.title, summary.new_count);
+            }
+            FeedUpdateResult::StatusChanged(FeedStatus::Error(error)) => {
+                self.failed += 1;
+                println!("{} {}: {}", progress, name, error);
+            }
+            FeedUpdateResult::StatusChanged(_) => {}
+        }
+
+        if self.pending.is_empty() {
+            println!(
+                "Feeds updated: {}, failed: {}",
+                self.total - self.failed,
+                self.failed
+            );
+            System::current().stop();
+        }
+    }
+}
+
+/// Fetches the feeds that were just imported and blocks until all of them are updated.
+pub(crate) fn update_feeds(
+    data_provider: SqliteDataProvider,
+    entries: &[ImportEntry],
+) -> io::Result<()> {
+    let pending: HashMap<FeedId, String> = (entries.iter())
+        .filter_map(|entry| entry.feed_id.map(|feed_id| (feed_id, entry_name(entry))))
+        .collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let system = System::new();
+    system.block_on(async move {
+        let feed_ids: Vec<FeedId> = pending.keys().cloned().collect();
+        let progress = UpdateProgress {
+            total: pending.len(),
+            pending,
+            failed: 0,
+        }
+        .start();
+        let library = Library::new(InMemoryCache::new(data_provider)).start();
+        library.do_send(FeedUpdateRequest::Subscribe(progress.recipient()));
+        for feed_id in feed_ids {
+            library.do_send(FeedUpdateRequest::Update(UpdateQuery::Single(feed_id)));
+        }
+    });
+    system.run()
+}
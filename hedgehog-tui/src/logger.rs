@@ -59,6 +59,7 @@ enum LogTarget {
     Player,
     Playback,
     Sql,
+    Cache,
     Io,
     Networking,
     Browser,
@@ -77,6 +78,7 @@ impl LogTarget {
             "player" => LogTarget::Player,
             "playback" => LogTarget::Playback,
             "sql" => LogTarget::Sql,
+            "cache" => LogTarget::Cache,
             "io" => LogTarget::Io,
             "networking" => LogTarget::Networking,
             "browser" => LogTarget::Browser,
@@ -98,6 +100,7 @@ pub(crate) struct LogEntry {
 impl LogEntry {
     fn store_in_history(&self) -> bool {
         match (&self.severity, &self.target) {
+            (_, LogTarget::Cache) => true,
             (Severity::Information, _) => false,
             (_, LogTarget::Command) => false,
             (_, _) => true,
@@ -129,6 +132,7 @@ impl LogEntry {
             LogTarget::Player => Some("Internal audio player error"),
             LogTarget::Playback => Some("Playback error:"),
             LogTarget::Sql => Some("Internal database error"),
+            LogTarget::Cache => None,
             LogTarget::Io => Some("I/O error"),
             LogTarget::Networking => Some("Network error"),
             LogTarget::Browser => None,
@@ -219,6 +223,11 @@ impl LogHistory {
         if entry.severity < self.level {
             return;
         }
+        if entry.target == LogTarget::Cache {
+            // Statistics are only kept in the log and are never shown in the status line
+            self.log.push(entry);
+            return;
+        }
         self.display = if entry.store_in_history() {
             self.log.push(entry);
             Some(LogDisplay::Last)
@@ -4,6 +4,7 @@ mod command;
 mod environment;
 mod events;
 mod history;
+mod import;
 mod keymap;
 mod logger;
 mod mouse;
@@ -26,9 +27,12 @@ use crossterm::terminal::{
 use directories::BaseDirs;
 use environment::AppEnvironment;
 use hedgehog_library::datasource::DataProvider;
+use hedgehog_library::migration::{self, antennapod, gpodder, newsboat};
 use hedgehog_library::opml::LineEndingTransformer;
 use hedgehog_library::status_writer::StatusWriter;
-use hedgehog_library::{opml, InMemoryCache, Library, SqliteDataProvider};
+use hedgehog_library::{
+    backup, bookmarks, opml, stats, DatabaseReader, InMemoryCache, Library, SqliteDataProvider,
+};
 use hedgehog_player::Player;
 use logger::ActorLogger;
 use screen::UI;
@@ -37,14 +41,21 @@ use std::fmt;
 use std::fs::OpenOptions;
 use std::io::{self, BufReader, SeekFrom};
 use std::io::{Read, Seek, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tui::backend::CrosstermBackend;
 use tui::Terminal;
 
+/// The number of connections used to read the database concurrently.
+const DATABASE_READERS: usize = 4;
+
 #[derive(Debug, thiserror::Error)]
 #[error("Data directory cannot be determined")]
 struct CannotDetermineDataDirectory;
 
+#[derive(Debug, thiserror::Error)]
+#[error("The database of another client cannot be read from standard input")]
+struct DatabaseFromStdin;
+
 #[derive(Debug, thiserror::Error)]
 struct AlreadyRunningError {
     pid: Option<String>,
@@ -76,6 +87,21 @@ fn main() {
                         .help("A file path where the OPML file will be written"),
                 ),
         )
+        .subcommand(
+            clap::SubCommand::with_name("export-bookmarks")
+                .about("Export bookmarks as a Markdown file")
+                .arg(
+                    clap::Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .help("A file path where the Markdown file will be written"),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("stats").about("Print listening statistics"),
+        )
         .subcommand(
             clap::SubCommand::with_name("import")
                 .about("Import podcasts from the OPML file")
@@ -83,7 +109,64 @@ fn main() {
                     clap::Arg::with_name("file")
                         .required(true)
                         .value_name("FILE")
-                        .help("A path to the OPML file or '-' for standard input"),
+                        .help("A path or an HTTP URL of the OPML file or '-' for standard input"),
+                )
+                .arg(
+                    clap::Arg::with_name("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .takes_value(true)
+                        .possible_values(&["opml", "gpodder", "newsboat", "antennapod"])
+                        .default_value("opml")
+                        .help("The format of the file or the client it was written by"),
+                )
+                .arg(
+                    clap::Arg::with_name("dry_run")
+                        .long("dry-run")
+                        .conflicts_with("update")
+                        .help("Report the feeds that would be added without adding them"),
+                )
+                .arg(
+                    clap::Arg::with_name("update")
+                        .long("update")
+                        .help("Fetch the added feeds right away"),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("backup")
+                .about("Write the whole library to a JSON backup")
+                .arg(
+                    clap::Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .help("A file path where the backup will be written"),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("restore")
+                .about("Merge a JSON backup into the library")
+                .arg(
+                    clap::Arg::with_name("file")
+                        .required(true)
+                        .value_name("FILE")
+                        .help("A path to the backup file or '-' for standard input"),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("db")
+                .about("Manage the episodes database")
+                .setting(clap::AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    clap::SubCommand::with_name("restore")
+                        .about("Replace the episodes database with a backup")
+                        .arg(
+                            clap::Arg::with_name("backup")
+                                .required(true)
+                                .value_name("BACKUP")
+                                .help("A path to the backup file"),
+                        ),
                 ),
         )
         .arg(
@@ -171,7 +254,11 @@ fn main() {
         };
 
         data_dir.push("episodes");
+        if let ("db", Some(args)) = cli_args.subcommand() {
+            return run_db(&data_dir, args);
+        }
         let mut data_provider = SqliteDataProvider::connect(&data_dir)?;
+        let database_path = data_dir.clone();
         data_dir.pop();
 
         let mut environment = AppEnvironment::new_with_data_path(data_dir);
@@ -201,8 +288,12 @@ fn main() {
 
         match cli_args.subcommand() {
             ("export", Some(args)) => run_export(&mut data_provider, args),
-            ("import", Some(args)) => run_import(&mut data_provider, args),
-            _ => run_player(data_provider, &cli_args, environment),
+            ("export-bookmarks", Some(args)) => run_export_bookmarks(&mut data_provider, args),
+            ("stats", Some(_)) => run_stats(&mut data_provider),
+            ("import", Some(args)) => run_import(data_provider, args),
+            ("backup", Some(args)) => run_backup(&mut data_provider, args),
+            ("restore", Some(args)) => run_restore(&mut data_provider, args),
+            _ => run_player(data_provider, database_path, &cli_args, environment),
         }
     })();
 
@@ -231,14 +322,122 @@ fn run_export<D: DataProvider>(
     Ok(())
 }
 
-fn run_import<P: DataProvider>(
+fn run_export_bookmarks<D: DataProvider>(
+    data_provider: &mut D,
+    args: &ArgMatches,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let output = args.value_of("output");
+    match output {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .truncate(true)
+                .write(true)
+                .open(path)?;
+            bookmarks::build_markdown(file, data_provider)?;
+        }
+        None => bookmarks::build_markdown(io::stdout(), data_provider)?,
+    }
+    Ok(())
+}
+
+fn run_stats<D: DataProvider>(data_provider: &mut D) -> Result<(), Box<dyn std::error::Error>> {
+    stats::build_report(io::stdout(), data_provider)?;
+    Ok(())
+}
+
+fn read_opml<R: io::BufRead, P: DataProvider>(
+    reader: R,
+    data_provider: &mut P,
+    dry_run: bool,
+) -> Result<opml::ImportReport, opml::Error> {
+    match dry_run {
+        true => opml::check_opml_import(reader, data_provider),
+        false => opml::import_opml(reader, data_provider),
+    }
+}
+
+fn write_client_feeds<P: DataProvider>(
+    feeds: Vec<migration::ClientFeed>,
+    data_provider: &mut P,
+    dry_run: bool,
+) -> Result<opml::ImportReport, migration::Error> {
+    match dry_run {
+        true => migration::check_feeds_import(feeds, data_provider),
+        false => migration::import_feeds(feeds, data_provider),
+    }
+}
+
+fn run_import(
+    mut data_provider: SqliteDataProvider,
+    args: &ArgMatches,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let file = args.value_of("file").expect("arg is required");
+    let format = args.value_of("format").expect("arg has a default value");
+    let dry_run = args.is_present("dry_run");
+    let report = match (format, file) {
+        ("opml", "-") => read_opml(std::io::stdin().lock(), &mut data_provider, dry_run)?,
+        ("opml", url) if url.starts_with("http://") || url.starts_with("https://") => {
+            let opml = System::new().block_on(opml::download_opml(url))?;
+            read_opml(io::Cursor::new(opml), &mut data_provider, dry_run)?
+        }
+        ("opml", file) => read_opml(
+            BufReader::new(OpenOptions::new().read(true).open(file)?),
+            &mut data_provider,
+            dry_run,
+        )?,
+        ("newsboat", file) => {
+            let feeds = match file {
+                "-" => newsboat::read_feeds(std::io::stdin().lock())?,
+                file => newsboat::read_feeds(BufReader::new(
+                    OpenOptions::new().read(true).open(file)?,
+                ))?,
+            };
+            write_client_feeds(feeds, &mut data_provider, dry_run)?
+        }
+        (_, "-") => return Err(DatabaseFromStdin.into()),
+        (format, file) => {
+            let feeds = match format {
+                "gpodder" => gpodder::read_feeds(Path::new(file))?,
+                _ => antennapod::read_feeds(Path::new(file))?,
+            };
+            write_client_feeds(feeds, &mut data_provider, dry_run)?
+        }
+    };
+    import::print_report(&report, dry_run);
+    if args.is_present("update") {
+        import::update_feeds(data_provider, &report.added)?;
+    }
+    Ok(())
+}
+
+fn run_backup<D: DataProvider>(
+    data_provider: &mut D,
+    args: &ArgMatches,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let output = args.value_of("output");
+    match output {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .truncate(true)
+                .write(true)
+                .open(path)?;
+            backup::build_backup(file, data_provider)?;
+        }
+        None => backup::build_backup(io::stdout(), data_provider)?,
+    }
+    Ok(())
+}
+
+fn run_restore<P: DataProvider>(
     data_provider: &mut P,
     args: &ArgMatches,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let file = args.value_of("file").expect("arg is required");
     match file {
-        "-" => opml::import_opml(std::io::stdin().lock(), data_provider)?,
-        file => opml::import_opml(
+        "-" => backup::restore_backup(std::io::stdin().lock(), data_provider)?,
+        file => backup::restore_backup(
             BufReader::new(OpenOptions::new().read(true).open(file)?),
             data_provider,
         )?,
@@ -246,8 +445,19 @@ fn run_import<P: DataProvider>(
     Ok(())
 }
 
+fn run_db(path: &Path, args: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    if let ("restore", Some(args)) = args.subcommand() {
+        let backup = args.value_of("backup").expect("arg is required");
+        if let Some(error) = SqliteDataProvider::restore_backup(path, backup)? {
+            eprintln!("Warning: the current database was not backed up ({})", error);
+        }
+    }
+    Ok(())
+}
+
 fn run_player(
     data_provider: SqliteDataProvider,
+    database_path: PathBuf,
     args: &ArgMatches,
     env: AppEnvironment,
 ) -> Result<(), Box<dyn std::error::Error>> {
@@ -274,9 +484,18 @@ fn run_player(
     terminal.clear()?;
 
     system.block_on(async {
+        let (readers, readers_error) =
+            match DatabaseReader::start_pool(&database_path, DATABASE_READERS) {
+                Ok(readers) => (Some(readers), None),
+                Err(error) => (None, Some(error.to_string())),
+            };
         let library_arbiter = Arbiter::new();
         let library = Library::start_in_arbiter(&library_arbiter.handle(), |_| {
-            Library::new(InMemoryCache::new(data_provider))
+            let library = Library::new(InMemoryCache::new(data_provider));
+            match readers {
+                Some(readers) => library.with_readers(readers),
+                None => library,
+            }
         });
 
         let status_writer = StatusWriter::new(library.clone())
@@ -302,6 +521,9 @@ fn run_player(
         log::set_max_level(log::LevelFilter::Info);
         log::set_boxed_logger(Box::new(ActorLogger::new(ui_addr.recipient())))
             .expect("Logger cannot be set more then once");
+        if let Some(error) = readers_error {
+            log::warn!(target: "sql", "Database is read on a single connection, {}", error);
+        }
 
         if !args.is_present("no_mpris") {
             run_mpris(player, player_arbiter.handle());
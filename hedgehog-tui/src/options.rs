@@ -1,3 +1,6 @@
+use std::str::FromStr;
+use std::time::Duration;
+
 macro_rules! gen_options {
     ($($(#$attr:tt)* $command:ident($(#$arg_attr:tt)* $name:ident: $value:ty = $default:expr)),*$(,)?) => {
         pub(crate) struct Options {
@@ -40,6 +43,7 @@ gen_options! {
     LabelEpisodeFinished(label_episode_finished: String = " finished ".to_string()),
     LabelEpisodeError(label_episode_error: String = " error ".to_string()),
     LabelFeedError(label_feed_error: String = "E".to_string()),
+    LabelGroupCollapsed(label_group_collapsed: String = "+".to_string()),
     FeedUpdatingChars(
         #[cmd(parser = "cmdparse::parsers::TransformParser<cmdparse::parsers::StringParser, CharVecTransformation, Vec<char>>")]
         feed_updating_chars: Vec<char> = vec!['⠇', '⠋', '⠙', '⠸', '⢰', '⣠', '⣄', '⡆']
@@ -48,6 +52,12 @@ gen_options! {
     UpdateOnStart(update_on_start: bool = true),
     ShowEpisodeNumber(show_episode_number: bool = true),
     Hidden(hidden: bool = false),
+    Autoplay(autoplay: AutoplayMode = AutoplayMode::None),
+    FinishThreshold(finish_threshold: Option<Percentage> = None),
+    FinishRemaining(finish_remaining: Option<Seconds> = None),
+    TrashRetention(
+        trash_retention: Option<Seconds> = Some(Seconds(Duration::from_secs(30 * 86400)))
+    ),
     ProgressBarWidth(progress_bar_width: u16 = 32),
     ProgressBarChars(
         #[cmd(parser = "cmdparse::parsers::TransformParser<cmdparse::parsers::StringParser, CharVecTransformation, Vec<char>>")]
@@ -61,6 +71,57 @@ impl OptionsUpdate {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, cmdparse::Parsable)]
+pub(crate) enum AutoplayMode {
+    Next,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Percentage(pub(crate) f64);
+
+impl FromStr for Percentage {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value: f64 = s.strip_suffix('%').unwrap_or(s).parse().map_err(|_| ())?;
+        if (0.0..=100.0).contains(&value) {
+            Ok(Percentage(value))
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl<Ctx> cmdparse::Parsable<Ctx> for Percentage {
+    type Parser = cmdparse::parsers::FromStrParser<Self>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Seconds(pub(crate) Duration);
+
+impl FromStr for Seconds {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, multiplier) = if let Some(value) = s.strip_suffix('d') {
+            (value, 86400)
+        } else if let Some(value) = s.strip_suffix('h') {
+            (value, 3600)
+        } else if let Some(value) = s.strip_suffix('m') {
+            (value, 60)
+        } else {
+            (s.strip_suffix('s').unwrap_or(s), 1)
+        };
+        let value: u64 = value.parse().map_err(|_| ())?;
+        Ok(Seconds(Duration::from_secs(value.checked_mul(multiplier).ok_or(())?)))
+    }
+}
+
+impl<Ctx> cmdparse::Parsable<Ctx> for Seconds {
+    type Parser = cmdparse::parsers::FromStrParser<Self>;
+}
+
 struct CharVecTransformation;
 
 impl cmdparse::parsers::ParsableTransformation<Vec<char>> for CharVecTransformation {
@@ -70,3 +131,27 @@ impl cmdparse::parsers::ParsableTransformation<Vec<char>> for CharVecTransformat
         Ok(input.chars().collect())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Percentage, Seconds};
+    use std::time::Duration;
+
+    #[test]
+    fn parsing_percentage() {
+        assert_eq!("95%".parse::<Percentage>(), Ok(Percentage(95.0)));
+        assert_eq!("97.5".parse::<Percentage>(), Ok(Percentage(97.5)));
+        assert_eq!("101%".parse::<Percentage>(), Err(()));
+        assert_eq!("%".parse::<Percentage>(), Err(()));
+    }
+
+    #[test]
+    fn parsing_seconds() {
+        assert_eq!("60s".parse::<Seconds>(), Ok(Seconds(Duration::from_secs(60))));
+        assert_eq!("45".parse::<Seconds>(), Ok(Seconds(Duration::from_secs(45))));
+        assert_eq!("2m".parse::<Seconds>(), Ok(Seconds(Duration::from_secs(120))));
+        assert_eq!("7d".parse::<Seconds>(), Ok(Seconds(Duration::from_secs(604800))));
+        assert_eq!("-5s".parse::<Seconds>(), Err(()));
+        assert_eq!("300000000000000d".parse::<Seconds>(), Err(()));
+    }
+}
@@ -1,43 +1,51 @@
 use crate::cmdcontext::CommandContext;
 use crate::cmdreader::CommandReader;
 use crate::command::{
-    Command, CommandConfirmation, FocusedPane, LinkType, Predicate, SelectedItem,
+    Command, CommandConfirmation, FocusedPane, LinkType, Predicate, QueueCommand, SelectedItem,
+    ViewCommand,
 };
 use crate::events::key;
 use crate::history::CommandsHistory;
 use crate::keymap::KeyMapping;
 use crate::logger::{log_set_level, LogEntry, LogHistory, Severity};
 use crate::mouse::{MouseEventKind, MouseHitResult, MouseState, WidgetPositions};
-use crate::options::Options;
+use crate::options::{AutoplayMode, Options, Seconds};
 use crate::scrolling::pagination::{DataProvider, PaginatedData};
 use crate::scrolling::{selection, DataView, ScrollAction, ScrollableList};
 use crate::theming::Theme;
 use crate::widgets::animation::AnimationController;
+use crate::widgets::bookmarks::BookmarksWidget;
 use crate::widgets::command::{CommandActionResult, CommandEditor, CommandState};
 use crate::widgets::confirmation::ConfirmationView;
 use crate::widgets::errors_log::ErrorsLogWidget;
 use crate::widgets::library::LibraryWidget;
 use crate::widgets::player_state::PlayerState;
+use crate::widgets::queue::QueueWidget;
 use crate::widgets::search_results::SearchResults;
+use crate::widgets::stats::StatsWidget;
 use crate::widgets::split_bottom;
 use crate::widgets::status::LogEntryView;
+use crate::widgets::trash::TrashWidget;
 use actix::clock::sleep;
 use actix::fut::wrap_future;
 use actix::prelude::*;
+use chrono::Utc;
 use crossterm::event::{self, Event};
 use crossterm::QueueableCommand;
 use hedgehog_library::model::{
-    Episode, EpisodeId, EpisodePlaybackData, EpisodeSummary, EpisodeSummaryStatus,
+    Bookmark, Episode, EpisodeId, EpisodePlaybackData, EpisodeSummary, EpisodeSummaryStatus,
     EpisodesListMetadata, Feed, FeedId, FeedSummary, FeedView, GroupId, GroupSummary, Identifiable,
+    ListeningStats, TagTarget, TrashedFeed, ViewId, ViewSummary,
 };
 use hedgehog_library::search::{self, SearchClient, SearchResult};
 use hedgehog_library::status_writer::{self, StatusWriter, StatusWriterCommand};
 use hedgehog_library::{
-    EpisodePlaybackDataRequest, EpisodeSummariesRequest, EpisodesListMetadataRequest,
-    EpisodesQuery, FeedSummariesRequest, FeedSummariesResponse, FeedUpdateNotification,
-    FeedUpdateRequest, FeedUpdateResult, Library, NewFeedMetadata, UpdateQuery,
+    BookmarksRequest, EpisodePlaybackDataRequest, EpisodeSummariesRequest,
+    EpisodesListMetadataRequest, EpisodesQuery, FeedSummariesRequest, FeedSummariesResponse,
+    FeedUpdateNotification, FeedUpdateRequest, FeedUpdateResult, Library, ListeningStatsRequest,
+    NewFeedMetadata, NextEpisodeRequest, QueryError, QueueRequest, TrashRequest, UpdateQuery,
 };
-use hedgehog_player::state::PlaybackState;
+use hedgehog_player::state::{PlaybackState, PlaybackStatus, PlaybackTiming};
 use hedgehog_player::{
     InitialPlaybackState, PlaybackCommand, PlaybackMetadata, Player, PlayerNotification,
     SeekDirection, SeekOffset,
@@ -56,10 +64,17 @@ pub(crate) enum SearchState {
 }
 
 pub(crate) struct LibraryViewModel {
-    pub(crate) feeds: ScrollableList<Vec<FeedView<FeedSummary, GroupSummary>>>,
+    pub(crate) feeds: ScrollableList<Vec<FeedView<FeedSummary, GroupSummary, ViewSummary>>>,
     pub(crate) feeds_loaded: bool,
+    pub(crate) feeds_error: Option<QueryError>,
     pub(crate) episodes: ScrollableList<PaginatedData<EpisodeSummary>>,
     pub(crate) episodes_list_metadata: Option<EpisodesListMetadata>,
+    pub(crate) episodes_error: Option<QueryError>,
+    pub(crate) queue: ScrollableList<Vec<EpisodeSummary>>,
+    pub(crate) bookmarks: ScrollableList<Vec<Bookmark>>,
+    pub(crate) trash: ScrollableList<Vec<TrashedFeed>>,
+    pub(crate) tags: Vec<String>,
+    pub(crate) stats: Option<ListeningStats>,
     pub(crate) search: SearchState,
     pub(crate) focus: FocusedPane,
     pub(crate) updating_feeds: HashSet<FeedId>,
@@ -71,8 +86,15 @@ impl LibraryViewModel {
         LibraryViewModel {
             feeds: ScrollableList::new(Vec::new(), window_size, 3),
             feeds_loaded: false,
+            feeds_error: None,
             episodes: ScrollableList::new(PaginatedData::new(), window_size, 3),
             episodes_list_metadata: None,
+            episodes_error: None,
+            queue: ScrollableList::new(Vec::new(), window_size, 3),
+            bookmarks: ScrollableList::new(Vec::new(), window_size, 3),
+            trash: ScrollableList::new(Vec::new(), window_size, 3),
+            tags: Vec::new(),
+            stats: None,
             search: SearchState::Loading,
             focus: FocusedPane::FeedsList,
             playing_episode: None,
@@ -83,6 +105,9 @@ impl LibraryViewModel {
     fn set_window_size(&mut self, window_size: usize) {
         self.episodes.set_window_size(window_size);
         self.feeds.set_window_size(window_size);
+        self.queue.set_window_size(window_size);
+        self.bookmarks.set_window_size(window_size);
+        self.trash.set_window_size(window_size);
     }
 }
 
@@ -108,8 +133,15 @@ pub(crate) struct UI {
     theme: Theme,
     key_mapping: KeyMapping<Command>,
     library: LibraryViewModel,
-    selected_feed: Option<FeedView<FeedId, GroupId>>,
+    selected_feed: Option<FeedView<FeedId, GroupId, ViewId>>,
+    // Incremented every time the list is requested, so that responses to the older requests,
+    // which may be served by another reader after the newer ones, are dropped
+    feeds_request: u64,
+    episodes_request: u64,
     playback_state: PlaybackState,
+    playback_list_query: Option<EpisodesQuery>,
+    playback_rate: f64,
+    listening_episode: Option<EpisodeId>,
 
     previous_command: Option<Command>,
     log_history: ScrollableList<LogHistory>,
@@ -146,7 +178,12 @@ impl UI {
             key_mapping: KeyMapping::default(),
             library: LibraryViewModel::new(size.1.saturating_sub(2) as usize),
             selected_feed: None,
+            feeds_request: 0,
+            episodes_request: 0,
             playback_state: PlaybackState::default(),
+            playback_list_query: None,
+            playback_rate: 1.0,
+            listening_episode: None,
 
             previous_command: None,
             rendering_suspended: false,
@@ -186,6 +223,23 @@ impl UI {
                     );
                     f.render_widget(library_widget, area);
                 }
+                FocusedPane::Queue => {
+                    let widget = QueueWidget::new(&self.library.queue, &self.options, &self.theme)
+                        .playing(self.library.playing_episode.as_ref().map(|episode| episode.id));
+                    f.render_widget(widget, area);
+                }
+                FocusedPane::Bookmarks => {
+                    let widget = BookmarksWidget::new(&self.library.bookmarks, &self.theme);
+                    f.render_widget(widget, area);
+                }
+                FocusedPane::Trash => {
+                    let widget = TrashWidget::new(&self.library.trash, &self.options, &self.theme);
+                    f.render_widget(widget, area);
+                }
+                FocusedPane::Stats => {
+                    let widget = StatsWidget::new(self.library.stats.as_ref(), &self.theme);
+                    f.render_widget(widget, area);
+                }
                 FocusedPane::Search => {
                     self.layout.set_search_list(area);
                     let widget = SearchResults::new(&self.library.search, &self.theme);
@@ -292,16 +346,34 @@ impl UI {
         episode_id: EpisodeId,
         initial_state: InitialPlaybackState,
         ctx: &mut <Self as Actor>::Context,
+    ) {
+        self.start_playback_at(episode_id, None, initial_state, ctx);
+    }
+
+    fn start_playback_at(
+        &mut self,
+        episode_id: EpisodeId,
+        position: Option<Duration>,
+        initial_state: InitialPlaybackState,
+        ctx: &mut <Self as Actor>::Context,
     ) {
         let future = wrap_future(
             self.library_actor
                 .send(EpisodePlaybackDataRequest(episode_id)),
         )
         .map(move |result, actor: &mut UI, ctx| match result {
-            Ok(Some(playback_data)) => {
+            Ok(Ok(Some(mut playback_data))) => {
+                let resume_position = playback_data.position.max(playback_data.skip_intro);
+                playback_data.position = position.unwrap_or(resume_position);
+                let playing_id = actor.library.playing_episode.as_ref().map(|episode| episode.id);
+                if playing_id != Some(episode_id) {
+                    actor.finish_if_past_threshold();
+                }
                 actor.library.playing_episode = Some(playback_data.clone());
                 actor.playback_state =
                     PlaybackState::new_started(playback_data.position, playback_data.duration);
+                actor.playback_rate = playback_data.playback_rate.unwrap_or(1.0);
+                actor.update_listening_session(true);
                 actor
                     .player_actor
                     .do_send(hedgehog_player::PlaybackCommand::Play(
@@ -314,6 +386,14 @@ impl UI {
                         }),
                         initial_state,
                     ));
+                if let Some(rate) = playback_data.playback_rate {
+                    actor.player_actor.do_send(PlaybackCommand::SetRate(rate));
+                }
+                if !playback_data.skip_outro.is_zero() {
+                    actor
+                        .player_actor
+                        .do_send(PlaybackCommand::SetOutro(playback_data.skip_outro));
+                }
                 actor
                     .library
                     .episodes
@@ -327,10 +407,97 @@ impl UI {
                     });
                 actor.invalidate(ctx);
             }
-            Ok(None) => {}
-            Err(error) => {
-                log::error!(target: "actix", "{}", error);
+            Ok(Ok(None)) => {}
+            Ok(Err(error)) => log::error!(target: "sql", "{}", error),
+            Err(error) => log::error!(target: "actix", "{}", error),
+        });
+        ctx.spawn(future);
+    }
+
+    fn mark_finished(&mut self, episode_id: EpisodeId) {
+        self.status_writer_actor
+            .do_send(StatusWriterCommand::set_finished(episode_id));
+        self.library
+            .episodes
+            .update_data::<selection::DoNotUpdate, _>(|data, _| {
+                let episode = data
+                    .find(|item| item.id == episode_id)
+                    .and_then(|index| data.item_at_mut(index));
+                if let Some(episode) = episode {
+                    episode.status = EpisodeSummaryStatus::Finished;
+                }
+            });
+    }
+
+    fn finish_if_past_threshold(&mut self) {
+        let playing_id = match self.library.playing_episode {
+            Some(ref episode) => episode.id,
+            None => return,
+        };
+        let (position, duration) = match self.playback_state.timing() {
+            Some(PlaybackTiming {
+                position,
+                duration: Some(duration),
+            }) if !duration.is_zero() => (position, duration),
+            _ => return,
+        };
+
+        let past_threshold = (self.options.finish_threshold).map_or(false, |threshold| {
+            position.as_secs_f64() >= duration.as_secs_f64() * threshold.0 / 100.0
+        });
+        let past_remaining = (self.options.finish_remaining)
+            .map_or(false, |remaining| duration.saturating_sub(position) <= remaining.0);
+        if past_threshold || past_remaining {
+            self.mark_finished(playing_id);
+        }
+    }
+
+    fn update_listening_session(&mut self, restart: bool) {
+        let listening = match self.playback_state.status() {
+            PlaybackStatus::Playing | PlaybackStatus::Buffering => {
+                self.library.playing_episode.as_ref().map(|episode| episode.id)
+            }
+            PlaybackStatus::Paused | PlaybackStatus::None => None,
+        };
+        if listening == self.listening_episode && !(restart && listening.is_some()) {
+            return;
+        }
+
+        if self.listening_episode.is_some() {
+            self.status_writer_actor
+                .do_send(StatusWriterCommand::EndSession);
+        }
+        if let Some(episode_id) = listening {
+            let position = (self.playback_state.timing())
+                .map(|timing| timing.position)
+                .unwrap_or_default();
+            self.status_writer_actor
+                .do_send(StatusWriterCommand::StartSession(
+                    episode_id,
+                    position,
+                    self.playback_rate,
+                ));
+        }
+        self.listening_episode = listening;
+    }
+
+    fn play_next_in_list(
+        &mut self,
+        query: EpisodesQuery,
+        episode_id: EpisodeId,
+        ctx: &mut <Self as Actor>::Context,
+    ) {
+        let future = wrap_future(
+            self.library_actor
+                .send(NextEpisodeRequest(query, episode_id)),
+        )
+        .map(move |result, actor: &mut UI, ctx| match result {
+            Ok(Ok(Some(next_episode_id))) => {
+                actor.start_playback(next_episode_id, InitialPlaybackState::Playing, ctx);
             }
+            Ok(Ok(None)) => {}
+            Ok(Err(error)) => log::error!(target: "sql", "{}", error),
+            Err(error) => log::error!(target: "actix", "{}", error),
         });
         ctx.spawn(future);
     }
@@ -344,6 +511,10 @@ impl UI {
                         self.update_current_feed(ctx);
                     }
                     FocusedPane::EpisodesList => self.library.episodes.scroll(command),
+                    FocusedPane::Queue => self.library.queue.scroll(command),
+                    FocusedPane::Bookmarks => self.library.bookmarks.scroll(command),
+                    FocusedPane::Trash => self.library.trash.scroll(command),
+                    FocusedPane::Stats => {}
                     FocusedPane::Search => {
                         if let SearchState::Loaded(list) = &mut self.library.search {
                             list.scroll(command);
@@ -354,12 +525,24 @@ impl UI {
                 self.invalidate_later(ctx);
             }
             Command::SetFocus(focused_pane) => {
+                match focused_pane {
+                    FocusedPane::Stats => self.load_stats(ctx),
+                    FocusedPane::Trash => self.load_trash(ctx),
+                    _ => {}
+                }
                 if self.library.focus != focused_pane {
                     self.library.focus = focused_pane;
                     self.invalidate(ctx);
                 }
             }
-            Command::Quit => System::current().stop(),
+            Command::Quit => {
+                let session_ended = self
+                    .status_writer_actor
+                    .send(StatusWriterCommand::EndSession);
+                ctx.spawn(wrap_future(session_ended).map(|_, _actor: &mut UI, _ctx| {
+                    System::current().stop();
+                }));
+            }
             Command::Map(key, command) => {
                 let redefined = self.key_mapping.contains(key);
                 self.key_mapping.map(key, *command);
@@ -397,6 +580,7 @@ impl UI {
                     loop {
                         let command_context = CommandContext {
                             feeds: self.library.feeds.data(),
+                            tags: &self.library.tags,
                         };
                         match reader.read(command_context) {
                             Ok(None) => break,
@@ -422,9 +606,27 @@ impl UI {
                 self.confirmation = Some(*confirmation);
                 self.invalidate(ctx);
             }
+            Command::PlayCurrent if self.library.focus == FocusedPane::Bookmarks => {
+                let (episode_id, position) = match self.library.bookmarks.selection() {
+                    Some(bookmark) => (bookmark.episode_id, bookmark.position),
+                    None => return true,
+                };
+                let playing_id = self.library.playing_episode.as_ref().map(|episode| episode.id);
+                if playing_id == Some(episode_id) {
+                    self.player_actor.do_send(PlaybackCommand::Seek(position));
+                } else {
+                    self.playback_list_query = None;
+                    self.invalidate_later(ctx);
+                    self.start_playback_at(
+                        episode_id,
+                        Some(position),
+                        InitialPlaybackState::Playing,
+                        ctx,
+                    );
+                }
+            }
             Command::PlayCurrent => {
-                let episode_id = if let Some(current_episode) = self.library.episodes.selection() {
-                    let episode_id = current_episode.id;
+                let episode_id = if let Some(episode_id) = self.selected_episode_id() {
                     if Some(episode_id)
                         == self
                             .library
@@ -442,25 +644,35 @@ impl UI {
                 } else {
                     return true;
                 };
+                if self.library.focus == FocusedPane::Queue {
+                    self.library_actor
+                        .do_send(FeedUpdateRequest::Dequeue(episode_id));
+                    self.playback_list_query = None;
+                } else {
+                    let reversed_order = (self.library.episodes_list_metadata.as_ref())
+                        .map(|metadata| metadata.reversed_order)
+                        .unwrap_or(false);
+                    self.playback_list_query = self
+                        .selected_feed_query()
+                        .map(|query| query.reversed_order(reversed_order));
+                }
                 self.invalidate_later(ctx);
                 self.start_playback(episode_id, InitialPlaybackState::Playing, ctx);
             }
-            Command::Playback(command) => self.player_actor.do_send(command),
+            Command::Playback(command) => {
+                if let PlaybackCommand::SetRate(rate) = command {
+                    if self.library.playing_episode.is_some() {
+                        self.playback_rate = rate;
+                        self.update_listening_session(true);
+                    }
+                }
+                self.player_actor.do_send(command);
+            }
             Command::Finish => {
-                if let Some(playing) = &self.library.playing_episode {
+                let playing_id = self.library.playing_episode.as_ref().map(|episode| episode.id);
+                if let Some(playing_id) = playing_id {
                     self.player_actor.do_send(PlaybackCommand::Stop);
-                    self.status_writer_actor
-                        .do_send(StatusWriterCommand::set_finished(playing.id));
-                    self.library
-                        .episodes
-                        .update_data::<selection::DoNotUpdate, _>(|data, _| {
-                            let episode = data
-                                .find(|item| item.id == playing.id)
-                                .and_then(|index| data.item_at_mut(index));
-                            if let Some(episode) = episode {
-                                episode.status = EpisodeSummaryStatus::Finished;
-                            }
-                        });
+                    self.mark_finished(playing_id);
                 }
             }
             Command::Volume(command) => self.player_actor.do_send(command),
@@ -470,11 +682,101 @@ impl UI {
             Command::AddGroup(name) => self
                 .library_actor
                 .do_send(FeedUpdateRequest::AddGroup(name)),
+            Command::View(ViewCommand::Add {
+                name,
+                group,
+                status,
+                unfinished,
+                days,
+                tag,
+            }) => {
+                let mut query = EpisodesQuery::default();
+                if let Some(group) = group {
+                    let group_id = (self.library.feeds.data().iter())
+                        .filter_map(|entry| entry.as_group())
+                        .find(|entry| entry.name == group)
+                        .map(|entry| entry.id);
+                    match group_id {
+                        Some(group_id) => query = query.group_id(group_id),
+                        None => {
+                            log::error!("Cannot find a group with this name");
+                            return false;
+                        }
+                    }
+                }
+                if let Some(status) = status {
+                    query = query.status(status);
+                }
+                if unfinished {
+                    query = query.excluded_status(EpisodeSummaryStatus::Finished);
+                }
+                if let Some(days) = days {
+                    query = query.published_within(Duration::from_secs(days * 24 * 60 * 60));
+                }
+                if let Some(tag) = tag {
+                    query = query.tag(tag);
+                }
+                self.library_actor
+                    .do_send(FeedUpdateRequest::AddView(name, query));
+            }
+            Command::Queue(QueueCommand::Add) => {
+                if let Some(episode_id) = self.selected_episode_id() {
+                    self.library_actor
+                        .do_send(FeedUpdateRequest::Enqueue(episode_id));
+                }
+            }
+            Command::Queue(QueueCommand::Remove) => {
+                if let Some(episode_id) = self.selected_episode_id() {
+                    self.library_actor
+                        .do_send(FeedUpdateRequest::Dequeue(episode_id));
+                }
+            }
+            Command::Queue(QueueCommand::Move(position)) => {
+                let episode_id = match self.selected_episode_id() {
+                    Some(episode_id) => episode_id,
+                    None => return true,
+                };
+                if !self.library.queue.data().iter().any(|item| item.id == episode_id) {
+                    log::error!("This episode is not in the queue");
+                    return false;
+                }
+                self.library_actor
+                    .do_send(FeedUpdateRequest::SetQueuePosition(episode_id, position));
+            }
+            Command::Queue(QueueCommand::Clear) => {
+                self.library_actor.do_send(FeedUpdateRequest::ClearQueue);
+            }
+            Command::Bookmark(note) => {
+                let playing_id = self.library.playing_episode.as_ref().map(|episode| episode.id);
+                let position = self.playback_state.timing().map(|timing| timing.position);
+                match (playing_id, position) {
+                    (Some(episode_id), Some(position)) => {
+                        self.library_actor.do_send(FeedUpdateRequest::AddBookmark(
+                            episode_id, position, note,
+                        ));
+                        log::info!("Bookmark added");
+                    }
+                    _ => {
+                        log::error!("Nothing is playing");
+                        return false;
+                    }
+                }
+            }
+            Command::DeleteBookmark => {
+                if self.library.focus != FocusedPane::Bookmarks {
+                    log::error!("Select a bookmark to delete in the bookmarks pane");
+                    return false;
+                }
+                if let Some(bookmark) = self.library.bookmarks.selection() {
+                    self.library_actor
+                        .do_send(FeedUpdateRequest::DeleteBookmark(bookmark.id));
+                }
+            }
             Command::SetGroup(name) => {
-                let feed_id = match self.selected_feed {
-                    Some(FeedView::Feed(feed_id)) => feed_id,
+                let selected = match self.selected_feed {
+                    Some(selected @ (FeedView::Feed(_) | FeedView::Group(_))) => selected,
                     Some(_) => {
-                        log::error!("Group can be set only for individual podcasts");
+                        log::error!("Group can be set only for individual podcasts and groups");
                         return false;
                     }
                     None => return true,
@@ -492,28 +794,59 @@ impl UI {
                     }
                 };
 
-                self.library_actor
-                    .do_send(FeedUpdateRequest::SetGroup(Some(group_id), feed_id));
+                let request = match selected {
+                    FeedView::Group(child_id) => {
+                        FeedUpdateRequest::SetGroupParent(child_id, Some(group_id))
+                    }
+                    FeedView::Feed(feed_id) => FeedUpdateRequest::SetGroup(Some(group_id), feed_id),
+                    _ => return false,
+                };
+                self.library_actor.do_send(request);
                 self.load_feeds(ctx);
             }
             Command::UnsetGroup => {
-                let feed_id = match self.selected_feed {
-                    Some(FeedView::Feed(feed_id)) => feed_id,
+                let request = match self.selected_feed {
+                    Some(FeedView::Feed(feed_id)) => FeedUpdateRequest::SetGroup(None, feed_id),
+                    Some(FeedView::Group(group_id)) => {
+                        FeedUpdateRequest::SetGroupParent(group_id, None)
+                    }
                     Some(_) => {
-                        log::error!("Select a podcast to remove from a group");
+                        log::error!("Select a podcast or a group to remove from a group");
                         return false;
                     }
                     None => return true,
                 };
 
-                self.library_actor
-                    .do_send(FeedUpdateRequest::SetGroup(None, feed_id));
+                self.library_actor.do_send(request);
                 self.load_feeds(ctx);
             }
+            Command::SetGroupCollapsed(collapsed) => match self.selected_group_state() {
+                Some((group_id, current)) => {
+                    if current != collapsed {
+                        self.set_group_collapsed(Some(group_id), collapsed, ctx);
+                    }
+                }
+                None => {
+                    log::warn!("Select a group to collapse or expand");
+                    return false;
+                }
+            },
+            Command::ToggleGroup => match self.selected_group_state() {
+                Some((group_id, collapsed)) => {
+                    self.set_group_collapsed(Some(group_id), !collapsed, ctx);
+                }
+                None => {
+                    log::warn!("Select a group to collapse or expand");
+                    return false;
+                }
+            },
+            Command::SetAllGroupsCollapsed(collapsed) => {
+                self.set_group_collapsed(None, collapsed, ctx);
+            }
             Command::PlaceGroup(position) => {
                 for index in (0..=self.library.feeds.selected_index()).rev() {
                     match self.library.feeds.data().get(index) {
-                        Some(FeedView::All | FeedView::New) => {
+                        Some(FeedView::All | FeedView::New | FeedView::View(_)) => {
                             log::error!("Select the group to change its position");
                             return false;
                         }
@@ -535,14 +868,70 @@ impl UI {
                 Some(FeedView::Feed(selected_feed)) => {
                     self.library_actor
                         .do_send(FeedUpdateRequest::DeleteFeed(selected_feed.id));
+                    log::info!("Feed moved to the trash");
                 }
                 Some(FeedView::Group(selected_group)) => {
                     self.library_actor
                         .do_send(FeedUpdateRequest::DeleteGroup(selected_group.id));
                     self.load_feeds(ctx);
                 }
+                Some(FeedView::View(selected_view)) => {
+                    self.library_actor
+                        .do_send(FeedUpdateRequest::DeleteView(selected_view.id));
+                    self.load_feeds(ctx);
+                }
                 _ => {}
             },
+            Command::Undo => self.library_actor.do_send(FeedUpdateRequest::Undo),
+            Command::Redo => self.library_actor.do_send(FeedUpdateRequest::Redo),
+            Command::MergeFeed(name) => {
+                let feed_id = match self.selected_feed {
+                    Some(FeedView::Feed(feed_id)) => feed_id,
+                    Some(_) => {
+                        log::error!("Only individual podcasts can be merged");
+                        return false;
+                    }
+                    None => return true,
+                };
+                let targets: Vec<FeedId> = (self.library.feeds.data().iter())
+                    .filter_map(FeedView::as_feed)
+                    .filter(|feed| feed.title == name && feed.id != feed_id)
+                    .map(|feed| feed.id)
+                    .collect();
+                let target_id = match targets.as_slice() {
+                    [target_id] => *target_id,
+                    [] => {
+                        log::error!("Cannot find a feed with this name");
+                        return false;
+                    }
+                    _ => {
+                        log::error!(
+                            "Several feeds are named \"{}\", rename the target feed first",
+                            name
+                        );
+                        return false;
+                    }
+                };
+                self.library_actor
+                    .do_send(FeedUpdateRequest::MergeFeed(feed_id, target_id));
+                self.load_feeds(ctx);
+                self.load_trash(ctx);
+            }
+            Command::Restore => {
+                if self.library.focus != FocusedPane::Trash {
+                    log::error!("Select a feed to restore in the trash pane");
+                    return false;
+                }
+                if let Some(feed) = self.library.trash.selection() {
+                    self.library_actor.do_send(FeedUpdateRequest::RestoreFeed(feed.id));
+                    self.load_feeds(ctx);
+                    self.load_trash(ctx);
+                }
+            }
+            Command::EmptyTrash => {
+                self.library_actor.do_send(FeedUpdateRequest::PurgeTrash(None));
+                self.load_trash(ctx);
+            }
             Command::Update { current_only } => {
                 let query = if current_only {
                     self.selected_feed
@@ -562,6 +951,15 @@ impl UI {
                         .do_send(FeedUpdateRequest::AddArchive(feed_id, feed_url));
                 }
             }
+            Command::SetSource(source) => match self.selected_feed {
+                Some(FeedView::Feed(feed_id)) => {
+                    self.library_actor.do_send(FeedUpdateRequest::SetSource(feed_id, source));
+                }
+                _ => {
+                    log::error!("Select a feed to change its source");
+                    return false;
+                }
+            },
             Command::SetOption(options_update) => {
                 let affects_episodes_list = options_update.affects_episodes_list();
                 self.options.update(options_update);
@@ -582,7 +980,7 @@ impl UI {
                 condition,
             } => {
                 if update_all {
-                    if let Some(feed) = self.selected_feed {
+                    if let Some(query) = self.selected_feed_query() {
                         self.library
                             .episodes
                             .update_data::<selection::DoNotUpdate, _>(|data, _| {
@@ -593,7 +991,7 @@ impl UI {
                                 }
                             });
 
-                        let mut query = EpisodesQuery::from_feed_view(feed);
+                        let mut query = query;
                         if let Some(condition) = condition {
                             query = query.status(condition);
                         }
@@ -647,6 +1045,7 @@ impl UI {
                     });
                 self.selected_feed = None;
                 self.library.episodes_list_metadata = None;
+                self.library.episodes_error = None;
                 self.load_feeds(ctx);
                 self.invalidate(ctx);
             }
@@ -663,6 +1062,48 @@ impl UI {
                     self.refresh_episodes(ctx, false);
                 }
             }
+            Command::FeedRate(rate) => {
+                if let Some(FeedView::Feed(feed_id)) = self.selected_feed {
+                    self.library_actor
+                        .do_send(FeedUpdateRequest::SetFeedPlaybackRate(feed_id, rate));
+                } else {
+                    log::warn!("Playback rate can only be set for individual podcasts");
+                }
+            }
+            Command::SkipIntro(Seconds(duration)) => {
+                if let Some(FeedView::Feed(feed_id)) = self.selected_feed {
+                    self.library_actor
+                        .do_send(FeedUpdateRequest::SetFeedSkipIntro(feed_id, duration));
+                } else {
+                    log::warn!("Intro can only be skipped for individual podcasts");
+                }
+            }
+            Command::SkipOutro(Seconds(duration)) => {
+                if let Some(FeedView::Feed(feed_id)) = self.selected_feed {
+                    self.library_actor
+                        .do_send(FeedUpdateRequest::SetFeedSkipOutro(feed_id, duration));
+                } else {
+                    log::warn!("Outro can only be skipped for individual podcasts");
+                }
+            }
+            Command::Tag(name) => match self.tag_target() {
+                Some(target) => self
+                    .library_actor
+                    .do_send(FeedUpdateRequest::AddTag(target, name)),
+                None => {
+                    log::warn!("Tags can only be set for episodes and individual podcasts");
+                    return false;
+                }
+            },
+            Command::Untag(name) => match self.tag_target() {
+                Some(target) => self
+                    .library_actor
+                    .do_send(FeedUpdateRequest::RemoveTag(target, name)),
+                None => {
+                    log::warn!("Tags can only be removed from episodes and individual podcasts");
+                    return false;
+                }
+            },
             Command::Reverse => {
                 match self
                     .selected_feed
@@ -697,6 +1138,15 @@ impl UI {
                             data[selection].as_group_mut().unwrap().name = name;
                         });
                 }
+                Some(FeedView::View(view_id)) => {
+                    self.library_actor
+                        .do_send(FeedUpdateRequest::RenameView(view_id, name.clone()));
+                    self.library
+                        .feeds
+                        .update_data::<selection::Keep, _>(|data, selection| {
+                            data[selection].as_view_mut().unwrap().name = name;
+                        });
+                }
                 Some(_) => log::warn!("Only individual podcasts can be renamed"),
                 None => {
                     log::warn!("Nothing to rename");
@@ -711,12 +1161,13 @@ impl UI {
                         )
                         .map(move |result, actor: &mut UI, _ctx| {
                             match result {
-                                Ok(Some(Feed {
+                                Ok(Ok(Some(Feed {
                                     link: Some(link), ..
-                                })) => {
+                                }))) => {
                                     actor.open_browser(&link);
                                 }
-                                Ok(_) => {}
+                                Ok(Ok(_)) => {}
+                                Ok(Err(error)) => log::error!(target: "sql", "{}", error),
                                 Err(error) => log::error!(target: "actix", "{}", error),
                             }
                         }),
@@ -734,12 +1185,13 @@ impl UI {
                         )
                         .map(move |result, actor: &mut UI, _ctx| {
                             match result {
-                                Ok(Some(Episode {
+                                Ok(Ok(Some(Episode {
                                     link: Some(link), ..
-                                })) => {
+                                }))) => {
                                     actor.open_browser(&link);
                                 }
-                                Ok(_) => {}
+                                Ok(Ok(_)) => {}
+                                Ok(Err(error)) => log::error!(target: "sql", "{}", error),
                                 Err(error) => log::error!(target: "actix", "{}", error),
                             }
                         }),
@@ -798,12 +1250,22 @@ impl UI {
                 Some(FeedView::All | FeedView::New) => SelectedItem::SpecialFeed,
                 Some(FeedView::Feed(_)) => SelectedItem::Feed,
                 Some(FeedView::Group(_)) => SelectedItem::Group,
+                Some(FeedView::View(_)) => SelectedItem::View,
                 None => SelectedItem::Nothing,
             },
             FocusedPane::EpisodesList if self.library.episodes.data().is_empty() => {
                 SelectedItem::Nothing
             }
             FocusedPane::EpisodesList => SelectedItem::Episode,
+            FocusedPane::Queue if self.library.queue.data().is_empty() => SelectedItem::Nothing,
+            FocusedPane::Queue => SelectedItem::QueuedEpisode,
+            FocusedPane::Bookmarks if self.library.bookmarks.data().is_empty() => {
+                SelectedItem::Nothing
+            }
+            FocusedPane::Bookmarks => SelectedItem::Bookmark,
+            FocusedPane::Trash if self.library.trash.data().is_empty() => SelectedItem::Nothing,
+            FocusedPane::Trash => SelectedItem::TrashedFeed,
+            FocusedPane::Stats => SelectedItem::Nothing,
             FocusedPane::Search if matches!(&self.library.search, SearchState::Loaded(data) if !data.data().is_empty()) => {
                 SelectedItem::SearchResult
             }
@@ -813,6 +1275,47 @@ impl UI {
         }
     }
 
+    fn selected_episode_id(&self) -> Option<EpisodeId> {
+        match self.library.focus {
+            FocusedPane::Queue => self.library.queue.selection().map(|episode| episode.id),
+            FocusedPane::Bookmarks => (self.library.bookmarks.selection())
+                .map(|bookmark| bookmark.episode_id),
+            _ => self.library.episodes.selection().map(|episode| episode.id),
+        }
+    }
+
+    fn selected_group_state(&self) -> Option<(GroupId, bool)> {
+        let group_id = match self.library.feeds.selection()? {
+            FeedView::Group(group) => return Some((group.id, group.collapsed)),
+            FeedView::Feed(feed) => feed.group_id?,
+            _ => return None,
+        };
+        (self.library.feeds.data().iter())
+            .filter_map(FeedView::as_group)
+            .find(|group| group.id == group_id)
+            .map(|group| (group.id, group.collapsed))
+    }
+
+    fn set_group_collapsed(
+        &mut self,
+        group_id: Option<GroupId>,
+        collapsed: bool,
+        ctx: &mut <UI as Actor>::Context,
+    ) {
+        self.library_actor
+            .do_send(FeedUpdateRequest::SetGroupCollapsed(group_id, collapsed));
+        self.load_feeds(ctx);
+    }
+
+    fn tag_target(&self) -> Option<TagTarget> {
+        match self.library.focus {
+            FocusedPane::EpisodesList | FocusedPane::Queue | FocusedPane::Bookmarks => {
+                self.selected_episode_id().map(TagTarget::Episode)
+            }
+            _ => self.selected_feed.and_then(|feed| feed.as_feed().cloned().map(TagTarget::Feed)),
+        }
+    }
+
     fn open_browser(&mut self, url: &str) {
         log::info!(target: "browser", "Opening '{}'", url);
         if let Err(error) = webbrowser::open(url) {
@@ -841,11 +1344,38 @@ impl UI {
             } else {
                 UpdateQuery::Pending
             }));
+        if let Some(Seconds(retention)) = self.options.trash_retention {
+            let purge_before = (chrono::Duration::from_std(retention).ok())
+                .and_then(|retention| Utc::now().checked_sub_signed(retention));
+            match purge_before {
+                Some(purge_before) => self
+                    .library_actor
+                    .do_send(FeedUpdateRequest::PurgeTrash(Some(purge_before))),
+                None => log::warn!("Trash retention is too long, the trash is not purged"),
+            }
+        }
+    }
+
+    fn selected_feed_query(&self) -> Option<EpisodesQuery> {
+        let selected_feed = self.selected_feed?;
+        let feed_view = match selected_feed {
+            FeedView::All => FeedView::All,
+            FeedView::New => FeedView::New,
+            FeedView::Feed(feed_id) => FeedView::Feed(feed_id),
+            FeedView::Group(group_id) => FeedView::Group(group_id),
+            FeedView::View(view_id) => {
+                let view = (self.library.feeds.data().iter())
+                    .filter_map(|entry| entry.as_view())
+                    .find(|view| view.id == view_id)?;
+                FeedView::View(view.query.clone())
+            }
+        };
+        Some(EpisodesQuery::from_feed_view(feed_view))
     }
 
     fn refresh_episodes(&mut self, ctx: &mut <UI as Actor>::Context, replace_current: bool) {
-        let feed_id = match self.selected_feed {
-            Some(feed_id) => feed_id,
+        let query = match self.selected_feed_query() {
+            Some(query) => query,
             None => return,
         };
         self.library
@@ -859,9 +1389,12 @@ impl UI {
             });
         if replace_current {
             self.library.episodes_list_metadata = None;
+            self.library.episodes_error = None;
         }
 
-        let query = EpisodesQuery::from_feed_view(feed_id).with_hidden(self.options.hidden);
+        let query = query.with_hidden(self.options.hidden);
+        self.episodes_request = self.episodes_request.wrapping_add(1);
+        let request = self.episodes_request;
         let address = ctx.address();
         let future = wrap_future(
             self.library_actor
@@ -869,7 +1402,7 @@ impl UI {
         )
         .then(|result, actor: &mut UI, _ctx| {
             let result = match result {
-                Ok(metadata) => {
+                Ok(Ok(metadata)) => {
                     let range = actor.library.episodes.data().initial_range(
                         metadata.items_count,
                         actor.library.episodes.viewport().range(),
@@ -877,9 +1410,11 @@ impl UI {
                     let new_provider = EpisodesListProvider {
                         query: query.clone().reversed_order(metadata.reversed_order),
                         actor: address,
+                        request,
                     };
-                    Some((metadata, range, new_provider))
+                    Some(Ok((metadata, range, new_provider)))
                 }
+                Ok(Err(error)) => Some(Err(error)),
                 Err(error) => {
                     log::error!(target: "actix", "{}", error);
                     None
@@ -889,18 +1424,22 @@ impl UI {
             wrap_future(async move {
                 match result {
                     None => None,
-                    Some((metadata, None, provider)) => Some((metadata, None, provider)),
-                    Some((metadata, Some(range), provider)) => {
+                    Some(Err(error)) => Some(Err(error)),
+                    Some(Ok((metadata, None, provider))) => Some(Ok((metadata, None, provider))),
+                    Some(Ok((metadata, Some(range), provider))) => {
                         let query = query.clone().reversed_order(metadata.reversed_order);
                         let episodes = library_actor
                             .send(EpisodeSummariesRequest::new(query, range.clone()))
                             .await;
-                        Some((metadata, Some((range, episodes)), provider))
+                        Some(Ok((metadata, Some((range, episodes)), provider)))
                     }
                 }
             })
         })
         .map(move |result, actor: &mut UI, ctx| {
+            if actor.episodes_request != request {
+                return;
+            }
             macro_rules! update_data {
                 ($fn:expr) => {{
                     let episodes = &mut actor.library.episodes;
@@ -911,34 +1450,49 @@ impl UI {
                     }
                 }};
             }
-            if let Some((metadata, episodes, new_provider)) = result {
-                let items_count = metadata.items_count;
-                actor.library.episodes_list_metadata = Some(metadata);
-                match episodes {
-                    Some((range, episodes)) => match episodes {
-                        Ok(episodes) => {
+            match result {
+                Some(Ok((metadata, episodes, new_provider))) => {
+                    let items_count = metadata.items_count;
+                    actor.library.episodes_list_metadata = Some(metadata);
+                    match episodes {
+                        Some((range, episodes)) => match episodes {
+                            Ok(Ok(episodes)) => {
+                                actor.library.episodes_error = None;
+                                update_data!(|data, _| {
+                                    data.set_provider(new_provider);
+                                    data.set_initial(items_count, episodes, range);
+                                });
+                            }
+                            Ok(Err(error)) => actor.set_episodes_error(error),
+                            Err(error) => {
+                                log::error!(target: "actix", "{}", error);
+                            }
+                        },
+                        None => {
+                            actor.library.episodes_error = None;
                             update_data!(|data, _| {
                                 data.set_provider(new_provider);
-                                data.set_initial(items_count, episodes, range);
+                                data.clear();
                             });
                         }
-                        Err(error) => {
-                            log::error!(target: "actix", "{}", error);
-                        }
-                    },
-                    None => {
-                        update_data!(|data, _| {
-                            data.set_provider(new_provider);
-                            data.clear();
-                        });
                     }
+                    actor.invalidate(ctx);
                 }
-                actor.invalidate(ctx);
+                Some(Err(error)) => {
+                    actor.set_episodes_error(error);
+                    actor.invalidate(ctx);
+                }
+                None => {}
             }
         });
         ctx.spawn(future);
     }
 
+    fn set_episodes_error(&mut self, error: QueryError) {
+        log::error!(target: "sql", "cannot load episodes, {}", error);
+        self.library.episodes_error = Some(error);
+    }
+
     fn update_current_feed(&mut self, ctx: &mut <UI as Actor>::Context) {
         let selected_id = self.library.feeds.selection().map(|item| item.id());
         if selected_id == self.selected_feed {
@@ -956,6 +1510,7 @@ impl UI {
                     data.clear_provider();
                 });
             self.library.episodes_list_metadata = None;
+            self.library.episodes_error = None;
         }
         self.invalidate_later(ctx);
     }
@@ -990,25 +1545,128 @@ impl UI {
         }
     }
 
+    fn load_queue(&mut self, ctx: &mut <UI as Actor>::Context) {
+        ctx.spawn(wrap_future(self.library_actor.send(QueueRequest)).map(
+            move |data, actor: &mut UI, ctx| match data {
+                Ok(Ok(queue)) => {
+                    actor
+                        .library
+                        .queue
+                        .update_data::<selection::Reset, _>(|data, _| *data = queue);
+                    actor.invalidate(ctx);
+                }
+                Ok(Err(error)) => {
+                    log::error!(target: "sql", "cannot load queue, {}", error);
+                }
+                Err(error) => {
+                    log::error!(target: "actix", "{}", error);
+                }
+            },
+        ));
+    }
+
+    fn load_bookmarks(&mut self, ctx: &mut <UI as Actor>::Context) {
+        ctx.spawn(wrap_future(self.library_actor.send(BookmarksRequest)).map(
+            move |data, actor: &mut UI, ctx| match data {
+                Ok(Ok(bookmarks)) => {
+                    actor
+                        .library
+                        .bookmarks
+                        .update_data::<selection::Reset, _>(|data, _| *data = bookmarks);
+                    actor.invalidate(ctx);
+                }
+                Ok(Err(error)) => {
+                    log::error!(target: "sql", "cannot load bookmarks, {}", error);
+                }
+                Err(error) => {
+                    log::error!(target: "actix", "{}", error);
+                }
+            },
+        ));
+    }
+
+    fn load_trash(&mut self, ctx: &mut <UI as Actor>::Context) {
+        ctx.spawn(wrap_future(self.library_actor.send(TrashRequest)).map(
+            move |data, actor: &mut UI, ctx| match data {
+                Ok(Ok(feeds)) => {
+                    actor
+                        .library
+                        .trash
+                        .update_data::<selection::FindPrevious<selection::Keep>, _>(|data, _| {
+                            *data = feeds;
+                        });
+                    actor.invalidate(ctx);
+                }
+                Ok(Err(error)) => {
+                    log::error!(target: "sql", "cannot load trash, {}", error);
+                }
+                Err(error) => {
+                    log::error!(target: "actix", "{}", error);
+                }
+            },
+        ));
+    }
+
+    fn load_stats(&mut self, ctx: &mut <UI as Actor>::Context) {
+        ctx.spawn(wrap_future(self.library_actor.send(ListeningStatsRequest)).map(
+            move |data, actor: &mut UI, ctx| match data {
+                Ok(Ok(stats)) => {
+                    actor.library.stats = Some(stats);
+                    actor.invalidate(ctx);
+                }
+                Ok(Err(error)) => {
+                    log::error!(target: "sql", "cannot load listening statistics, {}", error);
+                }
+                Err(error) => {
+                    log::error!(target: "actix", "{}", error);
+                }
+            },
+        ));
+    }
+
     fn load_feeds(&mut self, ctx: &mut <UI as Actor>::Context) {
+        self.feeds_request = self.feeds_request.wrapping_add(1);
+        let request = self.feeds_request;
         ctx.spawn(
             wrap_future(self.library_actor.send(FeedSummariesRequest)).map(
                 move |data, actor: &mut UI, ctx| match data {
-                    Ok(FeedSummariesResponse { feeds, groups }) => {
+                    _ if actor.feeds_request != request => {}
+                    Ok(Ok(FeedSummariesResponse {
+                        feeds,
+                        groups,
+                        views,
+                        tags,
+                    })) => {
+                        actor.library.feeds_error = None;
+                        actor.library.tags = tags;
                         actor
                             .library
                             .feeds
                             .update_data::<selection::FindPrevious, _>(|current_feeds, _| {
-                                let mut feed_views =
-                                    Vec::with_capacity(feeds.len() + groups.len() + 2);
+                                let mut feed_views = Vec::with_capacity(
+                                    feeds.len() + groups.len() + views.len() + 2,
+                                );
                                 feed_views.push(FeedView::All);
                                 feed_views.push(FeedView::New);
+                                feed_views.extend(views.into_iter().map(FeedView::View));
 
                                 let mut feeds_iter = feeds.into_iter().peekable();
+                                let mut collapsed_depth = None;
                                 for group in once(None).chain(groups.into_iter().map(Some)) {
                                     let group_id = group.as_ref().map(|group| group.id);
+                                    let mut visible = true;
                                     if let Some(group) = group {
-                                        feed_views.push(FeedView::Group(group));
+                                        let depth = group.depth;
+                                        if collapsed_depth.map_or(false, |parent| depth > parent) {
+                                            visible = false;
+                                        } else {
+                                            collapsed_depth = None;
+                                            if group.collapsed {
+                                                collapsed_depth = Some(depth);
+                                                visible = false;
+                                            }
+                                            feed_views.push(FeedView::Group(group));
+                                        }
                                     }
 
                                     let group_feeds =
@@ -1016,7 +1674,9 @@ impl UI {
                                             feed.group_id == group_id
                                         });
                                     for feed in group_feeds {
-                                        feed_views.push(FeedView::Feed(feed));
+                                        if visible {
+                                            feed_views.push(FeedView::Feed(feed));
+                                        }
                                     }
                                 }
 
@@ -1026,6 +1686,11 @@ impl UI {
                         actor.library.feeds_loaded = true;
                         actor.invalidate(ctx);
                     }
+                    Ok(Err(error)) => {
+                        log::error!(target: "sql", "cannot load podcasts, {}", error);
+                        actor.library.feeds_error = Some(error);
+                        actor.invalidate(ctx);
+                    }
                     Err(error) => {
                         log::error!(target: "error", "{}", error);
                     }
@@ -1040,6 +1705,8 @@ impl Actor for UI {
 
     fn started(&mut self, ctx: &mut Self::Context) {
         self.load_feeds(ctx);
+        self.load_queue(ctx);
+        self.load_bookmarks(ctx);
 
         self.player_actor
             .do_send(hedgehog_player::ActorCommand::Subscribe(
@@ -1185,6 +1852,11 @@ impl StreamHandler<crossterm::Result<event::Event>> for UI {
                                     self.library.focus = FocusedPane::FeedsList;
                                     self.library.feeds.scroll(ScrollAction::MoveToVisible(row));
                                     self.update_current_feed(ctx);
+                                    let clicked = self.library.feeds.has_item_at_window_row(row);
+                                    let selected = self.library.feeds.selection();
+                                    if clicked && matches!(selected, Some(FeedView::Group(_))) {
+                                        self.handle_command(Command::ToggleGroup, ctx);
+                                    }
                                 }
                                 MouseHitResult::EpisodesRow(row) => {
                                     self.library.focus = FocusedPane::EpisodesList;
@@ -1273,6 +1945,7 @@ impl StreamHandler<crossterm::Result<event::Event>> for UI {
                     CommandActionResult::Complete => {
                         let command_context = CommandContext {
                             feeds: self.library.feeds.data(),
+                            tags: &self.library.tags,
                         };
                         let command_str =
                             command_state.as_str_before_cursor(&self.commands_history);
@@ -1293,6 +1966,7 @@ impl StreamHandler<crossterm::Result<event::Event>> for UI {
                             &command_str,
                             CommandContext {
                                 feeds: self.library.feeds.data(),
+                                tags: &self.library.tags,
                             },
                         ) {
                             Ok(Some(command)) => {
@@ -1315,19 +1989,24 @@ impl StreamHandler<crossterm::Result<event::Event>> for UI {
 pub(crate) struct EpisodesListProvider {
     query: EpisodesQuery,
     actor: Addr<UI>,
+    request: u64,
 }
 
 impl DataProvider for EpisodesListProvider {
     fn request(&self, range: std::ops::Range<usize>) {
         self.actor
-            .do_send(DataFetchingRequest::Episodes(self.query.clone(), range));
+            .do_send(DataFetchingRequest::Episodes(
+                self.query.clone(),
+                range,
+                self.request,
+            ));
     }
 }
 
 #[derive(Debug, Message)]
 #[rtype(result = "()")]
 enum DataFetchingRequest {
-    Episodes(EpisodesQuery, Range<usize>),
+    Episodes(EpisodesQuery, Range<usize>, u64),
 }
 
 impl Handler<DataFetchingRequest> for UI {
@@ -1335,11 +2014,12 @@ impl Handler<DataFetchingRequest> for UI {
 
     fn handle(&mut self, msg: DataFetchingRequest, _ctx: &mut Self::Context) -> Self::Result {
         match msg {
-            DataFetchingRequest::Episodes(query, range) => {
-                let request = EpisodeSummariesRequest::new(query, range.clone());
-                Box::pin(wrap_future(self.library_actor.send(request)).map(
+            DataFetchingRequest::Episodes(query, range, request) => {
+                let summaries_request = EpisodeSummariesRequest::new(query, range.clone());
+                Box::pin(wrap_future(self.library_actor.send(summaries_request)).map(
                     move |data, actor: &mut UI, ctx| match data {
-                        Ok(episodes) => {
+                        _ if actor.episodes_request != request => {}
+                        Ok(Ok(episodes)) => {
                             actor
                                 .library
                                 .episodes
@@ -1348,6 +2028,10 @@ impl Handler<DataFetchingRequest> for UI {
                                 });
                             actor.invalidate(ctx);
                         }
+                        Ok(Err(error)) => {
+                            actor.set_episodes_error(error);
+                            actor.invalidate(ctx);
+                        }
                         Err(error) => log::error!(target: "actix", "{}", error),
                     },
                 ))
@@ -1368,7 +2052,11 @@ impl Handler<PlayerNotification> for UI {
                 }
             },
             PlayerNotification::StateChanged(state) => {
+                if state.is_none() {
+                    self.finish_if_past_threshold();
+                }
                 self.playback_state.set_state(state);
+                self.update_listening_session(false);
                 if state.is_none() {
                     self.library.playing_episode.take();
                     self.status_writer_actor
@@ -1380,7 +2068,11 @@ impl Handler<PlayerNotification> for UI {
                 self.playback_state.set_duration(duration);
                 self.invalidate(ctx);
             }
-            PlayerNotification::PositionSet { position, .. } => {
+            PlayerNotification::PositionSet { position, seeked } => {
+                self.playback_state.set_position(position);
+                if seeked {
+                    self.update_listening_session(true);
+                }
                 if let Some(playing_episode) = &self.library.playing_episode {
                     self.status_writer_actor
                         .do_send(StatusWriterCommand::set_position(
@@ -1388,27 +2080,33 @@ impl Handler<PlayerNotification> for UI {
                             position,
                         ));
                 }
-                self.playback_state.set_position(position);
                 self.invalidate(ctx);
             }
             PlayerNotification::Eos => {
-                if let Some(playing_episode) = &self.library.playing_episode {
-                    self.status_writer_actor
-                        .do_send(StatusWriterCommand::set_finished(playing_episode.id));
-                    self.library
-                        .episodes
-                        .update_data::<selection::DoNotUpdate, _>(|data, _| {
-                            let episode = data
-                                .find(|item| item.id == playing_episode.id)
-                                .and_then(|index| data.item_at_mut(index));
-                            if let Some(episode) = episode {
-                                episode.status = EpisodeSummaryStatus::Finished;
-                            }
-                        });
+                let playing_id = self.library.playing_episode.as_ref().map(|episode| episode.id);
+                if let Some(playing_id) = playing_id {
+                    self.mark_finished(playing_id);
+                }
+
+                let next_episode = (self.library.queue.data().iter())
+                    .map(|episode| episode.id)
+                    .find(|episode_id| Some(*episode_id) != playing_id);
+                if let Some(episode_id) = next_episode {
+                    self.library_actor
+                        .do_send(FeedUpdateRequest::Dequeue(episode_id));
+                    self.playback_list_query = None;
+                    self.start_playback(episode_id, InitialPlaybackState::Playing, ctx);
+                } else if self.options.autoplay == AutoplayMode::Next {
+                    if let (Some(query), Some(playing_id)) =
+                        (self.playback_list_query.clone(), playing_id)
+                    {
+                        self.play_next_in_list(query, playing_id, ctx);
+                    }
                 }
             }
             PlayerNotification::Failure => {
                 if let Some(playing_episode) = self.library.playing_episode.take() {
+                    self.update_listening_session(false);
                     self.status_writer_actor
                         .do_send(StatusWriterCommand::set_error(
                             playing_episode.id,
@@ -1505,8 +2203,54 @@ impl Handler<FeedUpdateNotification> for UI {
                     });
                 self.update_current_feed(ctx);
             }
+            FeedUpdateNotification::ViewAdded(view) => {
+                self.library
+                    .feeds
+                    .update_data::<selection::Keep, _>(|feeds, _| {
+                        let index = feeds
+                            .iter()
+                            .rposition(|feed| matches!(feed, FeedView::New | FeedView::View(_)))
+                            .map(|index| index + 1)
+                            .unwrap_or(0);
+                        feeds.insert(index, FeedView::View(view));
+                    });
+                self.update_current_feed(ctx);
+            }
+            FeedUpdateNotification::QueueUpdated(queue) => {
+                self.library
+                    .queue
+                    .update_data::<selection::FindPrevious<selection::Keep>, _>(|data, _| {
+                        *data = queue;
+                    });
+            }
+            FeedUpdateNotification::BookmarksUpdated(bookmarks) => {
+                self.library
+                    .bookmarks
+                    .update_data::<selection::FindPrevious<selection::Keep>, _>(|data, _| {
+                        *data = bookmarks;
+                    });
+            }
+            FeedUpdateNotification::TagsUpdated(tags) => {
+                self.library.tags = tags;
+                if matches!(self.selected_feed, Some(FeedView::View(_))) {
+                    self.refresh_episodes(ctx, false);
+                }
+            }
+            FeedUpdateNotification::ChangesReplayed => {
+                self.load_feeds(ctx);
+                self.refresh_episodes(ctx, false);
+            }
+            FeedUpdateNotification::DatabaseChanged => {
+                self.load_feeds(ctx);
+                self.load_queue(ctx);
+                self.load_bookmarks(ctx);
+                if self.library.focus == FocusedPane::Trash {
+                    self.load_trash(ctx);
+                }
+                self.refresh_episodes(ctx, false);
+            }
             FeedUpdateNotification::NewCountUpdated(new_count) => {
-                if self.selected_feed == Some(FeedView::New) {
+                if matches!(self.selected_feed, Some(FeedView::New | FeedView::View(_))) {
                     self.refresh_episodes(ctx, false);
                 }
                 self.library
@@ -1520,6 +2264,10 @@ impl Handler<FeedUpdateNotification> for UI {
                             }
                         }
                     });
+                let feeds = self.library.feeds.data();
+                if feeds.iter().any(|item| item.as_group().is_some()) {
+                    self.load_feeds(ctx);
+                }
             }
         }
         self.invalidate(ctx);
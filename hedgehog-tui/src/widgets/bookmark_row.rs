@@ -0,0 +1,78 @@
+use super::layout::{split_left, split_right};
+use super::list::ListItemRenderingDelegate;
+use super::utils::DurationFormatter;
+use crate::theming::{self, Theme};
+use hedgehog_library::model::Bookmark;
+use tui::buffer::Buffer;
+use tui::layout::{Alignment, Rect};
+use tui::text::Span;
+use tui::widgets::{Paragraph, Widget};
+
+pub(crate) struct BookmarkRowRenderer<'t> {
+    theme: &'t Theme,
+}
+
+impl<'t> BookmarkRowRenderer<'t> {
+    const POSITION_WIDTH: u16 = 10;
+
+    pub(crate) fn new(theme: &'t Theme) -> Self {
+        BookmarkRowRenderer { theme }
+    }
+}
+
+impl<'t, 'a> ListItemRenderingDelegate<'a> for BookmarkRowRenderer<'t> {
+    type Item = (&'a Bookmark, bool);
+
+    fn render_item(&self, area: Rect, item: Self::Item, buf: &mut Buffer) {
+        let (item, selected) = item;
+        let item_selector = theming::ListItem {
+            selected,
+            focused: true,
+            missing_title: item.note.is_none(),
+            state: Some(theming::ListState::Episode),
+            ..Default::default()
+        };
+
+        let (position_area, area) = split_left(area, Self::POSITION_WIDTH);
+        let style = self.theme.get(theming::List::Item(
+            item_selector.with_column(theming::ListColumn::Duration),
+        ));
+        Paragraph::new(format!(" {} ", DurationFormatter(item.position)))
+            .style(style)
+            .alignment(Alignment::Right)
+            .render(position_area, buf);
+
+        let (note_area, episode_area) = split_right(area, area.width * 4 / 10);
+        let style = self.theme.get(theming::List::Item(
+            item_selector.with_column(theming::ListColumn::FeedTitle),
+        ));
+        buf.set_style(episode_area, style);
+        buf.set_span(
+            episode_area.x + 1,
+            episode_area.y,
+            &Span::raw(item.episode_title.as_deref().unwrap_or("Untitled")),
+            episode_area.width.saturating_sub(2),
+        );
+
+        let style = self.theme.get(theming::List::Item(
+            item_selector.with_column(theming::ListColumn::Title),
+        ));
+        buf.set_style(note_area, style);
+        buf.set_span(
+            note_area.x + 1,
+            note_area.y,
+            &Span::raw(item.note.as_deref().unwrap_or("No note")),
+            note_area.width.saturating_sub(2),
+        );
+    }
+
+    fn render_empty(&self, area: Rect, buf: &mut Buffer) {
+        let item_selector = theming::ListItem {
+            focused: true,
+            state: Some(theming::ListState::Episode),
+            ..Default::default()
+        };
+        let style = self.theme.get(theming::List::Item(item_selector));
+        buf.set_style(area, style);
+    }
+}
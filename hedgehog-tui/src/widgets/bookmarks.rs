@@ -0,0 +1,36 @@
+use super::{bookmark_row::BookmarkRowRenderer, empty::EmptyView, list::List};
+use crate::{scrolling::ScrollableList, theming};
+use hedgehog_library::model::Bookmark;
+use tui::widgets::Widget;
+
+pub(crate) struct BookmarksWidget<'a> {
+    bookmarks: &'a ScrollableList<Vec<Bookmark>>,
+    theme: &'a theming::Theme,
+}
+
+impl<'a> BookmarksWidget<'a> {
+    pub(crate) fn new(
+        bookmarks: &'a ScrollableList<Vec<Bookmark>>,
+        theme: &'a theming::Theme,
+    ) -> Self {
+        BookmarksWidget { bookmarks, theme }
+    }
+}
+
+impl<'a> Widget for BookmarksWidget<'a> {
+    fn render(self, area: tui::layout::Rect, buf: &mut tui::buffer::Buffer) {
+        if self.bookmarks.data().is_empty() {
+            EmptyView::new(self.theme)
+                .title("There are no bookmarks")
+                .subtitle("Type :bookmark <note><Enter> to bookmark the current position of the playing episode")
+                .focused(true)
+                .render(area, buf);
+        } else {
+            List::new(
+                BookmarkRowRenderer::new(self.theme),
+                self.bookmarks.visible_iter(),
+            )
+            .render(area, buf);
+        }
+    }
+}
@@ -2,8 +2,10 @@ use super::animation::{AnimationController, LoadingIndicator};
 use super::{layout::split_right, list::ListItemRenderingDelegate};
 use crate::options::Options;
 use crate::theming::{self, Theme};
-use hedgehog_library::model::{FeedId, FeedStatus, FeedSummary, FeedView, GroupSummary};
-use std::collections::HashSet;
+use hedgehog_library::model::{
+    FeedId, FeedStatus, FeedSummary, FeedView, GroupId, GroupSummary, ViewSummary,
+};
+use std::collections::{HashMap, HashSet};
 use tui::buffer::Buffer;
 use tui::layout::Rect;
 use tui::widgets::{Paragraph, Widget};
@@ -15,6 +17,7 @@ pub(crate) struct FeedsListRowRenderer<'t> {
     options: &'t Options,
     updating_feeds: &'t HashSet<FeedId>,
     playing_feed: Option<FeedId>,
+    group_depths: HashMap<GroupId, usize>,
     animation_controller: AnimationController,
 }
 
@@ -37,10 +40,23 @@ impl<'t> FeedsListRowRenderer<'t> {
             focused,
             updating_feeds,
             playing_feed: None,
+            group_depths: HashMap::new(),
             animation_controller,
         }
     }
 
+    pub(crate) fn groups<'a>(mut self, groups: impl IntoIterator<Item = &'a GroupSummary>) -> Self {
+        self.group_depths = (groups.into_iter())
+            .map(|group| (group.id, group.depth))
+            .collect();
+        self
+    }
+
+    fn indent(&self, group_id: Option<GroupId>) -> u16 {
+        let depth = group_id.and_then(|group_id| self.group_depths.get(&group_id));
+        depth.map(|depth| *depth as u16 * 2).unwrap_or(0)
+    }
+
     pub(crate) fn playing(mut self, playing: impl Into<Option<FeedId>>) -> Self {
         self.playing_feed = playing.into();
         self
@@ -58,13 +74,13 @@ impl<'t> FeedsListRowRenderer<'t> {
 }
 
 impl<'t, 'a> ListItemRenderingDelegate<'a> for FeedsListRowRenderer<'t> {
-    type Item = (&'a FeedView<FeedSummary, GroupSummary>, bool);
+    type Item = (&'a FeedView<FeedSummary, GroupSummary, ViewSummary>, bool);
 
     fn render_item(&self, mut area: Rect, item: Self::Item, buf: &mut tui::buffer::Buffer) {
         let (item, selected) = item;
 
         match item {
-            FeedView::All | FeedView::New | FeedView::Group(_) => {
+            FeedView::All | FeedView::New | FeedView::View(_) | FeedView::Group(_) => {
                 let item_selector = theming::ListItem {
                     selected,
                     focused: self.focused,
@@ -77,17 +93,38 @@ impl<'t, 'a> ListItemRenderingDelegate<'a> for FeedsListRowRenderer<'t> {
                 let style = self.theme.get(theming::List::Item(item_selector));
                 buf.set_style(area, style);
 
+                let indent = match item {
+                    FeedView::Group(group) => {
+                        if group.new_count > 0 {
+                            let formatted = format!(" {} ", group.new_count);
+                            let width = formatted.width();
+                            let (title_area, count_area) = split_right(area, width as u16);
+                            let style = self.theme.get(theming::List::Item(
+                                item_selector.with_column(theming::ListColumn::NewCount),
+                            ));
+                            buf.set_string(count_area.x, count_area.y, formatted, style);
+                            area = title_area;
+                        }
+                        if group.collapsed {
+                            let label = &self.options.label_group_collapsed;
+                            buf.set_string(area.x, area.y, label, style);
+                        }
+                        self.indent(Some(group.id))
+                    }
+                    _ => 0,
+                };
                 let paragraph = Paragraph::new(match item {
                     FeedView::All => "All episodes",
                     FeedView::New => "New",
+                    FeedView::View(view) => &view.name,
                     FeedView::Group(group) => &group.name,
                     FeedView::Feed(_) => unreachable!(),
                 });
                 paragraph.render(
                     Rect::new(
-                        area.x + 1,
+                        area.x + 1 + indent,
                         area.y,
-                        area.width.saturating_sub(2),
+                        area.width.saturating_sub(2 + indent),
                         area.height,
                     ),
                     buf,
@@ -124,12 +161,13 @@ impl<'t, 'a> ListItemRenderingDelegate<'a> for FeedsListRowRenderer<'t> {
                     item_selector.with_column(theming::ListColumn::Title),
                 ));
                 buf.set_style(area, style);
+                let indent = self.indent(item.group_id);
                 let paragraph = Paragraph::new(item.title.as_str());
                 paragraph.render(
                     Rect::new(
-                        area.x + 2,
+                        area.x + 2 + indent,
                         area.y,
-                        area.width.saturating_sub(3),
+                        area.width.saturating_sub(3 + indent),
                         area.height,
                     ),
                     buf,
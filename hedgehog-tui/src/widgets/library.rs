@@ -41,6 +41,17 @@ impl<'a> LibraryWidget<'a> {
 
 impl<'a> Widget for LibraryWidget<'a> {
     fn render(self, area: tui::layout::Rect, buf: &mut tui::buffer::Buffer) {
+        if let Some(error) = self.data.feeds_error.as_ref() {
+            let subtitle = format!("\n{}\n\nType :refresh<Enter> to try again.", error);
+            EmptyView::new(self.theme)
+                .title("Could not load the library")
+                .subtitle(&subtitle)
+                .focused(true)
+                .render(area, buf);
+
+            return;
+        }
+
         if self.data.feeds.data().size() == 2 && self.data.feeds_loaded {
             EmptyView::new(self.theme)
                 .title("Hedgehog Podcast Player")
@@ -78,12 +89,20 @@ impl<'a> Widget for LibraryWidget<'a> {
                     .playing_episode
                     .as_ref()
                     .map(|episode| episode.feed_id),
-            ),
+            )
+            .groups(self.data.feeds.data().iter().filter_map(FeedView::as_group)),
             self.data.feeds.visible_iter(),
         )
         .render(feeds_area, buf);
 
-        if let Some(metadata) = self.data.episodes_list_metadata.as_ref() {
+        if let Some(error) = self.data.episodes_error.as_ref() {
+            let subtitle = format!("\n{}\n\nType :refresh<Enter> to try again.", error);
+            EmptyView::new(self.theme)
+                .title("Could not load episodes")
+                .subtitle(&subtitle)
+                .focused(self.data.focus == FocusedPane::EpisodesList)
+                .render(layout[1], buf);
+        } else if let Some(metadata) = self.data.episodes_list_metadata.as_ref() {
             let selected_feed_index = self.data.feeds.viewport().selected_index();
             let state = self
                 .data
@@ -95,6 +114,12 @@ impl<'a> Widget for LibraryWidget<'a> {
             if self.data.episodes.data().size() == 0 {
                 match state {
                     Some(FeedView::All | FeedView::Group(_)) => {}
+                    Some(FeedView::View(_)) => {
+                        EmptyView::new(self.theme)
+                            .title("No episodes match this view")
+                            .focused(self.data.focus == FocusedPane::EpisodesList)
+                            .render(layout[1], buf);
+                    }
                     Some(FeedView::New) => {
                         EmptyView::new(self.theme)
                             .title("There are no new episodes.")
@@ -130,7 +155,7 @@ impl<'a> Widget for LibraryWidget<'a> {
                 }
             } else {
                 let mut sizing = EpisodesListSizing::compute(self.options, metadata);
-                if matches!(state, Some(FeedView::All | FeedView::New))
+                if matches!(state, Some(FeedView::All | FeedView::New | FeedView::View(_)))
                     || !self.options.show_episode_number
                 {
                     sizing.hide_episode_numbers();
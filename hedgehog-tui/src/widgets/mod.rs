@@ -1,4 +1,6 @@
 pub(crate) mod animation;
+pub(crate) mod bookmark_row;
+pub(crate) mod bookmarks;
 pub(crate) mod command;
 pub(crate) mod confirmation;
 pub(crate) mod empty;
@@ -11,10 +13,14 @@ pub(crate) mod library;
 pub(crate) mod list;
 pub(crate) mod player_state;
 mod progressbar;
+pub(crate) mod queue;
 pub(crate) mod search_results;
 pub(crate) mod search_row;
+pub(crate) mod stats;
 pub(crate) mod status;
 pub(crate) mod textentry;
+pub(crate) mod trash;
+pub(crate) mod trash_row;
 mod utils;
 
 pub(crate) use layout::split_bottom;
@@ -0,0 +1,65 @@
+use super::empty::EmptyView;
+use super::episode_row::{EpisodesListRowRenderer, EpisodesListSizing};
+use super::list::List;
+use crate::options::Options;
+use crate::scrolling::ScrollableList;
+use crate::theming;
+use hedgehog_library::model::{EpisodeId, EpisodeSummary, EpisodesListMetadata};
+use tui::widgets::Widget;
+
+pub(crate) struct QueueWidget<'a> {
+    queue: &'a ScrollableList<Vec<EpisodeSummary>>,
+    options: &'a Options,
+    theme: &'a theming::Theme,
+    playing_id: Option<EpisodeId>,
+}
+
+impl<'a> QueueWidget<'a> {
+    pub(crate) fn new(
+        queue: &'a ScrollableList<Vec<EpisodeSummary>>,
+        options: &'a Options,
+        theme: &'a theming::Theme,
+    ) -> Self {
+        QueueWidget {
+            queue,
+            options,
+            theme,
+            playing_id: None,
+        }
+    }
+
+    pub(crate) fn playing(mut self, playing_id: impl Into<Option<EpisodeId>>) -> Self {
+        self.playing_id = playing_id.into();
+        self
+    }
+}
+
+impl<'a> Widget for QueueWidget<'a> {
+    fn render(self, area: tui::layout::Rect, buf: &mut tui::buffer::Buffer) {
+        let episodes = self.queue.data();
+        if episodes.is_empty() {
+            EmptyView::new(self.theme)
+                .title("The queue is empty")
+                .subtitle("Type :queue add<Enter> to add the selected episode to the queue")
+                .focused(true)
+                .render(area, buf);
+            return;
+        }
+
+        let metadata = EpisodesListMetadata {
+            items_count: episodes.len(),
+            max_duration: episodes.iter().filter_map(|episode| episode.duration).max(),
+            has_publication_date: episodes
+                .iter()
+                .any(|episode| episode.publication_date.is_some()),
+            ..Default::default()
+        };
+        let sizing = EpisodesListSizing::compute(self.options, &metadata).with_width(area.width);
+        List::new(
+            EpisodesListRowRenderer::new(self.theme, true, self.options, sizing)
+                .with_playing_id(self.playing_id),
+            self.queue.visible_iter_partial(),
+        )
+        .render(area, buf);
+    }
+}
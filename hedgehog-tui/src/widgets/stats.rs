@@ -0,0 +1,54 @@
+use super::empty::EmptyView;
+use super::layout::{shrink_h, split_top};
+use crate::theming::{self, Theme};
+use hedgehog_library::model::ListeningStats;
+use hedgehog_library::stats::write_report;
+use tui::widgets::{Paragraph, Widget};
+
+pub(crate) struct StatsWidget<'a> {
+    stats: Option<&'a ListeningStats>,
+    theme: &'a Theme,
+}
+
+impl<'a> StatsWidget<'a> {
+    pub(crate) fn new(stats: Option<&'a ListeningStats>, theme: &'a Theme) -> Self {
+        StatsWidget { stats, theme }
+    }
+}
+
+impl<'a> Widget for StatsWidget<'a> {
+    fn render(self, area: tui::layout::Rect, buf: &mut tui::buffer::Buffer) {
+        let stats = match self.stats {
+            Some(stats) if stats.started_episodes > 0 => stats,
+            Some(_) => {
+                EmptyView::new(self.theme)
+                    .title("Nothing has been played yet")
+                    .subtitle("Listening statistics will appear here once you play an episode")
+                    .focused(true)
+                    .render(area, buf);
+                return;
+            }
+            None => {
+                EmptyView::new(self.theme)
+                    .title("Loading statistics...")
+                    .focused(true)
+                    .render(area, buf);
+                return;
+            }
+        };
+
+        let mut report = Vec::new();
+        if write_report(&mut report, stats).is_err() {
+            return;
+        }
+        let style = self.theme.get(theming::List::Item(theming::ListItem {
+            focused: true,
+            ..Default::default()
+        }));
+        buf.set_style(area, style);
+        let (_, area) = split_top(area, 1);
+        Paragraph::new(String::from_utf8_lossy(&report).into_owned())
+            .style(style)
+            .render(shrink_h(area, 2), buf);
+    }
+}
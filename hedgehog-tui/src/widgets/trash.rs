@@ -0,0 +1,43 @@
+use super::{empty::EmptyView, list::List, trash_row::TrashRowRenderer};
+use crate::options::Options;
+use crate::{scrolling::ScrollableList, theming};
+use hedgehog_library::model::TrashedFeed;
+use tui::widgets::Widget;
+
+pub(crate) struct TrashWidget<'a> {
+    feeds: &'a ScrollableList<Vec<TrashedFeed>>,
+    options: &'a Options,
+    theme: &'a theming::Theme,
+}
+
+impl<'a> TrashWidget<'a> {
+    pub(crate) fn new(
+        feeds: &'a ScrollableList<Vec<TrashedFeed>>,
+        options: &'a Options,
+        theme: &'a theming::Theme,
+    ) -> Self {
+        TrashWidget {
+            feeds,
+            options,
+            theme,
+        }
+    }
+}
+
+impl<'a> Widget for TrashWidget<'a> {
+    fn render(self, area: tui::layout::Rect, buf: &mut tui::buffer::Buffer) {
+        if self.feeds.data().is_empty() {
+            EmptyView::new(self.theme)
+                .title("The trash is empty")
+                .subtitle("Deleted feeds are kept here until they are restored or purged")
+                .focused(true)
+                .render(area, buf);
+        } else {
+            List::new(
+                TrashRowRenderer::new(self.theme, self.options),
+                self.feeds.visible_iter(),
+            )
+            .render(area, buf);
+        }
+    }
+}
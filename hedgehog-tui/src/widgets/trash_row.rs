@@ -0,0 +1,66 @@
+use super::layout::split_right;
+use super::list::ListItemRenderingDelegate;
+use crate::options::Options;
+use crate::theming::{self, Theme};
+use hedgehog_library::model::TrashedFeed;
+use tui::buffer::Buffer;
+use tui::layout::{Alignment, Rect};
+use tui::text::Span;
+use tui::widgets::{Paragraph, Widget};
+use unicode_width::UnicodeWidthStr;
+
+pub(crate) struct TrashRowRenderer<'t> {
+    theme: &'t Theme,
+    options: &'t Options,
+}
+
+impl<'t> TrashRowRenderer<'t> {
+    pub(crate) fn new(theme: &'t Theme, options: &'t Options) -> Self {
+        TrashRowRenderer { theme, options }
+    }
+}
+
+impl<'t, 'a> ListItemRenderingDelegate<'a> for TrashRowRenderer<'t> {
+    type Item = (&'a TrashedFeed, bool);
+
+    fn render_item(&self, area: Rect, item: Self::Item, buf: &mut Buffer) {
+        let (item, selected) = item;
+        let item_selector = theming::ListItem {
+            selected,
+            focused: true,
+            state: Some(theming::ListState::Feed),
+            ..Default::default()
+        };
+
+        let date = format!(" {} ", item.deleted_at.format(&self.options.date_format));
+        let (area, date_area) = split_right(area, date.width() as u16);
+        let style = self.theme.get(theming::List::Item(
+            item_selector.with_column(theming::ListColumn::Date),
+        ));
+        Paragraph::new(date)
+            .style(style)
+            .alignment(Alignment::Right)
+            .render(date_area, buf);
+
+        let style = self.theme.get(theming::List::Item(
+            item_selector.with_column(theming::ListColumn::Title),
+        ));
+        buf.set_style(area, style);
+        buf.set_span(
+            area.x + 1,
+            area.y,
+            &Span::raw(item.title.as_str()),
+            area.width.saturating_sub(2),
+        );
+    }
+
+    fn render_empty(&self, area: Rect, buf: &mut Buffer) {
+        let item_selector = theming::ListItem {
+            focused: true,
+            state: Some(theming::ListState::Feed),
+            ..Default::default()
+        };
+        let style = self.theme.get(theming::List::Item(item_selector));
+        buf.set_style(area, style);
+    }
+}